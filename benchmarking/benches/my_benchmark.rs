@@ -1,8 +1,16 @@
 use benchmarking::*;
+use core_sdk::board_representation::game_state::GameState;
 use core_sdk::evaluation::eval_game_state;
 use core_sdk::move_generation::makemove::make_move;
 use core_sdk::move_generation::movegen::{self, MoveList};
+use core_sdk::search::cache::Cache;
+use core_sdk::search::reserved_memory::ReservedMoveList;
+use core_sdk::search::searcher::InterThreadCommunicationSystem;
+use core_sdk::search::{MATE_SCORE, MAX_SEARCH_DEPTH};
+use core_sdk::{bench_search, perft, perft_make_unmake};
 use criterion::{criterion_group, criterion_main, Criterion};
+use std::sync::Arc;
+use std::time::Duration;
 
 pub fn evaluation_bench(c: &mut Criterion) {
     let states = load_benchmarking_positions();
@@ -35,5 +43,132 @@ pub fn generate_moves_bench(c: &mut Criterion) {
         })
     });
 }
-criterion_group!(benches, evaluation_bench, generate_moves_bench);
+
+pub const MAKE_BENCH_POSITIONS: usize = 20;
+pub const MAKE_BENCH_PERFT_DEPTH: usize = 3;
+pub const SEARCH_BENCH_POSITIONS: usize = 10;
+pub const SEARCH_BENCH_DEPTH: usize = 8;
+pub const SEARCH_WALK_DEPTH: usize = 3;
+
+pub fn perft_make_bench(c: &mut Criterion) {
+    let states = load_benchmarking_positions();
+    let mut movelist = ReservedMoveList::default();
+    let mut group = c.benchmark_group("perft");
+    group.sample_size(10);
+    group.bench_function("copy-make", |b| {
+        b.iter(|| {
+            states[..MAKE_BENCH_POSITIONS]
+                .iter()
+                .map(|state| perft(state, MAKE_BENCH_PERFT_DEPTH, &mut movelist))
+                .sum::<u64>()
+        })
+    });
+    group.bench_function("make-unmake", |b| {
+        b.iter(|| {
+            states[..MAKE_BENCH_POSITIONS]
+                .iter()
+                .map(|state| perft_make_unmake(&mut state.clone(), MAKE_BENCH_PERFT_DEPTH, &mut movelist))
+                .sum::<u64>()
+        })
+    });
+    group.finish();
+}
+
+//Alpha-beta to a fixed depth followed by a captures-only quiescence search, with evaluated leaves. The engine's search copies the
+//state for every move, so this walks trees of the same shape with both ways of updating the state.
+fn search_walk_copy_make(state: &GameState, depth: usize, ply: usize, mut alpha: i16, beta: i16, movelist: &mut ReservedMoveList) -> i16 {
+    let captures_only = depth == 0;
+    if captures_only {
+        let stand_pat = eval_game_state(state).final_eval * (1 - 2 * state.get_color_to_move() as i16);
+        if stand_pat >= beta || ply + 1 >= MAX_SEARCH_DEPTH {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+    }
+    movegen::generate_moves(state, captures_only, &mut movelist.move_lists[ply]);
+    let len = movelist.move_lists[ply].move_list.len();
+    if len == 0 && !captures_only {
+        return 0;
+    }
+    for i in 0..len {
+        let mv = movelist.move_lists[ply].move_list[i].0;
+        let score = -search_walk_copy_make(&make_move(state, mv), depth.saturating_sub(1), ply + 1, -beta, -alpha, movelist);
+        if score > alpha {
+            alpha = score;
+            if alpha >= beta {
+                break;
+            }
+        }
+    }
+    alpha
+}
+
+fn search_walk_make_unmake(state: &mut GameState, depth: usize, ply: usize, mut alpha: i16, beta: i16, movelist: &mut ReservedMoveList) -> i16 {
+    let captures_only = depth == 0;
+    if captures_only {
+        let stand_pat = eval_game_state(state).final_eval * (1 - 2 * state.get_color_to_move() as i16);
+        if stand_pat >= beta || ply + 1 >= MAX_SEARCH_DEPTH {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+    }
+    movegen::generate_moves(state, captures_only, &mut movelist.move_lists[ply]);
+    let len = movelist.move_lists[ply].move_list.len();
+    if len == 0 && !captures_only {
+        return 0;
+    }
+    for i in 0..len {
+        let mv = movelist.move_lists[ply].move_list[i].0;
+        let undo = state.make(mv);
+        let score = -search_walk_make_unmake(state, depth.saturating_sub(1), ply + 1, -beta, -alpha, movelist);
+        state.unmake(mv, undo);
+        if score > alpha {
+            alpha = score;
+            if alpha >= beta {
+                break;
+            }
+        }
+    }
+    alpha
+}
+
+//The engine's own search to a fixed depth on the bench positions, with the same setup as `bench`, next to the search walks
+//with copy-make and make/unmake on the same positions.
+pub fn search_bench(c: &mut Criterion) {
+    let itcs = Arc::new(InterThreadCommunicationSystem::default());
+    InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+    *itcs.cache() = Cache::with_size_threaded(8, 1);
+    itcs.uci_options.write().unwrap().quiet = true;
+    let positions = &core_sdk::BENCHMARKING_POSITIONS[..SEARCH_BENCH_POSITIONS];
+    let states = positions.iter().map(|fen| GameState::from_fen(fen)).collect::<Vec<GameState>>();
+    let mut movelist = ReservedMoveList::default();
+    for state in states.iter() {
+        assert_eq!(
+            search_walk_copy_make(state, SEARCH_WALK_DEPTH, 0, -MATE_SCORE, MATE_SCORE, &mut movelist),
+            search_walk_make_unmake(&mut state.clone(), SEARCH_WALK_DEPTH, 0, -MATE_SCORE, MATE_SCORE, &mut movelist)
+        );
+    }
+    let mut group = c.benchmark_group("search");
+    group.sample_size(10).measurement_time(Duration::from_secs(30));
+    group.bench_function("engine", |b| b.iter(|| bench_search(&itcs, SEARCH_BENCH_DEPTH, positions)));
+    group.bench_function("walk-copy-make", |b| {
+        b.iter(|| {
+            states
+                .iter()
+                .map(|state| search_walk_copy_make(state, SEARCH_WALK_DEPTH, 0, -MATE_SCORE, MATE_SCORE, &mut movelist) as isize)
+                .sum::<isize>()
+        })
+    });
+    group.bench_function("walk-make-unmake", |b| {
+        b.iter(|| {
+            states
+                .iter()
+                .map(|state| search_walk_make_unmake(&mut state.clone(), SEARCH_WALK_DEPTH, 0, -MATE_SCORE, MATE_SCORE, &mut movelist) as isize)
+                .sum::<isize>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, evaluation_bench, generate_moves_bench, perft_make_bench, search_bench);
 criterion_main!(benches);
@@ -0,0 +1,262 @@
+use crate::bitboards::bitboards::{east_one, forward_one, north_one, south_one, west_one};
+use crate::board_representation::color::Color;
+use crate::board_representation::game_state::PieceType;
+use crate::board_representation::square::Square;
+use std::fmt::{Debug, Display, Formatter, Result};
+use std::iter::FromIterator;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr};
+
+//A set of squares. Iterating a bitboard yields its squares from a1 to h8.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(transparent)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0u64);
+    pub const FULL: Bitboard = Bitboard(!0u64);
+
+    #[inline(always)]
+    pub const fn raw(self) -> u64 {
+        self.0
+    }
+    #[inline(always)]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0u64
+    }
+    #[inline(always)]
+    pub const fn popcount(self) -> u32 {
+        self.0.count_ones()
+    }
+    #[inline(always)]
+    pub const fn more_than_one(self) -> bool {
+        self.0 & self.0.wrapping_sub(1) != 0u64
+    }
+    #[inline(always)]
+    pub const fn contains(self, sq: Square) -> bool {
+        self.0 & sq.bitboard().0 != 0u64
+    }
+    #[inline(always)]
+    pub fn lsb(self) -> Option<Square> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(Square::new(self.0.trailing_zeros() as usize))
+        }
+    }
+    #[inline(always)]
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let sq = self.lsb();
+        self.0 &= self.0.wrapping_sub(1);
+        sq
+    }
+    #[inline(always)]
+    pub const fn north(self) -> Self {
+        Bitboard(north_one(self.0))
+    }
+    #[inline(always)]
+    pub const fn south(self) -> Self {
+        Bitboard(south_one(self.0))
+    }
+    #[inline(always)]
+    pub const fn east(self) -> Self {
+        Bitboard(east_one(self.0))
+    }
+    #[inline(always)]
+    pub const fn west(self) -> Self {
+        Bitboard(west_one(self.0))
+    }
+    //North for white, south for black
+    #[inline(always)]
+    pub const fn forward(self, color: Color) -> Self {
+        Bitboard(forward_one(self.0, color.index()))
+    }
+    //Squares attacked by a non-pawn piece on sq. Occ is ignored for kings and knights
+    #[inline(always)]
+    pub fn attacks(piece_type: PieceType, sq: Square, occ: Bitboard) -> Self {
+        Bitboard(piece_type.attacks(sq.index(), occ.0))
+    }
+    #[inline(always)]
+    pub fn iter(self) -> BitboardIter {
+        BitboardIter(self.0)
+    }
+}
+
+//Iterator over the squares of a bitboard. Kept separate from Bitboard so that iterating
+//never consumes a board the caller still holds.
+#[derive(Clone, Debug)]
+pub struct BitboardIter(u64);
+
+impl Iterator for BitboardIter {
+    type Item = Square;
+    #[inline(always)]
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0u64 {
+            None
+        } else {
+            let sq = Square::new(self.0.trailing_zeros() as usize);
+            self.0 &= self.0 - 1;
+            Some(sq)
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+impl ExactSizeIterator for BitboardIter {}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = BitboardIter;
+    #[inline(always)]
+    fn into_iter(self) -> BitboardIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(iter: I) -> Self {
+        iter.into_iter().fold(Bitboard::EMPTY, |bb, sq| bb | sq.bitboard())
+    }
+}
+
+impl From<u64> for Bitboard {
+    #[inline(always)]
+    fn from(bb: u64) -> Self {
+        Bitboard(bb)
+    }
+}
+impl From<Bitboard> for u64 {
+    #[inline(always)]
+    fn from(bb: Bitboard) -> Self {
+        bb.0
+    }
+}
+impl From<Square> for Bitboard {
+    #[inline(always)]
+    fn from(sq: Square) -> Self {
+        sq.bitboard()
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+    #[inline(always)]
+    fn bitand(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 & other.0)
+    }
+}
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+    #[inline(always)]
+    fn bitor(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 | other.0)
+    }
+}
+impl BitXor for Bitboard {
+    type Output = Bitboard;
+    #[inline(always)]
+    fn bitxor(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 ^ other.0)
+    }
+}
+impl Not for Bitboard {
+    type Output = Bitboard;
+    #[inline(always)]
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+impl BitAndAssign for Bitboard {
+    #[inline(always)]
+    fn bitand_assign(&mut self, other: Bitboard) {
+        self.0 &= other.0;
+    }
+}
+impl BitOrAssign for Bitboard {
+    #[inline(always)]
+    fn bitor_assign(&mut self, other: Bitboard) {
+        self.0 |= other.0;
+    }
+}
+impl BitXorAssign for Bitboard {
+    #[inline(always)]
+    fn bitxor_assign(&mut self, other: Bitboard) {
+        self.0 ^= other.0;
+    }
+}
+impl Shl<u32> for Bitboard {
+    type Output = Bitboard;
+    #[inline(always)]
+    fn shl(self, shift: u32) -> Bitboard {
+        Bitboard(self.0 << shift)
+    }
+}
+impl Shr<u32> for Bitboard {
+    type Output = Bitboard;
+    #[inline(always)]
+    fn shr(self, shift: u32) -> Bitboard {
+        Bitboard(self.0 >> shift)
+    }
+}
+
+impl Display for Bitboard {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        let mut res_str = String::new();
+        for rank in (0..8).rev() {
+            for file in 0..8 {
+                res_str.push(if self.contains(Square::from_file_rank(file, rank)) { 'X' } else { '.' });
+            }
+            res_str.push('\n');
+        }
+        write!(formatter, "{}", res_str)
+    }
+}
+impl Debug for Bitboard {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        write!(formatter, "Bitboard(0x{:x})", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bitboard;
+    use crate::board_representation::color::Color;
+    use crate::board_representation::game_state::PieceType;
+    use crate::board_representation::square::Square;
+
+    #[test]
+    fn bitboard_square_conversions() {
+        let bb: Bitboard = [Square::A1, Square::E4, Square::H8].iter().copied().collect();
+        assert_eq!(bb.raw(), 1u64 | 1u64 << 28 | 1u64 << 63);
+        assert_eq!(bb.iter().collect::<Vec<Square>>(), vec![Square::A1, Square::E4, Square::H8]);
+        assert_eq!(bb.iter().len(), 3);
+        let mut visited = 0;
+        for sq in bb {
+            assert!(bb.contains(sq));
+            visited += 1;
+        }
+        assert_eq!((visited, bb.popcount()), (3, 3));
+        assert_eq!(
+            Bitboard::attacks(PieceType::Knight, Square::A1, Bitboard::FULL),
+            [Square::C2, Square::B3].iter().copied().collect()
+        );
+        assert_eq!(Bitboard::attacks(PieceType::Rook, Square::A1, Square::A2.bitboard() | Square::B1.bitboard()).popcount(), 2);
+        assert!(bb.more_than_one() && !Square::E4.bitboard().more_than_one());
+        assert_eq!(Square::E4.bitboard().forward(Color::WHITE), Square::E5.bitboard());
+        assert_eq!(Square::E4.bitboard().forward(Color::BLACK), Square::E3.bitboard());
+        assert_eq!(Square::A1.bitboard() << 9, Square::B2.bitboard());
+        assert_eq!(Square::A1.bitboard().west(), Bitboard::EMPTY);
+        assert_eq!(format!("{}", Square::E4), "e4");
+        assert_eq!("g7".parse::<Square>(), Ok(Square::G7));
+        assert!("i9".parse::<Square>().is_err());
+        assert_eq!(Square::H1.offset(1, 0), None);
+        assert_eq!(Square::H1.offset(-1, 2), Some(Square::G3));
+        assert_eq!(Square::C2.mirror(), Square::C7);
+        assert_eq!(Square::C7.relative_rank(Color::BLACK), 1);
+        assert_eq!(!Color::WHITE, Color::BLACK);
+        assert_eq!(usize::from(Color::BLACK), 1);
+        assert_eq!(Square::all().count(), 64);
+        assert_eq!(format!("{}", Square::A8.bitboard()).lines().next(), Some("X......."));
+    }
+}
@@ -8,6 +8,7 @@ use crate::move_generation::magic::Magic;
 use crate::move_generation::movegen::{bishop_attack, rook_attack};
 use std::fmt::Display;
 
+pub mod bitboard;
 pub mod bitboards;
 
 //Code for generating bitboards::
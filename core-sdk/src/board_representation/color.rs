@@ -0,0 +1,54 @@
+use crate::board_representation::game_state::{swap_side, BLACK, WHITE};
+use std::fmt::{Display, Formatter, Result};
+use std::ops::Not;
+
+//A side, wrapping the raw WHITE/BLACK index used throughout the engine.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct Color(usize);
+
+impl Color {
+    pub const WHITE: Color = Color(WHITE);
+    pub const BLACK: Color = Color(BLACK);
+    pub const BOTH: [Color; 2] = [Color::WHITE, Color::BLACK];
+
+    #[inline(always)]
+    pub const fn new(side: usize) -> Self {
+        debug_assert!(side < 2);
+        Color(side)
+    }
+    #[inline(always)]
+    pub const fn index(self) -> usize {
+        self.0
+    }
+    #[inline(always)]
+    pub const fn swap(self) -> Self {
+        Color(swap_side(self.0))
+    }
+    //1 for white and -1 for black, as used for negamax scores
+    #[inline(always)]
+    pub const fn sign(self) -> i16 {
+        1 - 2 * self.0 as i16
+    }
+}
+
+impl Not for Color {
+    type Output = Color;
+    #[inline(always)]
+    fn not(self) -> Self::Output {
+        self.swap()
+    }
+}
+
+impl From<Color> for usize {
+    #[inline(always)]
+    fn from(color: Color) -> Self {
+        color.index()
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        write!(formatter, "{}", if self.0 == WHITE { "w" } else { "b" })
+    }
+}
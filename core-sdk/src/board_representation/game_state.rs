@@ -1,6 +1,9 @@
+use crate::bitboards::bitboard::Bitboard;
 use crate::bitboards::bitboards::constants::square;
 use crate::bitboards::bitboards::constants::{KING_ATTACKS, KNIGHT_ATTACKS};
 use crate::bitboards::bitboards::square;
+use crate::board_representation::color::Color;
+use crate::board_representation::square::Square;
 use crate::board_representation::zobrist_hashing::ZOBRIST_KEYS;
 use crate::evaluation::params::*;
 use crate::evaluation::phase::Phase;
@@ -410,6 +413,47 @@ impl GameState {
     pub fn castle_permissions(&self) -> u8 {
         self.irreversible.castle_permissions
     }
+    pub(crate) fn get_irreversible(&self) -> &Irreversible {
+        &self.irreversible
+    }
+    pub(crate) fn set_irreversible(&mut self, new: Irreversible) {
+        self.irreversible = new
+    }
+    pub(crate) fn set_full_moves(&mut self, new: usize) {
+        self.full_moves = new
+    }
+    pub(crate) fn bitboards_mut(&mut self) -> (&mut [u64; 6], &mut [u64; 2]) {
+        (&mut self.piece_bb, &mut self.color_bb)
+    }
+}
+
+//Typed getters
+impl GameState {
+    pub fn side_to_move(&self) -> Color {
+        Color::new(self.color_to_move)
+    }
+    pub fn pieces(&self, piece_type: PieceType, color: Color) -> Bitboard {
+        Bitboard(self.get_piece(piece_type, color.index()))
+    }
+    pub fn pieces_of(&self, color: Color) -> Bitboard {
+        Bitboard(self.get_pieces_from_side(color.index()))
+    }
+    pub fn occupied(&self) -> Bitboard {
+        Bitboard(self.get_all_pieces())
+    }
+    pub fn king_square(&self, color: Color) -> Square {
+        Square::new(self.get_king_square(color.index()))
+    }
+    pub fn en_passant_square(&self) -> Option<Square> {
+        Bitboard(self.get_en_passant()).lsb()
+    }
+    pub fn pinned(&self) -> Bitboard {
+        Bitboard(self.get_pinned_pieces())
+    }
+    //Whether the side not to move attacks sq, with occ as occupancy and the pieces in exclude removed
+    pub fn is_attacked(&self, sq: Square, occ: Bitboard, exclude: Bitboard) -> bool {
+        self.square_attacked(sq.index(), occ.0, exclude.0)
+    }
 }
 
 //Utility functions
@@ -488,7 +532,7 @@ impl GameState {
         self.initialize_phase();
     }
 
-    fn parse_piece_char(c: char) -> (usize, PieceType) {
+    fn parse_piece_char(c: char) -> Option<(usize, PieceType)> {
         let side = if c.is_uppercase() { WHITE } else { BLACK };
         let piecetype = match c.to_ascii_lowercase() {
             'p' => PieceType::Pawn,
@@ -497,82 +541,118 @@ impl GameState {
             'r' => PieceType::Rook,
             'q' => PieceType::Queen,
             'k' => PieceType::King,
-            _ => panic!("Invalid fen"),
+            _ => return None,
         };
-        (side, piecetype)
+        Some((side, piecetype))
+    }
+
+    fn parse_square(desc: &str) -> Option<usize> {
+        let mut chars = desc.chars();
+        let file = chars.next().filter(|c| ('a'..='h').contains(c))?;
+        let rank = chars.next().filter(|c| ('1'..='8').contains(c))?;
+        if chars.next().is_some() {
+            return None;
+        }
+        Some(char_to_file(file) + 8 * char_to_rank(rank))
     }
 
     pub fn from_fen(fen: &str) -> GameState {
-        let vec: Vec<&str> = fen.trim().split(' ').collect();
+        GameState::try_from_fen(fen).unwrap_or_else(|e| panic!("Invalid FEN {}: {}", fen, e))
+    }
+
+    //Parses a FEN, rejecting malformed ones, boards without exactly one king per side and positions where the king of the side not to move
+    //could be captured.
+    //Castling rights without king and rook on their squares are dropped.
+    pub fn try_from_fen(fen: &str) -> std::result::Result<GameState, String> {
+        let vec: Vec<&str> = fen.split_whitespace().collect();
         if vec.len() < 4 {
-            panic!("Invalid FEN");
+            return Err("expected at least 4 fields".to_owned());
         }
         //Parse through FEN
         //Pieces
         let pieces: Vec<&str> = vec[0].split('/').collect();
         if pieces.len() != 8 {
-            panic!("Invalid FEN");
+            return Err("expected 8 ranks".to_owned());
         }
         //Iterate over all 8 ranks
         let mut piece_bb: [u64; 6] = [0u64; 6];
         let mut color_bb: [u64; 2] = [0u64; 2];
         for (rank, rank_str) in pieces.iter().enumerate() {
             let mut file: usize = 0;
-            let mut chars = rank_str.chars();
-            while file < 8 {
-                let idx = (7 - rank) * 8 + file;
-                let c = chars.next().expect("Invalid Fen");
+            for c in rank_str.chars() {
+                if file >= 8 {
+                    return Err(format!("rank {} is too long", 8 - rank));
+                }
                 match c {
                     '1'..='8' => file += c.to_digit(10).unwrap() as usize,
                     _ => {
-                        let (side, piecetype) = GameState::parse_piece_char(c);
+                        let (side, piecetype) = GameState::parse_piece_char(c).ok_or_else(|| format!("invalid piece {}", c))?;
+                        let idx = (7 - rank) * 8 + file;
                         color_bb[side] |= square(idx);
                         piece_bb[piecetype as usize] |= square(idx);
                         file += 1;
                     }
                 }
             }
+            if file != 8 {
+                return Err(format!("rank {} does not have 8 files", 8 - rank));
+            }
+        }
+        for &side in [WHITE, BLACK].iter() {
+            if (piece_bb[PieceType::King as usize] & color_bb[side]).count_ones() != 1 {
+                return Err("each side needs exactly one king".to_owned());
+            }
+            if (piece_bb[PieceType::Pawn as usize] & color_bb[side]).count_ones() > 8 || color_bb[side].count_ones() > 16 {
+                return Err("too many pieces".to_owned());
+            }
         }
 
         //Side to move
         let color_to_move = match vec[1] {
             "w" => WHITE,
             "b" => BLACK,
-            _ => panic!("Invalid FEN!"),
+            _ => return Err(format!("invalid side to move {}", vec[1])),
         };
 
         //Castling-Abilities
+        let on = |piece_type: PieceType, side: usize, sq: usize| piece_bb[piece_type as usize] & color_bb[side] & square(sq) != 0u64;
         let mut castle_permissions = 0u8;
-        if vec[2].contains('K') {
+        if vec[2].contains('K') && on(PieceType::King, WHITE, 4) && on(PieceType::Rook, WHITE, 7) {
             castle_permissions |= CASTLE_WHITE_KS
         }
-        if vec[2].contains('Q') {
+        if vec[2].contains('Q') && on(PieceType::King, WHITE, 4) && on(PieceType::Rook, WHITE, 0) {
             castle_permissions |= CASTLE_WHITE_QS
         }
-        if vec[2].contains('k') {
+        if vec[2].contains('k') && on(PieceType::King, BLACK, 60) && on(PieceType::Rook, BLACK, 63) {
             castle_permissions |= CASTLE_BLACK_KS
         }
-        if vec[2].contains('q') {
+        if vec[2].contains('q') && on(PieceType::King, BLACK, 60) && on(PieceType::Rook, BLACK, 56) {
             castle_permissions |= CASTLE_BLACK_QS
         }
 
         //En passant target square
         let en_passant: u64 = if vec[3] != "-" {
-            let mut idx: usize = 0usize;
-            let file = vec[3].chars().next();
-            let rank = vec[3].chars().nth(1);
-            idx += char_to_file(file.expect("Invalid FEN!").to_ascii_lowercase());
-            idx += 8 * char_to_rank(rank.expect("Invalid FEN!"));
+            let idx = GameState::parse_square(&vec[3].to_ascii_lowercase()).ok_or_else(|| format!("invalid en passant square {}", vec[3]))?;
+            if rank_of(idx) != if color_to_move == WHITE { 5 } else { 2 } {
+                return Err(format!("invalid en passant square {}", vec[3]));
+            }
             square(idx)
         } else {
             0u64
         };
 
         // Move counters
-        let (half_moves, full_moves) = if vec.len() > 4 {
-            (vec[4].parse().expect("unable to parse half moves"), vec[5].parse().expect("unable to parse full moves"))
-        } else {
-            (0, 1)
+        let half_moves = match vec.get(4) {
+            Some(half_moves) => half_moves.parse().map_err(|_| format!("unable to parse half moves {}", half_moves))?,
+            None => 0,
+        };
+        let full_moves = match vec.get(5) {
+            Some(full_moves) => match full_moves.parse::<usize>() {
+                Ok(0) => return Err("full moves start at 1".to_owned()),
+                Ok(full_moves) => full_moves,
+                Err(_) => return Err(format!("unable to parse full moves {}", full_moves)),
+            },
+            None => 1,
         };
 
         let mut res = GameState::new(
@@ -583,7 +663,13 @@ impl GameState {
             full_moves,
         );
         res.initialize();
-        res
+        //Otherwise the king could be captured
+        let mut opponent = res.clone();
+        opponent.set_color_to_move(1 - color_to_move);
+        if opponent.in_check() {
+            return Err("the side not to move is in check".to_owned());
+        }
+        Ok(res)
     }
 
     pub fn to_fen(&self) -> String {
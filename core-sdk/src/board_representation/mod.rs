@@ -1,2 +1,5 @@
+pub mod color;
 pub mod game_state;
+pub mod rules;
+pub mod square;
 pub mod zobrist_hashing;
@@ -0,0 +1,267 @@
+use crate::bitboards::bitboard::Bitboard;
+use crate::board_representation::game_state::{swap_side, GameMove, GameResult, GameState, PieceType, BLACK, WHITE};
+use crate::move_generation::movegen::{generate_moves, MoveList};
+use std::fmt::{Display, Formatter, Result};
+
+//Why a game ended, according to the rules of chess
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EndReason {
+    Checkmate,
+    Stalemate,
+    //Neither side can checkmate by any sequence of legal moves
+    InsufficientMaterial,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+}
+
+impl Display for EndReason {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        let res_str = match self {
+            EndReason::Checkmate => "Checkmate",
+            EndReason::Stalemate => "Stalemate",
+            EndReason::InsufficientMaterial => "Insufficient material",
+            EndReason::FiftyMoveRule => "Fifty move rule",
+            EndReason::ThreefoldRepetition => "Threefold repetition",
+        };
+        write!(formatter, "{}", res_str)
+    }
+}
+
+const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+
+//Draw adjudication shared by the search, the evaluation and the end of game detection
+pub struct DrawRules;
+
+impl DrawRules {
+    //The part of FIDE dead positions which can be decided by material alone: bare kings, a single minor piece against a bare king,
+    //and positions where all remaining pieces besides the kings are bishops on squares of the same color
+    pub fn is_dead_position(g: &GameState) -> bool {
+        if g.get_piece_bb(PieceType::Pawn) | g.get_piece_bb(PieceType::Rook) | g.get_piece_bb(PieceType::Queen) != 0u64 {
+            return false;
+        }
+        let knights = g.get_piece_bb(PieceType::Knight);
+        let bishops = g.get_piece_bb(PieceType::Bishop);
+        if (knights | bishops).count_ones() <= 1 {
+            return true;
+        }
+        knights == 0u64 && (bishops & LIGHT_SQUARES == 0u64 || bishops & !LIGHT_SQUARES == 0u64)
+    }
+
+    //Dead positions and material configurations where neither side can force mate without help of the opponent.
+    //The latter are drawn in practice, but not by the rules, so only the evaluation uses them.
+    pub fn cannot_force_mate(g: &GameState) -> bool {
+        if DrawRules::is_dead_position(g) {
+            return true;
+        }
+        if g.get_piece_bb(PieceType::Pawn) | g.get_piece_bb(PieceType::Rook) | g.get_piece_bb(PieceType::Queen) != 0u64 {
+            return false;
+        }
+        let minors = |side: usize| g.get_piece_amt(PieceType::Knight, side) + g.get_piece_amt(PieceType::Bishop, side);
+        let (white_minors, black_minors) = (minors(WHITE), minors(BLACK));
+        if white_minors > 2 || black_minors > 2 || (white_minors == 2 && black_minors == 2) {
+            return false;
+        }
+        //Two minors only win against a single piece with the bishop pair, and against a bare king also with bishop and knight
+        let can_mate = |side: usize, other_minors: usize| {
+            let bishops = g.get_piece(PieceType::Bishop, side);
+            let bishop_pair = bishops & LIGHT_SQUARES != 0u64 && bishops & !LIGHT_SQUARES != 0u64;
+            let bishop_and_knight = bishops != 0u64 && g.get_piece(PieceType::Knight, side) != 0u64;
+            (bishop_pair && g.get_piece(PieceType::Bishop, swap_side(side)) == 0u64) || (bishop_and_knight && other_minors == 0)
+        };
+        !can_mate(WHITE, black_minors) && !can_mate(BLACK, white_minors)
+    }
+
+    //Mate takes precedence over the fifty move rule
+    pub fn is_fifty_move_draw(g: &GameState) -> bool {
+        g.get_half_moves() >= 100 && (!g.in_check() || g.has_legal_moves())
+    }
+
+    //`in_search` counts the earlier occurences of a position since the root of a search, `before_root` those in the game before.
+    //A repetition inside the search is scored as a draw right away, as the side which wants to avoid it could have done so
+    //the first time. Positions of the game history have to occur three times in total.
+    pub fn is_repetition_draw(in_search: usize, before_root: usize) -> bool {
+        in_search >= 1 || before_root >= 2
+    }
+}
+
+impl GameState {
+    pub fn legal_moves(&self) -> impl Iterator<Item = GameMove> {
+        let mut movelist = MoveList::default();
+        generate_moves(self, false, &mut movelist);
+        movelist.move_list.into_iter().map(|mv| mv.0)
+    }
+
+    //Unlike is_valid_tt_move, this also checks that the captured piece and the move type are the ones the move generator would produce
+    pub fn is_legal(&self, mv: GameMove) -> bool {
+        self.legal_moves().any(|legal| legal == mv)
+    }
+
+    //Stops at the first legal move found. Without check every move of an unpinned piece is legal, so the move generator
+    //is only needed for positions where the king, pinned pieces and evasions are all that is left to check
+    pub fn has_legal_moves(&self) -> bool {
+        let side = self.side_to_move();
+        let own = self.pieces_of(side);
+        let all = self.occupied();
+        let king_square = self.king_square(side);
+        for target in Bitboard::attacks(PieceType::King, king_square, all) & !own {
+            if !self.is_attacked(target, all ^ king_square.bitboard(), target.bitboard()) {
+                return true;
+            }
+        }
+        if !self.in_check() {
+            let unpinned = !self.pinned();
+            for &piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen].iter() {
+                for from in self.pieces(piece_type, side) & unpinned {
+                    if !(Bitboard::attacks(piece_type, from, all) & !own).is_empty() {
+                        return true;
+                    }
+                }
+            }
+            if !((self.pieces(PieceType::Pawn, side) & unpinned).forward(side) & !all).is_empty() {
+                return true;
+            }
+        }
+        self.legal_moves().next().is_some()
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check() && !self.has_legal_moves()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.in_check() && !self.has_legal_moves()
+    }
+
+    //Result of the game if the side to move has no legal moves
+    pub fn no_legal_moves_result(&self, in_check: bool) -> GameResult {
+        if !in_check {
+            GameResult::Draw
+        } else if self.get_color_to_move() == WHITE {
+            GameResult::BlackWin
+        } else {
+            GameResult::WhiteWin
+        }
+    }
+
+    //History contains all positions before this one. Mate takes precedence over the fifty move rule.
+    pub fn end_reason(&self, history: &[GameState]) -> Option<EndReason> {
+        if !self.has_legal_moves() {
+            return Some(if self.in_check() { EndReason::Checkmate } else { EndReason::Stalemate });
+        }
+        if DrawRules::is_dead_position(self) {
+            return Some(EndReason::InsufficientMaterial);
+        }
+        if DrawRules::is_fifty_move_draw(self) {
+            return Some(EndReason::FiftyMoveRule);
+        }
+        if DrawRules::is_repetition_draw(0, self.repetitions(history)) {
+            return Some(EndReason::ThreefoldRepetition);
+        }
+        None
+    }
+
+    pub fn game_result(&self, history: &[GameState]) -> GameResult {
+        self.end_reason_result(self.end_reason(history))
+    }
+
+    //Result for an end reason of this position, so callers needing both only check the end of the game once
+    pub fn end_reason_result(&self, end_reason: Option<EndReason>) -> GameResult {
+        match end_reason {
+            None => GameResult::Ingame,
+            Some(EndReason::Checkmate) => self.no_legal_moves_result(true),
+            Some(_) => GameResult::Draw,
+        }
+    }
+
+    //How often this position occured in the history before. Positions before the last irreversible move can't repeat
+    pub fn repetitions(&self, history: &[GameState]) -> usize {
+        history.iter().rev().take(self.get_half_moves()).filter(|other| other.get_hash() == self.get_hash()).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board_representation::game_state::{GameResult, GameState};
+    use crate::board_representation::rules::{DrawRules, EndReason};
+
+    #[test]
+    fn game_end_rules() {
+        let mate = GameState::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+        assert!(mate.is_checkmate());
+        assert_eq!(mate.game_result(&[]), GameResult::BlackWin);
+        let stalemate = GameState::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 60");
+        assert!(stalemate.is_stalemate());
+        assert_eq!(stalemate.end_reason(&[]), Some(EndReason::Stalemate));
+        //Mate on the move which would complete fifty moves still counts
+        let mate_at_fifty = GameState::from_fen("6rk/5Npp/8/8/8/8/8/6K1 b - - 100 80");
+        assert_eq!(mate_at_fifty.end_reason(&[]), Some(EndReason::Checkmate));
+        assert_eq!(GameState::from_fen("6rk/6pp/8/8/8/8/8/6K1 b - - 100 80").end_reason(&[]), Some(EndReason::FiftyMoveRule));
+        assert_eq!(GameState::from_fen("8/8/4k3/8/8/3K4/8/8 w - - 0 60").game_result(&[]), GameResult::Draw);
+
+        let start = GameState::standard();
+        let mut history = Vec::new();
+        let mut state = start.clone();
+        for _ in 0..2 {
+            for mv in ["g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+                history.push(state.clone());
+                state = crate::move_generation::makemove::make_move(&state, state.legal_moves().find(|x| format!("{:?}", x) == *mv).unwrap());
+            }
+            assert!(state.is_legal(state.legal_moves().next().unwrap()));
+        }
+        assert_eq!(state.repetitions(&history), 2);
+        assert_eq!(state.end_reason(&history), Some(EndReason::ThreefoldRepetition));
+        assert_eq!(state.end_reason(&history[1..]), None);
+    }
+
+    //The shortcuts of has_legal_moves have to agree with the move generator
+    #[test]
+    fn has_legal_moves_matches_movegen() {
+        let fens = [
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 60",
+            "6rk/5Npp/8/8/8/8/8/6K1 b - - 100 80",
+            //Only a pinned piece and a blocked pawn besides the king, which can't move
+            "8/8/8/8/8/1p6/1P6/KB1q3k w - - 0 1",
+            "8/8/8/8/8/1p6/1P6/KB5k w - - 0 1",
+            "k7/8/8/8/8/8/1r6/K1q5 w - - 0 1",
+            "k7/P7/K7/8/8/8/8/8 w - - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ];
+        for fen in fens.iter() {
+            let state = GameState::from_fen(fen);
+            assert_eq!(state.has_legal_moves(), state.legal_moves().next().is_some(), "{}", fen);
+            for mv in state.legal_moves() {
+                let next = crate::move_generation::makemove::make_move(&state, mv);
+                assert_eq!(next.has_legal_moves(), next.legal_moves().next().is_some(), "{} {:?}", fen, mv);
+            }
+        }
+    }
+
+    #[test]
+    fn draw_rules() {
+        let dead = |fen: &str| DrawRules::is_dead_position(&GameState::from_fen(fen));
+        assert!(dead("8/8/4k3/8/8/3K4/8/8 w - - 0 60"));
+        assert!(dead("8/8/4k3/8/8/3KN3/8/8 w - - 0 60"));
+        assert!(dead("8/8/4k3/8/3b4/3KB3/8/8 w - - 0 60"));
+        assert!(dead("8/8/4k3/8/8/3K4/8/B1B5 w - - 0 60"));
+        assert!(!dead("8/8/4k3/8/2b5/3KB3/8/8 w - - 0 60"));
+        assert!(!dead("8/8/4k3/8/2n5/3KN3/8/8 w - - 0 60"));
+        assert!(!dead("8/8/4k3/8/8/3KP3/8/8 w - - 0 60"));
+
+        let no_forced_mate = |fen: &str| DrawRules::cannot_force_mate(&GameState::from_fen(fen));
+        assert!(no_forced_mate("8/8/4k3/8/2n5/3KB3/8/8 w - - 0 60"));
+        assert!(no_forced_mate("8/8/4k3/8/8/3KN3/4N3/8 w - - 0 60"));
+        assert!(no_forced_mate("8/8/4k3/8/3b4/3KBB2/8/8 w - - 0 60"));
+        assert!(!no_forced_mate("8/8/4k3/8/8/3KBB2/8/8 w - - 0 60"));
+        assert!(!no_forced_mate("8/8/4k3/8/8/3KBN2/8/8 w - - 0 60"));
+        assert!(!no_forced_mate("8/8/4k3/8/8/3K1R2/8/8 w - - 0 60"));
+
+        assert!(DrawRules::is_fifty_move_draw(&GameState::from_fen("6rk/6pp/8/8/8/8/8/6K1 b - - 100 80")));
+        assert!(!DrawRules::is_fifty_move_draw(&GameState::from_fen("6rk/5Npp/8/8/8/8/8/6K1 b - - 100 80")));
+        assert!(!DrawRules::is_fifty_move_draw(&GameState::from_fen("6rk/6pp/8/8/8/8/8/6K1 b - - 99 80")));
+
+        assert!(DrawRules::is_repetition_draw(1, 0));
+        assert!(DrawRules::is_repetition_draw(0, 2));
+        assert!(!DrawRules::is_repetition_draw(0, 1));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::bitboards::bitboard::Bitboard;
+use crate::bitboards::bitboards::square;
+use crate::board_representation::color::Color;
+use crate::board_representation::game_state::{file_of, mirror_square, rank_of, relative_rank};
+use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
+
+//A square of the board, a1 = 0, h1 = 7, ..., h8 = 63. Converts to and from the raw usize index used throughout the engine.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[repr(transparent)]
+pub struct Square(u8);
+
+impl Square {
+    pub const A1: Square = Square(square::A1 as u8);
+    pub const B1: Square = Square(square::B1 as u8);
+    pub const C1: Square = Square(square::C1 as u8);
+    pub const D1: Square = Square(square::D1 as u8);
+    pub const E1: Square = Square(square::E1 as u8);
+    pub const F1: Square = Square(square::F1 as u8);
+    pub const G1: Square = Square(square::G1 as u8);
+    pub const H1: Square = Square(square::H1 as u8);
+    pub const A2: Square = Square(square::A2 as u8);
+    pub const B2: Square = Square(square::B2 as u8);
+    pub const C2: Square = Square(square::C2 as u8);
+    pub const D2: Square = Square(square::D2 as u8);
+    pub const E2: Square = Square(square::E2 as u8);
+    pub const F2: Square = Square(square::F2 as u8);
+    pub const G2: Square = Square(square::G2 as u8);
+    pub const H2: Square = Square(square::H2 as u8);
+    pub const A3: Square = Square(square::A3 as u8);
+    pub const B3: Square = Square(square::B3 as u8);
+    pub const C3: Square = Square(square::C3 as u8);
+    pub const D3: Square = Square(square::D3 as u8);
+    pub const E3: Square = Square(square::E3 as u8);
+    pub const F3: Square = Square(square::F3 as u8);
+    pub const G3: Square = Square(square::G3 as u8);
+    pub const H3: Square = Square(square::H3 as u8);
+    pub const A4: Square = Square(square::A4 as u8);
+    pub const B4: Square = Square(square::B4 as u8);
+    pub const C4: Square = Square(square::C4 as u8);
+    pub const D4: Square = Square(square::D4 as u8);
+    pub const E4: Square = Square(square::E4 as u8);
+    pub const F4: Square = Square(square::F4 as u8);
+    pub const G4: Square = Square(square::G4 as u8);
+    pub const H4: Square = Square(square::H4 as u8);
+    pub const A5: Square = Square(square::A5 as u8);
+    pub const B5: Square = Square(square::B5 as u8);
+    pub const C5: Square = Square(square::C5 as u8);
+    pub const D5: Square = Square(square::D5 as u8);
+    pub const E5: Square = Square(square::E5 as u8);
+    pub const F5: Square = Square(square::F5 as u8);
+    pub const G5: Square = Square(square::G5 as u8);
+    pub const H5: Square = Square(square::H5 as u8);
+    pub const A6: Square = Square(square::A6 as u8);
+    pub const B6: Square = Square(square::B6 as u8);
+    pub const C6: Square = Square(square::C6 as u8);
+    pub const D6: Square = Square(square::D6 as u8);
+    pub const E6: Square = Square(square::E6 as u8);
+    pub const F6: Square = Square(square::F6 as u8);
+    pub const G6: Square = Square(square::G6 as u8);
+    pub const H6: Square = Square(square::H6 as u8);
+    pub const A7: Square = Square(square::A7 as u8);
+    pub const B7: Square = Square(square::B7 as u8);
+    pub const C7: Square = Square(square::C7 as u8);
+    pub const D7: Square = Square(square::D7 as u8);
+    pub const E7: Square = Square(square::E7 as u8);
+    pub const F7: Square = Square(square::F7 as u8);
+    pub const G7: Square = Square(square::G7 as u8);
+    pub const H7: Square = Square(square::H7 as u8);
+    pub const A8: Square = Square(square::A8 as u8);
+    pub const B8: Square = Square(square::B8 as u8);
+    pub const C8: Square = Square(square::C8 as u8);
+    pub const D8: Square = Square(square::D8 as u8);
+    pub const E8: Square = Square(square::E8 as u8);
+    pub const F8: Square = Square(square::F8 as u8);
+    pub const G8: Square = Square(square::G8 as u8);
+    pub const H8: Square = Square(square::H8 as u8);
+
+    #[inline(always)]
+    pub const fn new(index: usize) -> Self {
+        debug_assert!(index < 64);
+        Square(index as u8)
+    }
+    #[inline(always)]
+    pub const fn from_file_rank(file: usize, rank: usize) -> Self {
+        Square::new(8 * rank + file)
+    }
+    #[inline(always)]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+    #[inline(always)]
+    pub const fn file(self) -> usize {
+        file_of(self.index())
+    }
+    #[inline(always)]
+    pub const fn rank(self) -> usize {
+        rank_of(self.index())
+    }
+    #[inline(always)]
+    pub const fn relative_rank(self, color: Color) -> usize {
+        relative_rank(color.index(), self.index())
+    }
+    //Mirrors the square vertically, a1 becomes a8
+    #[inline(always)]
+    pub const fn mirror(self) -> Self {
+        Square::new(mirror_square(self.index()))
+    }
+    #[inline(always)]
+    pub const fn bitboard(self) -> Bitboard {
+        Bitboard(1u64 << self.0)
+    }
+    //Returns None if the square would leave the board
+    pub fn offset(self, file_delta: isize, rank_delta: isize) -> Option<Square> {
+        let file = self.file() as isize + file_delta;
+        let rank = self.rank() as isize + rank_delta;
+        if !(0..8).contains(&file) || !(0..8).contains(&rank) {
+            None
+        } else {
+            Some(Square::from_file_rank(file as usize, rank as usize))
+        }
+    }
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square::new)
+    }
+}
+
+impl From<Square> for usize {
+    #[inline(always)]
+    fn from(sq: Square) -> Self {
+        sq.index()
+    }
+}
+
+impl Display for Square {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        write!(formatter, "{}{}", (b'a' + self.file() as u8) as char, self.rank() + 1)
+    }
+}
+
+impl FromStr for Square {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let bytes = s.as_bytes();
+        if bytes.len() != 2 || !(b'a'..=b'h').contains(&bytes[0]) || !(b'1'..=b'8').contains(&bytes[1]) {
+            return Err(format!("Invalid square {}", s));
+        }
+        Ok(Square::from_file_rank((bytes[0] - b'a') as usize, (bytes[1] - b'1') as usize))
+    }
+}
@@ -3,10 +3,12 @@ pub mod params;
 pub mod phase;
 pub mod psqt_evaluation;
 pub mod trace;
+pub mod wdl;
 
 use crate::bitboards::bitboards::constants::*;
 use crate::bitboards::bitboards::*;
 use crate::board_representation::game_state::{file_of, rank_of, relative_rank, swap_side, GameState, PieceType, BLACK, WHITE};
+use crate::board_representation::rules::DrawRules;
 use crate::move_generation::movegen;
 use crate::move_generation::movegen::{pawn_east_targets, pawn_targets, pawn_west_targets};
 
@@ -96,6 +98,8 @@ impl ops::MulAssign<i16> for EvaluationScore {
 
 pub struct EvaluationResult {
     pub final_eval: i16,
+    //Set for positions neither side can win, whose final_eval is 0
+    pub is_guaranteed_draw: bool,
     #[cfg(feature = "tuning")]
     pub trace: LargeTrace,
 }
@@ -107,10 +111,12 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
     }
     let mut result = EvaluationResult {
         final_eval: 0,
+        is_guaranteed_draw: false,
         #[cfg(feature = "tuning")]
         trace: LargeTrace::default(),
     };
     if is_guaranteed_draw(&g) {
+        result.is_guaranteed_draw = true;
         #[cfg(feature = "tuning")]
         {
             result.trace.is_guaranteed_draw = true;
@@ -306,21 +312,7 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
 }
 
 pub fn is_guaranteed_draw(g: &GameState) -> bool {
-    if g.get_piece_bb(PieceType::Pawn) | g.get_piece_bb(PieceType::Rook) | g.get_piece_bb(PieceType::Queen) > 0 {
-        return false;
-    }
-    let white_knights = g.get_piece_amt(PieceType::Knight, WHITE);
-    let black_knights = g.get_piece_amt(PieceType::Knight, BLACK);
-    let white_bishops = g.get_piece_amt(PieceType::Bishop, WHITE);
-    let black_bishops = g.get_piece_amt(PieceType::Bishop, BLACK);
-    if white_knights + white_bishops <= 2 && black_knights + black_bishops <= 2 {
-        if white_knights + white_bishops < 2 || black_knights + black_bishops < 2 {
-            if !(white_bishops == 2 && black_bishops == 0) && !(black_bishops == 2 && white_bishops == 0) {
-                return true;
-            }
-        }
-    }
-    false
+    DrawRules::cannot_force_mate(g)
 }
 
 pub fn endgame_rescaling(g: &GameState, res: &mut EvaluationScore, phase: f32, pawn_eval: (EvaluationScore, EvaluationScore), #[cfg(feature = "tuning")] trace: &mut LargeTrace) {
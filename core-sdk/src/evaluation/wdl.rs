@@ -0,0 +1,72 @@
+use crate::board_representation::game_state::GameState;
+
+//Win/draw/loss model. The win probability is a logistic in the score, whose midpoint a and width b depend on the
+//material m left on the board: win = 1 / (1 + exp((a(m) - score) / b(m))), loss = win(-score), draw = the rest.
+//a and b are cubic polynomials in m. The coefficients are fitted with the `fit_wdl` tool of the tuning crate,
+//on 120k positions of 1500 datagen self-play games at 5000 nodes per move.
+pub const WDL_A: [f64; 4] = [674.7, -2371.4, 3405.81, -1628.19];
+pub const WDL_B: [f64; 4] = [339.19, -1170.16, 2072.75, -1092.27];
+//Non pawn material of the starting position, as counted by the phase
+pub const STARTING_MATERIAL: f64 = 9640.;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WdlModel {
+    pub a: [f64; 4],
+    pub b: [f64; 4],
+}
+
+impl Default for WdlModel {
+    fn default() -> Self {
+        WdlModel { a: WDL_A, b: WDL_B }
+    }
+}
+
+impl WdlModel {
+    //Non pawn material in [0, 1], where 1 is the material of the starting position
+    pub fn material(game_state: &GameState) -> f64 {
+        (f64::from(game_state.get_phase().material_score) / STARTING_MATERIAL).min(1.)
+    }
+
+    pub fn polynomial(coefficients: &[f64; 4], material: f64) -> f64 {
+        coefficients.iter().rev().fold(0., |acc, c| acc * material + c)
+    }
+
+    //Probability that the side with the given score wins
+    pub fn win_probability(&self, score: f64, material: f64) -> f64 {
+        let a = WdlModel::polynomial(&self.a, material);
+        let b = WdlModel::polynomial(&self.b, material);
+        1. / (1. + ((a - score) / b).exp())
+    }
+
+    //Win, draw and loss probability in permille from the point of view of the side the score belongs to
+    pub fn wdl(&self, score: i16, material: f64) -> (u16, u16, u16) {
+        let win = (self.win_probability(f64::from(score), material) * 1000.).round() as u16;
+        let loss = (self.win_probability(-f64::from(score), material) * 1000.).round() as u16;
+        let loss = loss.min(1000 - win);
+        (win, 1000 - win - loss, loss)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WdlModel;
+    use crate::board_representation::game_state::GameState;
+
+    #[test]
+    fn wdl_is_symmetric_and_sums_up() {
+        let model = WdlModel::default();
+        let material = WdlModel::material(&GameState::standard());
+        assert!((material - 1.).abs() < 1e-9);
+        for &score in [-15000i16, -800, -50, 0, 20, 300, 14990].iter() {
+            let (w, d, l) = model.wdl(score, material);
+            assert_eq!(w + d + l, 1000);
+            let (w2, _, l2) = model.wdl(-score, material);
+            assert_eq!((w, l), (l2, w2));
+        }
+        assert_eq!(model.wdl(15000, 0.5), (1000, 0, 0));
+        let (w, d, l) = model.wdl(0, material);
+        assert!(d > 0 && w == l);
+        //The same advantage is more likely to be converted with more material on the board
+        assert!(model.wdl(300, 1.).0 > model.wdl(300, 0.2).0);
+    }
+}
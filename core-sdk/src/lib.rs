@@ -7,28 +7,58 @@ pub mod search;
 use crate::board_representation::game_state::GameState;
 use crate::move_generation::makemove::make_move;
 use crate::move_generation::movegen;
-use crate::search::alphabeta::{DEFAULT_FUTILITY_MARGIN, LMR_A, LMR_B, LMR_C, LMR_D};
+use crate::search::alphabeta::{LMR_A, LMR_B, LMR_C, LMR_D};
 use crate::search::cache::DEFAULT_HASH_SIZE;
+use crate::search::parameters::SearchParameters;
 use crate::search::reserved_memory::ReservedMoveList;
-use crate::search::searcher::{InterThreadCommunicationSystem, DEFAULT_SKIP_RATIO, DEFAULT_THREADS};
+use crate::search::searcher::{InterThreadCommunicationSystem, DEFAULT_CURRMOVE_DELAY, DEFAULT_SKIP_RATIO, DEFAULT_THREADS};
 use crate::search::timecontrol::DEFAULT_MOVE_OVERHEAD;
+use crate::search::DEFAULT_CONTEMPT;
 use std::sync::Arc;
 use std::time::Instant;
 
+//Protocol the search output is formatted for
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Protocol {
+    Uci,
+    //Thinking output for XBoard. The front end prints the move itself
+    Cecp,
+}
+
 #[derive(Copy, Clone)]
 pub struct UCIOptions {
     pub hash_size: usize,
     pub threads: usize,
     pub move_overhead: u64,
     pub debug_print: bool,
+    //Set by the UCI `debug` command, enables `info string` output
+    pub uci_debug: bool,
+    //Milliseconds after which currmove is reported
+    pub currmove_delay: u64,
+    //Appends the win/draw/loss estimate to every pv report
+    pub show_wdl: bool,
+    pub protocol: Protocol,
+    //Suppresses info and bestmove output, for tools driving the search directly
+    pub quiet: bool,
     pub skip_ratio: usize,
+    //Centipawns the side to move at the root gives up to avoid a draw
+    pub contempt: i16,
+    //Whether contempt also applies to infinite analysis, which otherwise gets neutral draw scores
+    pub analysis_contempt: bool,
 
-    pub futility_margin: i16,
+    //Search constants exposed as spin options, see SEARCH_PARAMETERS
+    pub search_parameters: SearchParameters,
     pub lmr_a: f32,
     pub lmr_b: f32,
     pub lmr_c: f32,
     pub lmr_d: f32,
 }
+impl UCIOptions {
+    pub fn debug_output(&self) -> bool {
+        !self.quiet && self.protocol == Protocol::Uci && (self.uci_debug || self.debug_print)
+    }
+}
+
 impl Default for UCIOptions {
     fn default() -> Self {
         UCIOptions {
@@ -36,8 +66,15 @@ impl Default for UCIOptions {
             threads: DEFAULT_THREADS,
             move_overhead: DEFAULT_MOVE_OVERHEAD,
             debug_print: false,
+            uci_debug: false,
+            currmove_delay: DEFAULT_CURRMOVE_DELAY,
+            show_wdl: false,
+            protocol: Protocol::Uci,
+            quiet: false,
             skip_ratio: DEFAULT_SKIP_RATIO,
-            futility_margin: DEFAULT_FUTILITY_MARGIN,
+            contempt: DEFAULT_CONTEMPT,
+            analysis_contempt: false,
+            search_parameters: SearchParameters::default(),
             lmr_a: LMR_A,
             lmr_b: LMR_B,
             lmr_c: LMR_C,
@@ -51,13 +88,9 @@ pub fn perft_div(g: &GameState, depth: usize) -> u64 {
     let mut movelist = ReservedMoveList::default();
     let now = Instant::now();
 
-    let _ = movegen::generate_moves(&g, false, &mut movelist.move_lists[depth]);
-    let len = movelist.move_lists[depth].move_list.len();
-    for i in 0..len {
-        let gmv = movelist.move_lists[depth].move_list[i];
-        let next_g = make_move(&g, gmv.0);
-        let res = perft(&next_g, depth - 1, &mut movelist);
-        println!("{:?}: {}", gmv.0, res);
+    for mv in g.legal_moves() {
+        let res = perft(&make_move(g, mv), depth - 1, &mut movelist);
+        println!("{:?}: {}", mv, res);
         count += res;
     }
     println!("{}", count);
@@ -86,7 +119,7 @@ pub fn perft(g: &GameState, depth: usize, movelist: &mut ReservedMoveList) -> u6
         res
     }
 }
-const BENCHMARKING_POSITIONS: [&str; 50] = [
+pub const BENCHMARKING_POSITIONS: [&str; 50] = [
     "r3k2r/2pb1ppp/2pp1q2/p7/1nP1B3/1P2P3/P2N1PPP/R2QK2R w KQkq a6 0 14",
     "4rrk1/2p1b1p1/p1p3q1/4p3/2P2n1p/1P1NR2P/PB3PP1/3R1QK1 b - - 2 24",
     "r3qbrk/6p1/2b2pPp/p3pP1Q/PpPpP2P/3P1B2/2PB3K/R5R1 w - - 16 42",
@@ -143,15 +176,57 @@ pub fn bench(depth: usize) {
     InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
     *itcs.cache() = search::cache::Cache::with_size_threaded(8, 1);
     let before_time = Instant::now();
+    let nodes = bench_search(&itcs, depth, &BENCHMARKING_POSITIONS);
+    let dur = Instant::now().duration_since(before_time).as_millis();
+    println!("Time: {}ms", dur);
+    println!("Nodes: {}", nodes);
+    println!("NPS: {:.0}", 1000. * nodes as f64 / dur as f64)
+}
+
+//Searches each position to a fixed depth, starting from an empty cache, and returns the nodes searched in total
+pub fn bench_search(itcs: &Arc<InterThreadCommunicationSystem>, depth: usize, positions: &[&str]) -> u64 {
     let mut nodes = 0;
+    for position in positions.iter() {
+        let state = board_representation::game_state::GameState::from_fen(position);
+        search::searcher::search_move(Arc::clone(itcs), depth as i16, state, Vec::new(), search::timecontrol::TimeControl::Infinite);
+        nodes += itcs.get_nodes_sum();
+        itcs.cache().clear_threaded(1);
+    }
+    nodes
+}
+
+//Statistics of the bench searches averaged over the positions, to compare cutoff rates between builds
+#[cfg(feature = "search-statistics")]
+pub fn bench_statistics(depth: usize) -> search::statistics::SearchStatisticsSnapshot {
+    let itcs = Arc::new(InterThreadCommunicationSystem::default());
+    InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+    *itcs.cache() = search::cache::Cache::with_size_threaded(8, 1);
+    let mut snapshots = Vec::with_capacity(BENCHMARKING_POSITIONS.len());
     for position in BENCHMARKING_POSITIONS.iter() {
         let state = board_representation::game_state::GameState::from_fen(position);
         search::searcher::search_move(Arc::clone(&itcs), depth as i16, state, Vec::new(), search::timecontrol::TimeControl::Infinite);
-        nodes += itcs.get_nodes_sum();
+        snapshots.push(itcs.search_statistics.lock().unwrap().snapshot());
         itcs.cache().clear_threaded(1);
     }
-    let dur = Instant::now().duration_since(before_time).as_millis();
-    println!("Time: {}ms", dur);
-    println!("Nodes: {}", nodes);
-    println!("NPS: {:.0}", 1000. * nodes as f64 / dur as f64)
+    search::statistics::SearchStatisticsSnapshot::average(&snapshots)
+}
+
+//Same as perft, but with make and unmake on a single state instead of copying it for every move
+pub fn perft_make_unmake(g: &mut GameState, depth: usize, movelist: &mut ReservedMoveList) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let _ = movegen::generate_moves(g, false, &mut movelist.move_lists[depth]);
+    let len = movelist.move_lists[depth].move_list.len();
+    if depth == 1 {
+        return len as u64;
+    }
+    let mut res = 0;
+    for i in 0..len {
+        let mv = movelist.move_lists[depth].move_list[i].0;
+        let undo = g.make(mv);
+        res += perft_make_unmake(g, depth - 1, movelist);
+        g.unmake(mv, undo);
+    }
+    res
 }
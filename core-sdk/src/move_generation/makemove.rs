@@ -6,9 +6,14 @@ use crate::evaluation::psqt_evaluation::{kp_add_piece, kp_move_king, kp_remove_p
 use crate::evaluation::EvaluationScore;
 
 #[inline(always)]
-pub fn toggle_piece(piece_bb: &mut [u64; 6], color_bb: &mut [u64; 2], piece: PieceType, sq: usize, color: usize, hash: &mut u64) {
+pub fn toggle_bitboards(piece_bb: &mut [u64; 6], color_bb: &mut [u64; 2], piece: PieceType, sq: usize, color: usize) {
     piece_bb[piece as usize] ^= square(sq);
     color_bb[color] ^= square(sq);
+}
+
+#[inline(always)]
+pub fn toggle_piece(piece_bb: &mut [u64; 6], color_bb: &mut [u64; 2], piece: PieceType, sq: usize, color: usize, hash: &mut u64) {
+    toggle_bitboards(piece_bb, color_bb, piece, sq, color);
     *hash ^= piece.to_zobrist_key(color, sq);
 }
 
@@ -70,86 +75,126 @@ pub fn rook_castling(to: usize) -> (usize, usize) {
 }
 
 pub fn make_move(g: &GameState, mv: GameMove) -> GameState {
-    //Step 1. Update immediate fields
-    let color = g.get_color_to_move();
-    let full_moves = g.get_full_moves() + color;
-    //Step 2. Update pieces, hash and other incremental fields
-    let mut piece_bb = g.get_piece_bb_array();
-    let mut color_bb = g.get_color_bb_array();
-    let mut hash = g.get_hash() ^ ZOBRIST_KEYS.side_to_move;
-    let mut psqt = g.get_psqt();
-    let mut phase = g.get_phase().clone();
-    let to = mv.to as usize;
-    let from = mv.from as usize;
-    let mut our_king_square = g.get_king_square(color);
-    //Remove piece from original square
-    if mv.piece_type == PieceType::King {
-        //Update our KP tables
-        kp_move_king(
-            from,
-            to,
-            g.get_piece(PieceType::Pawn, color),
-            g.get_piece(PieceType::Pawn, swap_side(color)),
-            color,
-            &mut psqt,
-        );
-        our_king_square = to;
-    } else if mv.piece_type == PieceType::Pawn {
-        //Move pawn for enemy king
-        kp_remove_piece(swap_side(color), g.get_king_square(swap_side(color)), false, PieceType::Pawn, from, &mut psqt);
-        kp_add_piece(swap_side(color), g.get_king_square(swap_side(color)), false, PieceType::Pawn, to, &mut psqt);
-        //Move pawn for our king
-        kp_remove_piece(color, our_king_square, true, PieceType::Pawn, from, &mut psqt);
-        kp_add_piece(color, our_king_square, true, PieceType::Pawn, to, &mut psqt);
-    }
-    remove_piece(&mut piece_bb, &mut color_bb, mv.piece_type, from, color, &mut hash, &mut psqt);
-    //Delete piece if capture
-    if let Some(piece) = mv.get_maybe_captured_piece() {
-        let square = to ^ (8 * (mv.move_type == GameMoveType::EnPassant) as usize);
-        remove_piece(&mut piece_bb, &mut color_bb, piece, square, swap_side(color), &mut hash, &mut psqt);
-        phase.delete_piece(piece);
-        if piece == PieceType::Pawn {
-            //Remove piece for our king
-            kp_remove_piece(color, our_king_square, false, PieceType::Pawn, square as usize, &mut psqt);
-            //Remove piece for enemy king
-            kp_remove_piece(swap_side(color), g.get_king_square(swap_side(color)), true, PieceType::Pawn, square as usize, &mut psqt);
+    let mut res = g.clone();
+    res.make(mv);
+    res
+}
+
+//Everything make can't cheaply reverse. Unmake restores it instead of recomputing hash, psqt and phase.
+#[derive(Clone)]
+pub struct Undo {
+    irreversible: Irreversible,
+}
+
+impl GameState {
+    pub fn make(&mut self, mv: GameMove) -> Undo {
+        let undo = Undo {
+            irreversible: self.get_irreversible().clone(),
+        };
+        //Step 1. Update immediate fields
+        let color = self.get_color_to_move();
+        let enemy = swap_side(color);
+        let full_moves = self.get_full_moves() + color;
+        //Step 2. Update castling rights, en passant field and half moves, which only depend on the position before the move
+        let to = mv.to as usize;
+        let from = mv.from as usize;
+        let mut hash = self.get_hash() ^ ZOBRIST_KEYS.side_to_move;
+        let castle_permissions = self.castle_permissions() & CASTLE_PERMISSION[from] & CASTLE_PERMISSION[to];
+        castle_hash(self, castle_permissions, &mut hash);
+        let en_passant = if mv.move_type == GameMoveType::Quiet && mv.piece_type == PieceType::Pawn && (to as isize - from as isize).abs() == 16 {
+            square(ep_pawn_square(to))
+        } else {
+            0u64
+        };
+        enpassant_hash(self.get_en_passant(), en_passant, &mut hash);
+        let half_moves = if mv.move_type == GameMoveType::Quiet && mv.piece_type != PieceType::Pawn {
+            self.get_half_moves() + 1
+        } else {
+            0
+        };
+        //Step 3. Update pieces, hash and other incremental fields
+        let mut psqt = self.get_psqt();
+        let mut phase = self.get_phase().clone();
+        let our_pawns = self.get_piece(PieceType::Pawn, color);
+        let enemy_pawns = self.get_piece(PieceType::Pawn, enemy);
+        let enemy_king_square = self.get_king_square(enemy);
+        let mut our_king_square = self.get_king_square(color);
+        let (piece_bb, color_bb) = self.bitboards_mut();
+        //Remove piece from original square
+        if mv.piece_type == PieceType::King {
+            //Update our KP tables
+            kp_move_king(from, to, our_pawns, enemy_pawns, color, &mut psqt);
+            our_king_square = to;
+        } else if mv.piece_type == PieceType::Pawn {
+            //Move pawn for enemy king
+            kp_remove_piece(enemy, enemy_king_square, false, PieceType::Pawn, from, &mut psqt);
+            kp_add_piece(enemy, enemy_king_square, false, PieceType::Pawn, to, &mut psqt);
+            //Move pawn for our king
+            kp_remove_piece(color, our_king_square, true, PieceType::Pawn, from, &mut psqt);
+            kp_add_piece(color, our_king_square, true, PieceType::Pawn, to, &mut psqt);
+        }
+        remove_piece(piece_bb, color_bb, mv.piece_type, from, color, &mut hash, &mut psqt);
+        //Delete piece if capture
+        if let Some(piece) = mv.get_maybe_captured_piece() {
+            let square = captured_square(mv);
+            remove_piece(piece_bb, color_bb, piece, square, enemy, &mut hash, &mut psqt);
+            phase.delete_piece(piece);
+            if piece == PieceType::Pawn {
+                //Remove piece for our king
+                kp_remove_piece(color, our_king_square, false, PieceType::Pawn, square, &mut psqt);
+                //Remove piece for enemy king
+                kp_remove_piece(enemy, enemy_king_square, true, PieceType::Pawn, square, &mut psqt);
+            }
+        }
+        //Move rook for castling
+        if let GameMoveType::Castle = mv.move_type {
+            add_piece(piece_bb, color_bb, mv.piece_type, to, color, &mut hash, &mut psqt);
+            let (rook_from, rook_to) = rook_castling(to);
+            remove_piece(piece_bb, color_bb, PieceType::Rook, rook_from, color, &mut hash, &mut psqt);
+            add_piece(piece_bb, color_bb, PieceType::Rook, rook_to, color, &mut hash, &mut psqt);
+        } else if let GameMoveType::Promotion(promo, _) = mv.move_type {
+            //If promotion, add promotion piece
+            add_piece(piece_bb, color_bb, promo, to, color, &mut hash, &mut psqt);
+            phase.add_piece(promo);
+        } else {
+            //Add piece again at to
+            add_piece(piece_bb, color_bb, mv.piece_type, to, color, &mut hash, &mut psqt);
         }
+        self.set_irreversible(Irreversible::new(hash, en_passant, half_moves as u16, castle_permissions, phase, psqt));
+        self.set_color_to_move(enemy);
+        self.set_full_moves(full_moves);
+        undo
     }
-    //Move rook for castling
-    if let GameMoveType::Castle = mv.move_type {
-        add_piece(&mut piece_bb, &mut color_bb, mv.piece_type, to, color, &mut hash, &mut psqt);
-        let (rook_from, rook_to) = rook_castling(to);
-        remove_piece(&mut piece_bb, &mut color_bb, PieceType::Rook, rook_from, color, &mut hash, &mut psqt);
-        add_piece(&mut piece_bb, &mut color_bb, PieceType::Rook, rook_to, color, &mut hash, &mut psqt);
-    } else if let GameMoveType::Promotion(promo, _) = mv.move_type {
-        //If promotion, add promotion piece
-        add_piece(&mut piece_bb, &mut color_bb, promo, to, color, &mut hash, &mut psqt);
-        phase.add_piece(promo);
-    } else {
-        //Add piece again at to
-        add_piece(&mut piece_bb, &mut color_bb, mv.piece_type, to, color, &mut hash, &mut psqt);
+
+    //Takes back a move made with make. The move has to be the last one made on this state.
+    pub fn unmake(&mut self, mv: GameMove, undo: Undo) {
+        let color = swap_side(self.get_color_to_move());
+        let to = mv.to as usize;
+        let from = mv.from as usize;
+        let (piece_bb, color_bb) = self.bitboards_mut();
+        if let GameMoveType::Castle = mv.move_type {
+            toggle_bitboards(piece_bb, color_bb, PieceType::King, from, color);
+            toggle_bitboards(piece_bb, color_bb, PieceType::King, to, color);
+            let (rook_from, rook_to) = rook_castling(to);
+            toggle_bitboards(piece_bb, color_bb, PieceType::Rook, rook_from, color);
+            toggle_bitboards(piece_bb, color_bb, PieceType::Rook, rook_to, color);
+        } else if let GameMoveType::Promotion(promo, _) = mv.move_type {
+            toggle_bitboards(piece_bb, color_bb, PieceType::Pawn, from, color);
+            toggle_bitboards(piece_bb, color_bb, promo, to, color);
+        } else {
+            toggle_bitboards(piece_bb, color_bb, mv.piece_type, from, color);
+            toggle_bitboards(piece_bb, color_bb, mv.piece_type, to, color);
+        }
+        if let Some(piece) = mv.get_maybe_captured_piece() {
+            toggle_bitboards(piece_bb, color_bb, piece, captured_square(mv), swap_side(color));
+        }
+        self.set_irreversible(undo.irreversible);
+        self.set_color_to_move(color);
+        self.set_full_moves(self.get_full_moves() - color);
     }
-    //Step 3. Update Castling Rights
-    let castle_permissions = g.castle_permissions() & CASTLE_PERMISSION[from] & CASTLE_PERMISSION[to];
-    castle_hash(g, castle_permissions, &mut hash);
-    //Step 4. Update en passant field
-    let en_passant = if mv.move_type == GameMoveType::Quiet && mv.piece_type == PieceType::Pawn && (to as isize - from as isize).abs() == 16 {
-        square(ep_pawn_square(to))
-    } else {
-        0u64
-    };
-    enpassant_hash(g.get_en_passant(), en_passant, &mut hash);
-    //Step 5. Half moves
-    let half_moves = if mv.move_type == GameMoveType::Quiet && mv.piece_type != PieceType::Pawn {
-        g.get_half_moves() + 1
-    } else {
-        0
-    };
-    GameState::new(
-        swap_side(color),
-        piece_bb,
-        color_bb,
-        Irreversible::new(hash, en_passant, half_moves as u16, castle_permissions, phase, psqt),
-        full_moves,
-    )
+}
+
+#[inline(always)]
+fn captured_square(mv: GameMove) -> usize {
+    mv.to as usize ^ (8 * (mv.move_type == GameMoveType::EnPassant) as usize)
 }
@@ -1,3 +1,4 @@
 pub mod magic;
 pub mod makemove;
 pub mod movegen;
+pub mod perft;
@@ -81,6 +81,28 @@ impl GameState {
     pub fn in_check(&self) -> bool {
         self.square_attacked(self.get_king_square(self.get_color_to_move()), self.get_all_pieces(), 0u64)
     }
+    //Pieces of the side to move which are pinned to their own king
+    pub fn get_pinned_pieces(&self) -> u64 {
+        let side = self.get_color_to_move();
+        let enemy = swap_side(side);
+        let king_square = self.get_king_square(side);
+        let side_pieces = self.get_pieces_from_side(side);
+        let all_pieces = self.get_all_pieces();
+        let mut res = 0u64;
+        let mut rook_pinners = xray_rook_attacks(rook_attack(king_square, all_pieces), all_pieces, side_pieces, king_square) & self.get_rook_like_bb(enemy);
+        while rook_pinners != 0u64 {
+            let pinner_square = rook_pinners.trailing_zeros() as usize;
+            res |= ROOK_RAYS[king_square][pinner_square] & side_pieces;
+            rook_pinners ^= square(pinner_square);
+        }
+        let mut bishop_pinners = xray_bishop_attacks(bishop_attack(king_square, all_pieces), all_pieces, side_pieces, king_square) & self.get_bishop_like_bb(enemy);
+        while bishop_pinners != 0u64 {
+            let pinner_square = bishop_pinners.trailing_zeros() as usize;
+            res |= BISHOP_RAYS[king_square][pinner_square] & side_pieces;
+            bishop_pinners ^= square(pinner_square);
+        }
+        res
+    }
 }
 
 impl PieceType {
@@ -185,7 +207,7 @@ pub fn xray_bishop_attacks(bishop_attacks: u64, occupied_squares: u64, my_pieces
 #[inline(always)]
 pub fn add_pin_moves_to_movelist(
     legal_moves: &mut MoveList,
-    only_captures: bool,
+    gen_type: GenerationType,
     ray_to_king: u64,
     push_mask: u64,
     capture_mask: u64,
@@ -197,8 +219,8 @@ pub fn add_pin_moves_to_movelist(
     other_pinner_piece_type: PieceType,
 ) {
     let pin_quiet_targets = ray_to_king & push_mask & !square(pinned_piece_position);
-    let pin_capture_possible = (capture_mask & enemy_pinner) != 0u64;
-    if !only_captures {
+    let pin_capture_possible = gen_type.captures() && (capture_mask & enemy_pinner) != 0u64;
+    if gen_type.quiets() {
         add_moves_to_movelist(legal_moves, pinned_piece_position, pin_quiet_targets, moving_piece_type, GameMoveType::Quiet);
     }
     if pin_capture_possible {
@@ -217,9 +239,9 @@ pub fn add_pin_moves_to_movelist(
 }
 
 #[inline(always)]
-pub fn add_king_moves_to_movelist(g: &GameState, legal_moves: &mut MoveList, only_captures: bool, stm_legal_kingmoves: u64, stm_king_index: usize, enemy_pieces: u64) {
-    let mut captures = stm_legal_kingmoves & enemy_pieces;
-    let quiets = stm_legal_kingmoves & !captures;
+pub fn add_king_moves_to_movelist(g: &GameState, legal_moves: &mut MoveList, gen_type: GenerationType, stm_legal_kingmoves: u64, stm_king_index: usize, enemy_pieces: u64) {
+    let quiets = stm_legal_kingmoves & !enemy_pieces;
+    let mut captures = if gen_type.captures() { stm_legal_kingmoves & enemy_pieces } else { 0u64 };
     while captures != 0u64 {
         let capture_index = captures.trailing_zeros() as usize;
         add_move_to_movelist(
@@ -231,7 +253,7 @@ pub fn add_king_moves_to_movelist(g: &GameState, legal_moves: &mut MoveList, onl
         );
         captures ^= square(capture_index);
     }
-    if !only_captures {
+    if gen_type.quiets() {
         add_moves_to_movelist(legal_moves, stm_king_index, quiets, PieceType::King, GameMoveType::Quiet);
     }
 }
@@ -270,14 +292,14 @@ pub fn add_normal_moves_to_movelist(
     empty_squares: u64,
     push_mask: u64,
     capture_mask: u64,
-    only_captures: bool,
+    gen_type: GenerationType,
 ) {
     while piece_board != 0u64 {
         let piece_index = piece_board.trailing_zeros() as usize;
         let piece = square(piece_index);
         if pinned_pieces & piece == 0 {
             let piece_target = piece_type.attacks(piece_index, g.get_all_pieces());
-            let mut captures = piece_target & capture_mask & enemy_pieces;
+            let mut captures = if gen_type.captures() { piece_target & capture_mask & enemy_pieces } else { 0u64 };
             while captures != 0u64 {
                 let capture_index = captures.trailing_zeros() as usize;
                 add_move_to_movelist(
@@ -290,7 +312,7 @@ pub fn add_normal_moves_to_movelist(
                 captures ^= square(capture_index);
             }
 
-            if !only_captures {
+            if gen_type.quiets() {
                 let quiets = piece_target & push_mask & empty_squares;
                 add_moves_to_movelist(legal_moves, piece_index, quiets, piece_type, GameMoveType::Quiet);
             }
@@ -341,6 +363,36 @@ pub fn add_move_to_movelist(legal_moves: &mut MoveList, from_square: usize, to_s
     });
 }
 
+//Selects the moves a call to append_moves generates
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GenerationType {
+    //All legal moves
+    All,
+    //Captures, including capture promotions and en passant
+    Captures,
+    //Captures and all promotions
+    Tactical,
+    //All moves not generated by Tactical, including castling
+    Quiets,
+    //All legal moves, if the side to move is in check
+    Evasions,
+}
+
+impl GenerationType {
+    #[inline(always)]
+    pub fn captures(self) -> bool {
+        self != GenerationType::Quiets
+    }
+    #[inline(always)]
+    pub fn quiets(self) -> bool {
+        matches!(self, GenerationType::All | GenerationType::Quiets | GenerationType::Evasions)
+    }
+    #[inline(always)]
+    pub fn quiet_promotions(self) -> bool {
+        matches!(self, GenerationType::All | GenerationType::Tactical | GenerationType::Evasions)
+    }
+}
+
 #[derive(Clone)]
 pub struct AdditionalGameStateInformation {
     pub stm_incheck: bool,
@@ -397,10 +449,18 @@ impl MoveList {
 }
 
 pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveList) -> AdditionalGameStateInformation {
+    movelist.move_list.clear();
+    append_moves(g, if only_captures { GenerationType::Captures } else { GenerationType::All }, movelist)
+}
+
+//Adds the legal moves of the given type to the move list, without clearing it first
+pub fn append_moves(g: &GameState, gen_type: GenerationType, movelist: &mut MoveList) -> AdditionalGameStateInformation {
+    if gen_type == GenerationType::Evasions {
+        return append_evasions(g, movelist);
+    }
     //----------------------------------------------------------------------
     //**********************************************************************
     //1. General bitboards and variable initialization
-    movelist.move_list.clear();
 
     let side = g.get_color_to_move();
     let enemy = swap_side(side);
@@ -418,7 +478,7 @@ pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveLis
     //**********************************************************************
     //2. Safe King moves
     let stm_legal_kingmoves = PieceType::King.attacks(g.get_king_square(side), all_pieces) & !enemy_attacks & !side_pieces;
-    add_king_moves_to_movelist(g, movelist, only_captures, stm_legal_kingmoves, g.get_king_square(side), enemy_pieces);
+    add_king_moves_to_movelist(g, movelist, gen_type, stm_legal_kingmoves, g.get_king_square(side), enemy_pieces);
     //----------------------------------------------------------------------
     //**********************************************************************
     //3. Check & Check Evasions
@@ -470,7 +530,7 @@ pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveLis
                 //Add possible queen pushes
                 add_pin_moves_to_movelist(
                     movelist,
-                    only_captures,
+                    gen_type,
                     ray_to_king,
                     push_mask,
                     capture_mask,
@@ -485,7 +545,7 @@ pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveLis
                 //Add possible rook pushes
                 add_pin_moves_to_movelist(
                     movelist,
-                    only_captures,
+                    gen_type,
                     ray_to_king,
                     push_mask,
                     capture_mask,
@@ -501,7 +561,7 @@ pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveLis
                 side_pawns ^= pinned_piece;
                 let stm_pawn_pin_single_push = single_push_pawn_targets(side, pinned_piece, empty_squares) & ray_to_king & push_mask;
                 let stm_pawn_pin_double_push = double_push_pawn_targets(side, pinned_piece, empty_squares) & ray_to_king & push_mask;
-                if !only_captures {
+                if gen_type.quiets() {
                     add_moves_to_movelist(
                         movelist,
                         pinned_piece_position,
@@ -530,7 +590,7 @@ pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveLis
                 //Add possible queen pushes
                 add_pin_moves_to_movelist(
                     movelist,
-                    only_captures,
+                    gen_type,
                     ray_to_king,
                     push_mask,
                     capture_mask,
@@ -545,7 +605,7 @@ pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveLis
                 //Add possible bishop pushes
                 add_pin_moves_to_movelist(
                     movelist,
-                    only_captures,
+                    gen_type,
                     ray_to_king,
                     push_mask,
                     capture_mask,
@@ -559,46 +619,48 @@ pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveLis
             } else if pinned_piece & side_pawns != 0u64 {
                 //Add possible pawn captures
                 side_pawns ^= pinned_piece;
-                let stm_pawn_pin_target = pawn_targets(side, pinned_piece);
-                //Normal captures
-                let stm_pawn_pin_captures = stm_pawn_pin_target & capture_mask & enemy_bishop;
-                let stm_pawn_pin_promotion_capture = stm_pawn_pin_captures & RANKS[if stm_color_iswhite { 7 } else { 0 }];
-                if stm_pawn_pin_promotion_capture != 0u64 {
-                    add_promotion_move_to_movelist(
-                        movelist,
-                        pinned_piece_position,
-                        enemy_bishop_position,
-                        GameMoveType::Capture(if enemy_bishop & g.get_piece(PieceType::Queen, enemy) != 0u64 {
-                            PieceType::Queen
-                        } else {
-                            PieceType::Bishop
-                        }),
-                    );
-                }
-                let stm_pawn_pin_nonpromotion_capture = stm_pawn_pin_captures & !stm_pawn_pin_promotion_capture;
-                if stm_pawn_pin_nonpromotion_capture != 0u64 {
-                    add_move_to_movelist(
-                        movelist,
-                        pinned_piece_position,
-                        enemy_bishop_position,
-                        PieceType::Pawn,
-                        GameMoveType::Capture(if enemy_bishop & g.get_piece(PieceType::Queen, enemy) != 0u64 {
-                            PieceType::Queen
-                        } else {
-                            PieceType::Bishop
-                        }),
-                    );
-                }
-                //En passants
-                let stm_pawn_pin_enpassant = stm_pawn_pin_target & g.get_en_passant() & capture_mask & ray_to_king;
-                if stm_pawn_pin_enpassant != 0u64 {
-                    add_move_to_movelist(
-                        movelist,
-                        pinned_piece_position,
-                        stm_pawn_pin_enpassant.trailing_zeros() as usize,
-                        PieceType::Pawn,
-                        GameMoveType::EnPassant,
-                    );
+                if gen_type.captures() {
+                    let stm_pawn_pin_target = pawn_targets(side, pinned_piece);
+                    //Normal captures
+                    let stm_pawn_pin_captures = stm_pawn_pin_target & capture_mask & enemy_bishop;
+                    let stm_pawn_pin_promotion_capture = stm_pawn_pin_captures & RANKS[if stm_color_iswhite { 7 } else { 0 }];
+                    if stm_pawn_pin_promotion_capture != 0u64 {
+                        add_promotion_move_to_movelist(
+                            movelist,
+                            pinned_piece_position,
+                            enemy_bishop_position,
+                            GameMoveType::Capture(if enemy_bishop & g.get_piece(PieceType::Queen, enemy) != 0u64 {
+                                PieceType::Queen
+                            } else {
+                                PieceType::Bishop
+                            }),
+                        );
+                    }
+                    let stm_pawn_pin_nonpromotion_capture = stm_pawn_pin_captures & !stm_pawn_pin_promotion_capture;
+                    if stm_pawn_pin_nonpromotion_capture != 0u64 {
+                        add_move_to_movelist(
+                            movelist,
+                            pinned_piece_position,
+                            enemy_bishop_position,
+                            PieceType::Pawn,
+                            GameMoveType::Capture(if enemy_bishop & g.get_piece(PieceType::Queen, enemy) != 0u64 {
+                                PieceType::Queen
+                            } else {
+                                PieceType::Bishop
+                            }),
+                        );
+                    }
+                    //En passants
+                    let stm_pawn_pin_enpassant = stm_pawn_pin_target & g.get_en_passant() & capture_mask & ray_to_king;
+                    if stm_pawn_pin_enpassant != 0u64 {
+                        add_move_to_movelist(
+                            movelist,
+                            pinned_piece_position,
+                            stm_pawn_pin_enpassant.trailing_zeros() as usize,
+                            PieceType::Pawn,
+                            GameMoveType::EnPassant,
+                        );
+                    }
                 }
             }
             enemy_bishop_on_xray ^= enemy_bishop;
@@ -609,76 +671,80 @@ pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveLis
     //**********************************************************************
     //5. Pawn pushes, captures, and promotions (captures, capture-enpassant, capture-promotion, normal-promotion)
     //5.1 Single push (promotions and pushes)
-    if !only_captures {
-        let stm_pawns_single_push = (forward_one(side_pawns, side) & empty_squares) & push_mask;
-        let stm_pawn_promotions = stm_pawns_single_push & RANKS[if stm_color_iswhite { 7 } else { 0 }];
+    let stm_pawns_single_push = (forward_one(side_pawns, side) & empty_squares) & push_mask;
+    let stm_pawn_promotions = stm_pawns_single_push & RANKS[if stm_color_iswhite { 7 } else { 0 }];
+    if gen_type.quiet_promotions() {
         add_pawn_moves_to_movelist(g, movelist, stm_pawn_promotions, 8, false, true, pinned_pieces);
+    }
+    if gen_type.quiets() {
         let stm_pawns_quiet_single_push = stm_pawns_single_push & !stm_pawn_promotions;
         add_pawn_moves_to_movelist(g, movelist, stm_pawns_quiet_single_push, 8, false, false, pinned_pieces);
     }
 
     //5.2 Double push
-    if !only_captures {
+    if gen_type.quiets() {
         let stm_pawns_double_push = double_push_pawn_targets(side, side_pawns, empty_squares) & push_mask;
         add_pawn_moves_to_movelist(g, movelist, stm_pawns_double_push, 16, false, false, pinned_pieces);
     }
 
-    //5.3 West captures (normal capture, promotion capture, en passant)
-    let west_targets = pawn_west_targets(side, side_pawns);
-    let stm_pawn_west_captures = west_targets & capture_mask & enemy_pieces;
-    //Split up in promotion and non-promotion captures
-    let stm_pawn_west_promotion_capture = stm_pawn_west_captures & RANKS[if stm_color_iswhite { 7 } else { 0 }];
-    add_pawn_moves_to_movelist(g, movelist, stm_pawn_west_promotion_capture, 7, true, true, pinned_pieces);
-    let stm_pawn_west_nonpromotion_capture = stm_pawn_west_captures & !stm_pawn_west_promotion_capture;
-    add_pawn_moves_to_movelist(g, movelist, stm_pawn_west_nonpromotion_capture, 7, true, false, pinned_pieces);
-    //En passants
-    let stm_pawn_west_enpassants = west_targets & g.get_en_passant() & if stm_color_iswhite { capture_mask << 8 } else { capture_mask >> 8 };
-    if stm_pawn_west_enpassants != 0u64
-        && if stm_color_iswhite {
-            stm_pawn_west_enpassants >> 7
-        } else {
-            stm_pawn_west_enpassants << 7
-        } & pinned_pieces
-            == 0u64
-    {
-        let pawn_index = stm_pawn_west_enpassants.trailing_zeros() as usize;
-        let (pawn_from, removed_piece_index) = if stm_color_iswhite {
-            (pawn_index - 7, pawn_index - 8)
-        } else {
-            (pawn_index + 7, pawn_index + 8)
-        };
-        let all_pieces_without_en_passants = all_pieces & !square(pawn_from) & !square(removed_piece_index);
-        if rook_attack(g.get_king_square(side), all_pieces_without_en_passants) & RANKS[rank_of(g.get_king_square(side))] & g.get_rook_like_bb(enemy) == 0u64 {
-            add_move_to_movelist(movelist, pawn_from, pawn_index, PieceType::Pawn, GameMoveType::EnPassant);
+    if gen_type.captures() {
+        //5.3 West captures (normal capture, promotion capture, en passant)
+        let west_targets = pawn_west_targets(side, side_pawns);
+        let stm_pawn_west_captures = west_targets & capture_mask & enemy_pieces;
+        //Split up in promotion and non-promotion captures
+        let stm_pawn_west_promotion_capture = stm_pawn_west_captures & RANKS[if stm_color_iswhite { 7 } else { 0 }];
+        add_pawn_moves_to_movelist(g, movelist, stm_pawn_west_promotion_capture, 7, true, true, pinned_pieces);
+        let stm_pawn_west_nonpromotion_capture = stm_pawn_west_captures & !stm_pawn_west_promotion_capture;
+        add_pawn_moves_to_movelist(g, movelist, stm_pawn_west_nonpromotion_capture, 7, true, false, pinned_pieces);
+        //En passants
+        let stm_pawn_west_enpassants = west_targets & g.get_en_passant() & if stm_color_iswhite { capture_mask << 8 } else { capture_mask >> 8 };
+        if stm_pawn_west_enpassants != 0u64
+            && if stm_color_iswhite {
+                stm_pawn_west_enpassants >> 7
+            } else {
+                stm_pawn_west_enpassants << 7
+            } & pinned_pieces
+                == 0u64
+        {
+            let pawn_index = stm_pawn_west_enpassants.trailing_zeros() as usize;
+            let (pawn_from, removed_piece_index) = if stm_color_iswhite {
+                (pawn_index - 7, pawn_index - 8)
+            } else {
+                (pawn_index + 7, pawn_index + 8)
+            };
+            let all_pieces_without_en_passants = all_pieces & !square(pawn_from) & !square(removed_piece_index);
+            if rook_attack(g.get_king_square(side), all_pieces_without_en_passants) & RANKS[rank_of(g.get_king_square(side))] & g.get_rook_like_bb(enemy) == 0u64 {
+                add_move_to_movelist(movelist, pawn_from, pawn_index, PieceType::Pawn, GameMoveType::EnPassant);
+            }
         }
-    }
-    //5.4 East captures (normal capture, promotion capture, en passant)
-    let east_targets = pawn_east_targets(side, side_pawns);
-    let stm_pawn_east_captures = east_targets & capture_mask & enemy_pieces;
-    //Split up in promotion and non-promotion captures
-    let stm_pawn_east_promotion_capture = stm_pawn_east_captures & RANKS[if stm_color_iswhite { 7 } else { 0 }];
-    add_pawn_moves_to_movelist(g, movelist, stm_pawn_east_promotion_capture, 9, true, true, pinned_pieces);
-    let stm_pawn_east_nonpromotion_capture = stm_pawn_east_captures & !stm_pawn_east_promotion_capture;
-    add_pawn_moves_to_movelist(g, movelist, stm_pawn_east_nonpromotion_capture, 9, true, false, pinned_pieces);
-    //En passants
-    let stm_pawn_east_enpassants = east_targets & g.get_en_passant() & if stm_color_iswhite { capture_mask << 8 } else { capture_mask >> 8 };
-    if stm_pawn_east_enpassants != 0u64
-        && if stm_color_iswhite {
-            stm_pawn_east_enpassants >> 9
-        } else {
-            stm_pawn_east_enpassants << 9
-        } & pinned_pieces
-            == 0u64
-    {
-        let pawn_index = stm_pawn_east_enpassants.trailing_zeros() as usize;
-        let (pawn_from, removed_piece_index) = if stm_color_iswhite {
-            (pawn_index - 9, pawn_index - 8)
-        } else {
-            (pawn_index + 9, pawn_index + 8)
-        };
-        let all_pieces_without_en_passants = all_pieces & !square(pawn_from) & !square(removed_piece_index);
-        if rook_attack(g.get_king_square(side), all_pieces_without_en_passants) & RANKS[rank_of(g.get_king_square(side))] & g.get_rook_like_bb(enemy) == 0u64 {
-            add_move_to_movelist(movelist, pawn_from, pawn_index, PieceType::Pawn, GameMoveType::EnPassant);
+        //5.4 East captures (normal capture, promotion capture, en passant)
+        let east_targets = pawn_east_targets(side, side_pawns);
+        let stm_pawn_east_captures = east_targets & capture_mask & enemy_pieces;
+        //Split up in promotion and non-promotion captures
+        let stm_pawn_east_promotion_capture = stm_pawn_east_captures & RANKS[if stm_color_iswhite { 7 } else { 0 }];
+        add_pawn_moves_to_movelist(g, movelist, stm_pawn_east_promotion_capture, 9, true, true, pinned_pieces);
+        let stm_pawn_east_nonpromotion_capture = stm_pawn_east_captures & !stm_pawn_east_promotion_capture;
+        add_pawn_moves_to_movelist(g, movelist, stm_pawn_east_nonpromotion_capture, 9, true, false, pinned_pieces);
+        //En passants
+        let stm_pawn_east_enpassants = east_targets & g.get_en_passant() & if stm_color_iswhite { capture_mask << 8 } else { capture_mask >> 8 };
+        if stm_pawn_east_enpassants != 0u64
+            && if stm_color_iswhite {
+                stm_pawn_east_enpassants >> 9
+            } else {
+                stm_pawn_east_enpassants << 9
+            } & pinned_pieces
+                == 0u64
+        {
+            let pawn_index = stm_pawn_east_enpassants.trailing_zeros() as usize;
+            let (pawn_from, removed_piece_index) = if stm_color_iswhite {
+                (pawn_index - 9, pawn_index - 8)
+            } else {
+                (pawn_index + 9, pawn_index + 8)
+            };
+            let all_pieces_without_en_passants = all_pieces & !square(pawn_from) & !square(removed_piece_index);
+            if rook_attack(g.get_king_square(side), all_pieces_without_en_passants) & RANKS[rank_of(g.get_king_square(side))] & g.get_rook_like_bb(enemy) == 0u64 {
+                add_move_to_movelist(movelist, pawn_from, pawn_index, PieceType::Pawn, GameMoveType::EnPassant);
+            }
         }
     }
 
@@ -696,13 +762,13 @@ pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveLis
             empty_squares,
             push_mask,
             capture_mask,
-            only_captures,
+            gen_type,
         )
     }
     //----------------------------------------------------------------------
     //**********************************************************************
     //7. Castling
-    if !only_captures && checkers == 0 {
+    if gen_type.quiets() && checkers == 0 {
         if stm_color_iswhite {
             if g.castle_white_kingside() && (all_pieces | enemy_attacks) & (square(square::F1) | square(square::G1)) == 0u64 {
                 movelist.add_move(GameMove {
@@ -742,3 +808,77 @@ pub fn generate_moves(g: &GameState, only_captures: bool, movelist: &mut MoveLis
     //----------------------------------------------------------------------
     AdditionalGameStateInformation { stm_incheck }
 }
+
+//Generates all legal moves while the side to move is in check. Besides king moves, only captures of the checker and
+//blocks of a sliding checker are legal, and a pinned piece can do neither.
+pub fn append_evasions(g: &GameState, movelist: &mut MoveList) -> AdditionalGameStateInformation {
+    let side = g.get_color_to_move();
+    let enemy = swap_side(side);
+    let king_square = g.get_king_square(side);
+    let side_pieces = g.get_pieces_from_side(side);
+    let enemy_pieces = g.get_pieces_from_side(enemy);
+    let all_pieces = side_pieces | enemy_pieces;
+    let empty_squares = !all_pieces;
+    let check_board = g.get_checkers();
+    debug_assert!(check_board != 0u64);
+
+    //1. King moves. Enemy attacks xray our king, so it can't step back on the checking ray
+    let stm_legal_kingmoves = KING_ATTACKS[king_square] & !g.get_attacks_from_side(enemy) & !side_pieces;
+    add_king_moves_to_movelist(g, movelist, GenerationType::All, stm_legal_kingmoves, king_square, enemy_pieces);
+    if check_board.count_ones() > 1 {
+        return AdditionalGameStateInformation { stm_incheck: true };
+    }
+
+    //2. Capture the checker or block it, if it's a slider
+    let checker_square = check_board.trailing_zeros() as usize;
+    let block_mask = if check_board & (g.get_bishop_like_bb(enemy) | g.get_piece(PieceType::Rook, enemy)) == 0u64 {
+        0u64
+    } else if check_board & FREEFIELD_ROOK_ATTACKS[king_square] != 0u64 {
+        ROOK_RAYS[king_square][checker_square]
+    } else {
+        BISHOP_RAYS[king_square][checker_square]
+    };
+    let pinned_pieces = g.get_pinned_pieces();
+    for pt in [PieceType::Knight, PieceType::Queen, PieceType::Bishop, PieceType::Rook].iter() {
+        add_normal_moves_to_movelist(
+            g,
+            movelist,
+            *pt,
+            g.get_piece(*pt, side),
+            pinned_pieces,
+            enemy_pieces,
+            empty_squares,
+            block_mask,
+            check_board,
+            GenerationType::All,
+        )
+    }
+
+    //3. Pawn blocks and captures of the checker
+    let side_pawns = g.get_piece(PieceType::Pawn, side) & !pinned_pieces;
+    let last_rank = RANKS[if side == WHITE { 7 } else { 0 }];
+    let single_pushes = forward_one(side_pawns, side) & empty_squares & block_mask;
+    add_pawn_moves_to_movelist(g, movelist, single_pushes & last_rank, 8, false, true, 0u64);
+    add_pawn_moves_to_movelist(g, movelist, single_pushes & !last_rank, 8, false, false, 0u64);
+    add_pawn_moves_to_movelist(g, movelist, double_push_pawn_targets(side, side_pawns, empty_squares) & block_mask, 16, false, false, 0u64);
+    for &(targets, shift) in [(pawn_west_targets(side, side_pawns), 7), (pawn_east_targets(side, side_pawns), 9)].iter() {
+        let captures = targets & check_board;
+        add_pawn_moves_to_movelist(g, movelist, captures & last_rank, shift, true, true, 0u64);
+        add_pawn_moves_to_movelist(g, movelist, captures & !last_rank, shift, true, false, 0u64);
+    }
+    //En passant captures the checker, if it is the pawn which just double pushed
+    let en_passant = g.get_en_passant();
+    if en_passant != 0u64 && check_board & square(en_passant.trailing_zeros() as usize ^ 8) != 0u64 {
+        let mut capturers = pawn_targets(enemy, en_passant) & side_pawns;
+        while capturers != 0u64 {
+            let pawn_from = capturers.trailing_zeros() as usize;
+            //Both pawns leave the rank, which might expose our king to a rook
+            let all_pieces_after = (all_pieces & !square(pawn_from) & !check_board) | en_passant;
+            if rook_attack(king_square, all_pieces_after) & RANKS[rank_of(king_square)] & g.get_rook_like_bb(enemy) == 0u64 {
+                add_move_to_movelist(movelist, pawn_from, en_passant.trailing_zeros() as usize, PieceType::Pawn, GameMoveType::EnPassant);
+            }
+            capturers ^= square(pawn_from);
+        }
+    }
+    AdditionalGameStateInformation { stm_incheck: true }
+}
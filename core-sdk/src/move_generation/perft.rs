@@ -0,0 +1,229 @@
+use crate::board_representation::game_state::{GameMove, GameState};
+use crate::move_generation::movegen;
+use crate::search::reserved_memory::ReservedMoveList;
+use std::fmt::{Display, Formatter, Result};
+use std::fs;
+use std::io;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+pub const DEFAULT_PERFT_HASH_SIZE: usize = 16; //IN MB
+const ENTRY_SIZE: usize = 16;
+//Mixes the depth into the key, so that one position can be stored for several depths
+const DEPTH_MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+struct PerftEntry {
+    //Stores key ^ count, so that a torn write from another thread fails the key check
+    key: AtomicU64,
+    count: AtomicU64,
+}
+
+//Lockless table of node counts, shared by all perft threads
+pub struct PerftTable {
+    entries: Vec<PerftEntry>,
+}
+
+impl PerftTable {
+    //A size of 0 disables the table
+    pub fn with_size(mb: usize) -> Self {
+        let amount = mb * 1024 * 1024 / ENTRY_SIZE;
+        let mut entries = Vec::with_capacity(amount);
+        for _ in 0..amount {
+            entries.push(PerftEntry {
+                key: AtomicU64::new(0),
+                count: AtomicU64::new(0),
+            });
+        }
+        PerftTable { entries }
+    }
+
+    #[inline(always)]
+    fn key(hash: u64, depth: usize) -> u64 {
+        hash ^ (depth as u64).wrapping_mul(DEPTH_MIX)
+    }
+
+    #[inline(always)]
+    pub fn probe(&self, hash: u64, depth: usize) -> Option<u64> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let key = PerftTable::key(hash, depth);
+        let entry = &self.entries[(key % self.entries.len() as u64) as usize];
+        let count = entry.count.load(Ordering::Relaxed);
+        if entry.key.load(Ordering::Relaxed) ^ count == key {
+            Some(count)
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    pub fn store(&self, hash: u64, depth: usize, count: u64) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let key = PerftTable::key(hash, depth);
+        let entry = &self.entries[(key % self.entries.len() as u64) as usize];
+        entry.key.store(key ^ count, Ordering::Relaxed);
+        entry.count.store(count, Ordering::Relaxed);
+    }
+}
+
+pub fn perft_hashed(g: &mut GameState, depth: usize, movelist: &mut ReservedMoveList, table: &PerftTable) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if depth > 1 {
+        if let Some(count) = table.probe(g.get_hash(), depth) {
+            return count;
+        }
+    }
+    movegen::generate_moves(g, false, &mut movelist.move_lists[depth]);
+    let len = movelist.move_lists[depth].move_list.len();
+    if depth == 1 {
+        return len as u64;
+    }
+    let mut res = 0;
+    for i in 0..len {
+        let mv = movelist.move_lists[depth].move_list[i].0;
+        let undo = g.make(mv);
+        res += perft_hashed(g, depth - 1, movelist, table);
+        g.unmake(mv, undo);
+    }
+    table.store(g.get_hash(), depth, res);
+    res
+}
+
+//Node count below every root move. Root moves are split between the threads.
+pub fn perft_divide(g: &GameState, depth: usize, threads: usize, table: &Arc<PerftTable>) -> Vec<(GameMove, u64)> {
+    assert!(depth >= 1, "Perft depth has to be at least 1");
+    let root_moves = Arc::new(g.legal_moves().collect::<Vec<GameMove>>());
+    let counts = Arc::new((0..root_moves.len()).map(|_| AtomicU64::new(0)).collect::<Vec<AtomicU64>>());
+    let next_move = Arc::new(AtomicUsize::new(0));
+    let mut handles = Vec::with_capacity(threads);
+    for _ in 0..threads.max(1) {
+        let mut state = g.clone();
+        let root_moves = Arc::clone(&root_moves);
+        let counts = Arc::clone(&counts);
+        let next_move = Arc::clone(&next_move);
+        let table = Arc::clone(table);
+        handles.push(thread::spawn(move || {
+            let mut movelist = ReservedMoveList::default();
+            loop {
+                let index = next_move.fetch_add(1, Ordering::Relaxed);
+                if index >= root_moves.len() {
+                    break;
+                }
+                let mv = root_moves[index];
+                let undo = state.make(mv);
+                counts[index].store(perft_hashed(&mut state, depth - 1, &mut movelist, &table), Ordering::Relaxed);
+                state.unmake(mv, undo);
+            }
+        }));
+    }
+    for handle in handles {
+        handle.join().expect("Perft thread panicked");
+    }
+    root_moves.iter().zip(counts.iter()).map(|(mv, count)| (*mv, count.load(Ordering::Relaxed))).collect()
+}
+
+pub struct PerftPosition {
+    pub fen: String,
+    //Depth and expected node count
+    pub expected: Vec<(usize, u64)>,
+}
+
+//Parses a line of a perftsuite.epd file, e.g. "<fen> ;D1 20 ;D2 400"
+pub fn parse_perft_epd(line: &str) -> Option<PerftPosition> {
+    let mut parts = line.split(';');
+    let fen = parts.next()?.trim();
+    if fen.is_empty() || fen.starts_with('#') {
+        return None;
+    }
+    let mut expected = Vec::new();
+    for part in parts {
+        let mut tokens = part.split_whitespace();
+        let depth = tokens.next()?.strip_prefix('D')?.parse::<usize>().ok()?;
+        let count = tokens.next()?.parse::<u64>().ok()?;
+        expected.push((depth, count));
+    }
+    Some(PerftPosition { fen: fen.to_owned(), expected })
+}
+
+pub fn load_perft_suite(path: &str) -> io::Result<Vec<PerftPosition>> {
+    let mut res = Vec::new();
+    for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_perft_epd(line) {
+            Some(position) => res.push(position),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid perft entry in line {}: {}", index + 1, line))),
+        }
+    }
+    Ok(res)
+}
+
+pub struct PerftMismatch {
+    pub fen: String,
+    pub depth: usize,
+    pub expected: u64,
+    pub found: u64,
+    pub divide: Vec<(GameMove, u64)>,
+}
+
+impl Display for PerftMismatch {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        writeln!(
+            formatter,
+            "Perft mismatch in {} at depth {}: expected {}, found {}",
+            self.fen, self.depth, self.expected, self.found
+        )?;
+        let mut divide = self.divide.iter().map(|(mv, count)| (format!("{:?}", mv), *count)).collect::<Vec<(String, u64)>>();
+        divide.sort();
+        for (mv, count) in divide {
+            writeln!(formatter, "{}: {}", mv, count)?;
+        }
+        Ok(())
+    }
+}
+
+//Checks every position up to max_depth and returns the mismatches, together with the divide output of the failing depth
+pub fn run_perft_suite(positions: &[PerftPosition], max_depth: usize, threads: usize, table: &Arc<PerftTable>) -> Vec<PerftMismatch> {
+    let mut mismatches = Vec::new();
+    for position in positions {
+        let state = GameState::from_fen(&position.fen);
+        for &(depth, expected) in position.expected.iter().filter(|(depth, _)| *depth <= max_depth) {
+            let divide = perft_divide(&state, depth, threads, table);
+            let found = divide.iter().map(|(_, count)| count).sum::<u64>();
+            if found != expected {
+                mismatches.push(PerftMismatch {
+                    fen: position.fen.clone(),
+                    depth,
+                    expected,
+                    found,
+                    divide,
+                });
+            }
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perft_divide_with_table() {
+        let position = parse_perft_epd("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ;D1 48 ;D2 2039 ;D3 97862").unwrap();
+        assert_eq!(position.expected, vec![(1, 48), (2, 2039), (3, 97862)]);
+        let table = Arc::new(PerftTable::with_size(1));
+        assert!(run_perft_suite(&[position], 3, 2, &table).is_empty());
+        //Second run is answered from the table
+        let state = GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(perft_divide(&state, 3, 1, &table).iter().map(|(_, count)| count).sum::<u64>(), 97862);
+        assert!(parse_perft_epd("8/8/8/8/8/8/8/K6k w - - 0 1 ;D1 x").is_none());
+    }
+}
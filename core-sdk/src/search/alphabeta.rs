@@ -2,14 +2,14 @@ use super::super::board_representation::game_state::*;
 use super::quiescence::q_search;
 use super::*;
 use super::{MATE_SCORE, MAX_SEARCH_DEPTH, STANDARD_SCORE};
-use crate::evaluation::eval_game_state;
 use crate::move_generation::makemove::{make_move, make_nullmove};
+use crate::move_generation::movegen::{self, GenerationType};
 use crate::search::cache::{CacheEntry, INVALID_STATIC_EVALUATION};
-use crate::search::moveordering::{MoveOrderer, NORMAL_STAGES};
+use crate::search::moveordering::MoveOrderer;
 use crate::search::quiescence::{piece_value, see};
 use crate::search::searcher::Thread;
 
-pub const LMP_DEPTH: usize = 4;
+pub const LMP_DEPTH: i16 = 4;
 
 pub const MIN_FUTILITY_MARGIN: i16 = 20;
 pub const DEFAULT_FUTILITY_MARGIN: i16 = 90;
@@ -25,12 +25,43 @@ pub const STATIC_NULL_MOVE_MARGIN: i16 = 120;
 pub const STATIC_NULL_MOVE_DEPTH: i16 = 5;
 pub const NULL_MOVE_PRUNING_DEPTH: i16 = 3;
 pub const HISTORY_PRUNING_DEPTH: i16 = 2;
-pub const HISTORY_PRUNING_THRESHOLD: isize = 0;
+pub const HISTORY_PRUNING_THRESHOLD: i16 = 0;
 pub const SEE_PRUNING_DEPTH: i16 = 8;
-pub const SEE_PRUNING_CAPTURE_MULT: f64 = -50.;
-pub const SEE_PRUNING_QUIET_MULT: f64 = -3.;
+pub const SEE_PRUNING_CAPTURE_MULT: i16 = -50;
+pub const SEE_PRUNING_QUIET_MULT: i16 = -3;
+pub const RAZORING_DEPTH: i16 = 2;
+pub const RAZORING_MARGIN: i16 = 300;
+pub const PROBCUT_DEPTH: i16 = 5;
+pub const PROBCUT_MARGIN: i16 = 150;
+pub const PROBCUT_REDUCTION: i16 = 4;
+pub const SINGULAR_EXTENSION_DEPTH: i16 = 8;
+//The TT entry has to come from a search at most this much shallower
+pub const SINGULAR_TT_DEPTH_MARGIN: i16 = 3;
+
+pub const MIN_SINGULAR_MARGIN: i16 = 0;
+pub const DEFAULT_SINGULAR_MARGIN: i16 = 2;
+pub const MAX_SINGULAR_MARGIN: i16 = 16;
 
-pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
+#[inline(always)]
+pub fn principal_variation_search(p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
+    #[cfg(feature = "search-tree")]
+    {
+        let mv = if p.current_depth > 0 { thread.current_moves[p.current_depth - 1] } else { None };
+        let entered = match p.excluded_move {
+            Some(excluded) => thread.search_tree.enter_excluded(p.current_depth, mv, excluded, p.alpha, p.beta, p.depth_left),
+            None => thread.search_tree.enter(p.current_depth, mv, p.alpha, p.beta, p.depth_left, false),
+        };
+        let score = search_node(p, thread);
+        if entered {
+            thread.search_tree.leave(score);
+        }
+        score
+    }
+    #[cfg(not(feature = "search-tree"))]
+    search_node(p, thread)
+}
+
+fn search_node(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
     //Step 0. Prepare variables
     thread.search_statistics.add_normal_node(p.current_depth);
     clear_pv(p.current_depth, thread);
@@ -48,13 +79,14 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     }
 
     //Step 2. Max Search depth reached
-    if let SearchInstruction::StopSearching(res) = max_depth(&p) {
+    if let SearchInstruction::StopSearching(res) = max_depth(&p, thread) {
         return res;
     }
 
     //Step 3. Check for draw or mate distance pruning if not root (need best move at root)
-    if !root {
-        if let SearchInstruction::StopSearching(r) = check_for_draw(p.game_state, &thread.history) {
+    //Verification searches have already been checked in the node they verify
+    if !root && p.excluded_move.is_none() {
+        if let SearchInstruction::StopSearching(r) = check_for_draw(p.game_state, &thread.history, thread.draw_score(p.game_state)) {
             return r;
         }
         //Mate distance pruning
@@ -73,7 +105,8 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     let incheck = p.game_state.in_check();
 
     //Step 5. Check extensions if not at root
-    if incheck && !root {
+    if incheck && !root && p.excluded_move.is_none() {
+        tree_event!(thread, p.current_depth, CheckExtension);
         p.depth_left += 1;
     }
 
@@ -94,6 +127,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
         {
             thread.search_statistics.add_cache_hit_aj_replace_ns();
         }
+        tree_event!(thread, p.current_depth, TtCutoff);
         return res;
     }
     #[cfg(feature = "search-statistics")]
@@ -102,6 +136,9 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             thread.search_statistics.add_cache_hit_ns();
         }
     }
+    if tt_entry.is_some() {
+        tree_event!(thread, p.current_depth, TtHit);
+    }
     let tt_move = if let Some(ce) = tt_entry {
         Some(CacheEntry::u16_to_mv(ce.mv, p.game_state))
     } else {
@@ -110,13 +147,13 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     let static_evaluation = if tt_entry.is_some() && tt_entry.unwrap().static_evaluation != INVALID_STATIC_EVALUATION {
         tt_entry.unwrap().static_evaluation
     } else {
-        eval_game_state(p.game_state).final_eval
+        static_evaluation(p.game_state, thread)
     };
     thread.eval_hist[p.current_depth] = Some(static_evaluation);
     thread.history.push(p.game_state.get_hash(), p.game_state.get_half_moves() == 0);
 
     //Step 9. Static Eval if needed
-    let prunable = !is_pv_node && !incheck;
+    let prunable = !is_pv_node && !incheck && p.excluded_move.is_none();
     let improving = if p.current_depth >= 2 {
         assert!(thread.eval_hist[p.current_depth - 2].is_some());
         let prev_eval = thread.eval_hist[p.current_depth - 2].unwrap();
@@ -149,35 +186,57 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     if prunable {
         //Step 10.1 Static Null Move Pruning
         if let SearchInstruction::StopSearching(res) = static_null_move_pruning(&p, thread, static_evaluation) {
+            tree_event!(thread, p.current_depth, StaticNullMovePruning);
             return res;
         }
         //Step 10.2 Null Move Forward Pruning
         if let SearchInstruction::StopSearching(res) = null_move_pruning(&p, thread, static_evaluation, &tt_entry) {
+            tree_event!(thread, p.current_depth, NullMovePruning);
+            return res;
+        }
+        //Step 10.3 Razoring
+        if let SearchInstruction::StopSearching(res) = razoring(&p, thread, static_evaluation) {
+            tree_event!(thread, p.current_depth, Razoring);
+            return res;
+        }
+        //Step 10.4 ProbCut
+        if let SearchInstruction::StopSearching(res) = probcut(&p, thread, static_evaluation, &tt_entry) {
+            tree_event!(thread, p.current_depth, ProbCut);
             return res;
         }
     }
     //Step 12. Futil Pruning and margin preparation
     let futil_margin = prepare_futility_pruning(&p, thread, static_evaluation);
 
+    //Step 13. Singular extension and multi-cut
+    let mut singular_move = None;
+    if let SearchInstruction::StopSearching(res) = singular_extension(&p, thread, tt_move, &tt_entry, &mut singular_move) {
+        tree_event!(thread, p.current_depth, MultiCut);
+        return res;
+    }
+    if singular_move.is_some() {
+        tree_event!(thread, p.current_depth, SingularExtension(format!("{:?}", singular_move.unwrap())));
+    }
+
     //Step 14. Iterate through all moves
+    let params = thread.uci_options.search_parameters;
     let mut current_max_score = STANDARD_SCORE;
     let mut index: usize = 0;
     let mut quiets_tried: usize = 0;
     let mut search_quiets = true;
-    let mut move_orderer = MoveOrderer {
-        stage: 0,
-        stages: &NORMAL_STAGES,
-        gen_only_captures: false,
-    };
+    let mut move_orderer = MoveOrderer::normal(incheck);
     loop {
         let mv = move_orderer.next(thread, &p, pv_table_move, tt_move, search_quiets);
         if mv.is_none() {
             break;
         }
         let (mv, move_score) = mv.unwrap(); //Move score is only set for bad_capture
+        if p.excluded_move == Some(mv) {
+            continue;
+        }
 
         //Step 14.4. UCI Reporting at root
-        //uci_report_move(&p, su, &mv, index);
+        uci_report_move(&p, thread, mv, index);
 
         let isc = mv.is_capture();
         let isp = if let GameMoveType::Promotion(_, _) = mv.move_type { true } else { false };
@@ -191,30 +250,36 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
                 {
                     thread.search_statistics.add_futil_pruning();
                 }
+                tree_event!(thread, p.current_depth, FutilityPruning(format!("{:?}", mv)));
                 index += 1;
                 search_quiets = false;
                 continue;
             }
             //Step 14.6. History Pruning. Skip quiet moves in low depths if they are below threshold
-            if p.depth_left <= HISTORY_PRUNING_DEPTH && thread.history_score[p.game_state.get_color_to_move()][mv.from as usize][mv.to as usize] < HISTORY_PRUNING_THRESHOLD {
+            if p.depth_left <= params.history_pruning_depth
+                && thread.quiet_history(p.current_depth, p.game_state.get_color_to_move(), mv) < isize::from(params.history_pruning_threshold)
+            {
                 #[cfg(feature = "search-statistics")]
                 {
                     thread.search_statistics.add_history_pruned();
                 }
+                tree_event!(thread, p.current_depth, HistoryPruning(format!("{:?}", mv)));
                 index += 1;
                 continue;
             }
 
-            if !incheck && p.depth_left <= LMP_DEPTH as i16 && quiets_tried > (3 * 2u32.pow((p.depth_left - 1) as u32)) as usize {
+            if !incheck && p.depth_left <= params.lmp_depth && quiets_tried > (3 * 2u32.pow((p.depth_left - 1) as u32)) as usize {
+                tree_event!(thread, p.current_depth, LateMovePruning(format!("{:?}", mv)));
                 index += 1;
                 search_quiets = false;
                 continue;
             }
             //Step 14.7 SEE Pruning. Skip quiet moves which have negative SEE Score on low depths
-            let margin = (SEE_PRUNING_QUIET_MULT * (p.depth_left as f64 * p.depth_left as f64)) as i16;
-            if p.depth_left <= SEE_PRUNING_DEPTH && -piece_value(mv.piece_type) < margin {
+            let margin = (f64::from(params.see_pruning_quiet_mult) * (p.depth_left as f64 * p.depth_left as f64)) as i16;
+            if p.depth_left <= params.see_pruning_depth && -piece_value(mv.piece_type) < margin {
                 let see_value = see(p.game_state, mv, true, &mut thread.see_buffer);
                 if see_value < margin {
+                    tree_event!(thread, p.current_depth, SeePruning(format!("{:?}", mv)));
                     index += 1;
                     continue;
                 }
@@ -222,11 +287,12 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
         } else if !root
             && isc
             && current_max_score > MATED_IN_MAX
-            && p.depth_left <= SEE_PRUNING_DEPTH
-            && move_score < SEE_PRUNING_CAPTURE_MULT * p.depth_left as f64 * p.depth_left as f64
+            && p.depth_left <= params.see_pruning_depth
+            && move_score < f64::from(params.see_pruning_capture_mult) * p.depth_left as f64 * p.depth_left as f64
             && p.game_state.has_non_pawns(p.game_state.get_color_to_move())
             && !gives_check
         {
+            tree_event!(thread, p.current_depth, SeePruning(format!("{:?}", mv)));
             index += 1;
             continue;
         }
@@ -237,8 +303,14 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
         } else {
             0
         };
+        if reduction > 0 {
+            tree_event!(thread, p.current_depth, LateMoveReduction(format!("{:?}", mv), reduction));
+        }
 
+        let new_depth = p.depth_left - 1 + if singular_move == Some(mv) { 1 } else { 0 };
+        let nodes_before = thread.search_statistics.nodes_searched;
         let next_state = make_move(p.game_state, mv);
+        thread.current_moves[p.current_depth] = Some(mv);
         //Step 14.8. Search the moves
         let mut following_score: i16;
         if p.depth_left <= 2 || !is_pv_node || index == 0 {
@@ -247,12 +319,12 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             // zero window again (with reduction). If the reduced zero window search raises alpha, research without reduction
             debug_assert!(!is_pv_node || reduction == 0);
             following_score = -principal_variation_search(
-                CombinedSearchParameters::from(-p.beta, -p.alpha, p.depth_left - 1 - reduction, &next_state, -p.color, p.current_depth + 1),
+                CombinedSearchParameters::from(-p.beta, -p.alpha, new_depth - reduction, &next_state, -p.color, p.current_depth + 1),
                 thread,
             );
             if reduction > 0 && following_score > p.alpha {
                 following_score = -principal_variation_search(
-                    CombinedSearchParameters::from(-p.beta, -p.alpha, p.depth_left - 1, &next_state, -p.color, p.current_depth + 1),
+                    CombinedSearchParameters::from(-p.beta, -p.alpha, new_depth, &next_state, -p.color, p.current_depth + 1),
                     thread,
                 );
             }
@@ -260,24 +332,28 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             //We are in a pv node and search with zero window all moves except the first (and with reduction). If
             // the reduced zero window search raises alpha, research
             following_score = -principal_variation_search(
-                CombinedSearchParameters::from(-p.alpha - 1, -p.alpha, p.depth_left - 1 - reduction, &next_state, -p.color, p.current_depth + 1),
+                CombinedSearchParameters::from(-p.alpha - 1, -p.alpha, new_depth - reduction, &next_state, -p.color, p.current_depth + 1),
                 thread,
             );
             if following_score > p.alpha {
                 following_score = -principal_variation_search(
-                    CombinedSearchParameters::from(-p.beta, -p.alpha, p.depth_left - 1, &next_state, -p.color, p.current_depth + 1),
+                    CombinedSearchParameters::from(-p.beta, -p.alpha, new_depth, &next_state, -p.color, p.current_depth + 1),
                     thread,
                 );
             }
         }
 
+        if root && p.excluded_move.is_none() {
+            thread.root_move_nodes[mv.from as usize][mv.to as usize] += thread.search_statistics.nodes_searched - nodes_before;
+        }
+
         //Step 14.9. Update principal variation if move raised current best moves score (does not have to raise alpha)
         // Also update UCI pv
         if following_score > current_max_score && !thread.self_stop {
             thread.pv_table[p.current_depth].pv[0] = Some(mv);
             current_max_score = following_score;
             concatenate_pv(p.current_depth, thread);
-            uci_report_pv(&p, thread, following_score, following_score > original_alpha);
+            uci_report_pv(&p, thread, following_score, ScoreBound::from_window(following_score, original_alpha, p.beta));
         }
 
         //Step 14.10. Update alpha if score raises alpha
@@ -312,19 +388,19 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
 
     thread.history.pop();
 
-    //Step 15. Evaluate leafs correctly
+    //Step 15. Evaluate leafs correctly. Without the excluded move there might not be any legal move left, which makes it singular
+    if p.excluded_move.is_some() && current_max_score == STANDARD_SCORE {
+        return p.alpha;
+    }
     let game_status = check_end_condition(p.game_state, current_max_score > STANDARD_SCORE, incheck);
     if game_status != GameResult::Ingame {
         clear_pv(p.current_depth, thread);
-        return leaf_score(game_status, p.color, p.current_depth as i16);
+        return leaf_score(game_status, p.color, p.current_depth as i16, thread.draw_score(p.game_state));
     }
     #[cfg(feature = "search-statistics")]
     {
         if p.alpha < p.beta {
             thread.search_statistics.add_normal_node_non_beta_cutoff();
-            if !improving2 {
-                thread.search_statistics.improving2[1] += 1;
-            }
         }
         if current_max_score < p.alpha {
             thread.search_statistics.normal_nodes_fail_lows += 1;
@@ -349,7 +425,12 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
 
 #[inline(always)]
 pub fn uci_report_move(p: &CombinedSearchParameters, thread: &mut Thread, mv: GameMove, index: usize) {
-    if p.current_depth == 0 && thread.itcs.get_time_elapsed() > 1000 {
+    if p.current_depth == 0
+        && thread.id == 0
+        && !thread.uci_options.quiet
+        && thread.uci_options.protocol == crate::Protocol::Uci
+        && thread.itcs.get_time_elapsed() >= thread.uci_options.currmove_delay
+    {
         println!("info depth {} currmove {:?} currmovenumber {}", p.depth_left, mv, (index + 1));
     }
 }
@@ -368,9 +449,9 @@ pub fn mate_distance_pruning(p: &mut CombinedSearchParameters) -> SearchInstruct
 }
 
 #[inline(always)]
-pub fn max_depth(p: &CombinedSearchParameters) -> SearchInstruction {
+pub fn max_depth(p: &CombinedSearchParameters, thread: &Thread) -> SearchInstruction {
     if p.current_depth >= (MAX_SEARCH_DEPTH - 1) {
-        SearchInstruction::StopSearching(eval_game_state(p.game_state).final_eval * p.color)
+        SearchInstruction::StopSearching(static_evaluation(p.game_state, thread) * p.color)
     } else {
         SearchInstruction::ContinueSearching
     }
@@ -391,7 +472,8 @@ pub fn get_pvtable_move(p: &CombinedSearchParameters, thread: &Thread) -> Option
 
 #[inline(always)]
 pub fn static_null_move_pruning(p: &CombinedSearchParameters, thread: &mut Thread, static_evaluation: i16) -> SearchInstruction {
-    if p.depth_left <= STATIC_NULL_MOVE_DEPTH && static_evaluation * p.color - STATIC_NULL_MOVE_MARGIN * p.depth_left >= p.beta {
+    let params = thread.uci_options.search_parameters;
+    if p.depth_left <= params.static_null_move_depth && static_evaluation * p.color - params.static_null_move_margin * p.depth_left >= p.beta {
         thread.history.pop();
         #[cfg(feature = "search-statistics")]
         {
@@ -408,8 +490,13 @@ pub fn null_move_pruning(p: &CombinedSearchParameters, thread: &mut Thread, stat
     let tt_do_nmp = tt_entry.is_some() && !tt_entry.unwrap().is_upper_bound() && tt_entry.unwrap().score >= p.beta;
     let tt_dont_nmp = tt_entry.is_some() && !tt_entry.unwrap().is_lower_bound() && tt_entry.unwrap().score < p.beta;
     let static_do_nmp = static_evaluation * p.color >= p.beta;
-    if p.depth_left >= NULL_MOVE_PRUNING_DEPTH && p.game_state.has_non_pawns(p.game_state.get_color_to_move()) && (tt_do_nmp || static_do_nmp) && !tt_dont_nmp {
+    if p.depth_left >= thread.uci_options.search_parameters.null_move_pruning_depth
+        && p.game_state.has_non_pawns(p.game_state.get_color_to_move())
+        && (tt_do_nmp || static_do_nmp)
+        && !tt_dont_nmp
+    {
         let nextgs = make_nullmove(p.game_state);
+        thread.current_moves[p.current_depth] = None;
         let rat = -principal_variation_search(
             CombinedSearchParameters::from(-p.beta, -p.beta + 1, (p.depth_left - 4 - p.depth_left / 6).max(0), &nextgs, -p.color, p.current_depth + 1),
             thread,
@@ -426,11 +513,132 @@ pub fn null_move_pruning(p: &CombinedSearchParameters, thread: &mut Thread, stat
     SearchInstruction::ContinueSearching
 }
 
+//Searches all moves but the TT move with reduced depth and a window below the TT score. If all of them fail low, the TT move is singular and
+// gets extended. If they fail high above beta instead, several moves are expected to beat beta and the node is cut.
+#[inline(always)]
+pub fn singular_extension(
+    p: &CombinedSearchParameters,
+    thread: &mut Thread,
+    tt_move: Option<GameMove>,
+    tt_entry: &Option<CacheEntry>,
+    singular_move: &mut Option<GameMove>,
+) -> SearchInstruction {
+    let (tt_move, ce) = match (tt_move, tt_entry) {
+        (Some(tt_move), Some(ce)) => (tt_move, ce),
+        _ => return SearchInstruction::ContinueSearching,
+    };
+    let params = thread.uci_options.search_parameters;
+    if p.current_depth == 0
+        || p.excluded_move.is_some()
+        || p.depth_left < params.singular_extension_depth
+        || ce.is_upper_bound()
+        || i16::from(ce.depth) < p.depth_left - params.singular_tt_depth_margin
+        || ce.score.abs() >= -MATED_IN_MAX
+        || !p.game_state.is_valid_tt_move(tt_move)
+    {
+        return SearchInstruction::ContinueSearching;
+    }
+    let singular_beta = ce.score - params.singular_margin * p.depth_left;
+    let score = principal_variation_search(
+        CombinedSearchParameters::from(singular_beta - 1, singular_beta, (p.depth_left - 1) / 2, p.game_state, p.color, p.current_depth).excluding(tt_move),
+        thread,
+    );
+    if thread.self_stop {
+        return SearchInstruction::ContinueSearching;
+    }
+    if score < singular_beta {
+        *singular_move = Some(tt_move);
+    } else if singular_beta >= p.beta {
+        thread.history.pop();
+        return SearchInstruction::StopSearching(singular_beta);
+    }
+    SearchInstruction::ContinueSearching
+}
+
+//If the static evaluation is far below alpha at low depth, only captures are expected to save the position. Let quiescence search verify that.
+#[inline(always)]
+pub fn razoring(p: &CombinedSearchParameters, thread: &mut Thread, static_evaluation: i16) -> SearchInstruction {
+    let params = thread.uci_options.search_parameters;
+    if p.depth_left > params.razoring_depth || static_evaluation * p.color + params.razoring_margin * p.depth_left > p.alpha {
+        return SearchInstruction::ContinueSearching;
+    }
+    //Quiescence search checks the position itself for repetitions
+    thread.history.pop();
+    let score = q_search(CombinedSearchParameters::from(p.alpha, p.alpha + 1, 0, p.game_state, p.color, p.current_depth), thread);
+    if score <= p.alpha {
+        #[cfg(feature = "search-statistics")]
+        {
+            thread.search_statistics.add_razoring();
+        }
+        return SearchInstruction::StopSearching(score);
+    }
+    thread.history.push(p.game_state.get_hash(), p.game_state.get_half_moves() == 0);
+    SearchInstruction::ContinueSearching
+}
+
+//If a good capture beats beta by a margin in a reduced search, the full depth search is expected to fail high as well
+#[inline(always)]
+pub fn probcut(p: &CombinedSearchParameters, thread: &mut Thread, static_evaluation: i16, tt_entry: &Option<CacheEntry>) -> SearchInstruction {
+    let params = thread.uci_options.search_parameters;
+    let probcut_beta = p.beta + params.probcut_margin;
+    if p.depth_left < params.probcut_depth || p.beta.abs() >= -MATED_IN_MAX {
+        return SearchInstruction::ContinueSearching;
+    }
+    //A search of nearly the same depth already failed to reach probcut_beta
+    if let Some(ce) = tt_entry {
+        if i16::from(ce.depth) > p.depth_left - params.probcut_reduction && !ce.is_lower_bound() && ce.score < probcut_beta {
+            return SearchInstruction::ContinueSearching;
+        }
+    }
+    let see_threshold = probcut_beta - static_evaluation * p.color;
+    thread.movelist.move_lists[p.current_depth].move_list.clear();
+    movegen::append_moves(p.game_state, GenerationType::Captures, &mut thread.movelist.move_lists[p.current_depth]);
+    //The main search generates its own moves afterwards, so the list can be used here
+    for index in 0..thread.movelist.move_lists[p.current_depth].move_list.len() {
+        let mv = thread.movelist.move_lists[p.current_depth].move_list[index].0;
+        if see(p.game_state, mv, true, &mut thread.see_buffer) < see_threshold {
+            continue;
+        }
+        let next_state = make_move(p.game_state, mv);
+        thread.current_moves[p.current_depth] = Some(mv);
+        let mut score = -q_search(
+            CombinedSearchParameters::from(-probcut_beta, -probcut_beta + 1, 0, &next_state, -p.color, p.current_depth + 1),
+            thread,
+        );
+        if score >= probcut_beta {
+            score = -principal_variation_search(
+                CombinedSearchParameters::from(
+                    -probcut_beta,
+                    -probcut_beta + 1,
+                    //The reduction may be tuned above the minimal depth
+                    (p.depth_left - params.probcut_reduction).max(0),
+                    &next_state,
+                    -p.color,
+                    p.current_depth + 1,
+                ),
+                thread,
+            );
+        }
+        if thread.self_stop {
+            break;
+        }
+        if score >= probcut_beta {
+            #[cfg(feature = "search-statistics")]
+            {
+                thread.search_statistics.add_probcut();
+            }
+            thread.history.pop();
+            return SearchInstruction::StopSearching(score);
+        }
+    }
+    SearchInstruction::ContinueSearching
+}
+
 #[inline(always)]
 pub fn prepare_futility_pruning(p: &CombinedSearchParameters, thread: &Thread, static_evaluation: i16) -> i16 {
-    let futil_pruning = p.depth_left <= FUTILITY_DEPTH && p.current_depth > 0;
+    let futil_pruning = p.depth_left <= thread.uci_options.search_parameters.futility_depth && p.current_depth > 0;
     if futil_pruning {
-        static_evaluation * p.color + p.depth_left * thread.uci_options.futility_margin
+        static_evaluation * p.color + p.depth_left * thread.uci_options.search_parameters.futility_margin
     } else {
         MATE_SCORE
     }
@@ -460,7 +668,10 @@ pub fn compute_lmr_reduction(p: &CombinedSearchParameters, thread: &Thread, mv:
     if in_check {
         reduction -= 2.;
     }
-    if thread.history_score[p.game_state.get_color_to_move()][mv.from as usize][mv.to as usize] > 0 {
+    if thread.quiet_history(p.current_depth, p.game_state.get_color_to_move(), mv) > 0 {
+        reduction -= 1.;
+    }
+    if thread.counter_move(p.current_depth, p.game_state.get_color_to_move()) == Some(mv) {
         reduction -= 1.;
     }
     reduction = reduction.min(p.depth_left as f32 - 1.);
@@ -468,7 +679,7 @@ pub fn compute_lmr_reduction(p: &CombinedSearchParameters, thread: &Thread, mv:
 }
 
 #[inline(always)]
-pub fn uci_report_pv(p: &CombinedSearchParameters, thread: &mut Thread, following_score: i16, no_fail: bool) {
+pub fn uci_report_pv(p: &CombinedSearchParameters, thread: &mut Thread, following_score: i16, bound: ScoreBound) {
     if p.current_depth == 0 {
         thread.replace_current_pv(
             p.game_state,
@@ -477,15 +688,23 @@ pub fn uci_report_pv(p: &CombinedSearchParameters, thread: &mut Thread, followin
                 score: following_score,
                 depth: p.depth_left as usize,
             },
-            no_fail,
+            bound,
         );
     }
 }
 
 #[inline(always)]
 pub fn update_quiet_cutoff(p: &CombinedSearchParameters, thread: &mut Thread, mv: GameMove, quiets_tried: usize) {
-    thread.hh_score[p.game_state.get_color_to_move()][mv.from as usize][mv.to as usize] += p.depth_left as usize * p.depth_left as usize;
-    thread.history_score[p.game_state.get_color_to_move()][mv.from as usize][mv.to as usize] += p.depth_left as isize * p.depth_left as isize;
+    let side = p.game_state.get_color_to_move();
+    thread.hh_score[side][mv.from as usize][mv.to as usize] += p.depth_left as usize * p.depth_left as usize;
+    thread.history_score[side][mv.from as usize][mv.to as usize] += p.depth_left as isize * p.depth_left as isize;
+    for plies_ago in 1..=2 {
+        let earlier = thread.previous_move(p.current_depth, plies_ago);
+        thread.continuation_history[plies_ago - 1].update(side, earlier, mv, p.depth_left as isize * p.depth_left as isize);
+    }
+    if let Some(previous) = thread.previous_move(p.current_depth, 1) {
+        thread.counter_moves[side][previous.piece_type as usize][previous.to as usize] = Some(mv);
+    }
     decrement_history_quiets(thread, p.current_depth, quiets_tried, p.depth_left as isize, p.game_state.get_color_to_move());
     if let Some(s) = thread.killer_moves[p.current_depth][0] {
         if mv == s {
@@ -504,8 +723,12 @@ pub fn update_quiet_cutoff(p: &CombinedSearchParameters, thread: &mut Thread, mv
 }
 
 pub fn decrement_history_quiets(thread: &mut Thread, current_depth: usize, quiets_tried: usize, depth_left: isize, side_to_move: usize) {
+    let earlier = [thread.previous_move(current_depth, 1), thread.previous_move(current_depth, 2)];
     for i in 0..quiets_tried {
-        let mv = thread.quiets_tried[current_depth][i].as_ref().unwrap();
+        let mv = thread.quiets_tried[current_depth][i].unwrap();
         thread.history_score[side_to_move][mv.from as usize][mv.to as usize] -= depth_left * depth_left;
+        for (continuation_history, earlier) in thread.continuation_history.iter_mut().zip(earlier.iter()) {
+            continuation_history.update(side_to_move, *earlier, mv, -depth_left * depth_left);
+        }
     }
 }
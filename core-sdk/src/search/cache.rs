@@ -1,6 +1,9 @@
 use crate::board_representation::game_state::{swap_side, GameMove, GameMoveType, GameState, PieceType, PIECE_TYPES};
 use crate::search::{CombinedSearchParameters, SearchInstruction, MATED_IN_MAX};
 use std::cell::UnsafeCell;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
 
 pub const INVALID_STATIC_EVALUATION: i16 = -32768;
 pub const DEFAULT_HASH_SIZE: usize = 256; //IN MB
@@ -8,6 +11,13 @@ pub const MIN_HASH_SIZE: usize = 0; //IN MB
 pub const MAX_HASH_SIZE: usize = 131_072; //IN MB
 pub const MAXIMUM_AGE: u8 = 32;
 
+//Layout of a saved hash file: a header, the entries of every bucket and a checksum over the entries
+pub const HASH_FILE_MAGIC: [u8; 8] = *b"FABHASH\0";
+pub const HASH_FILE_VERSION: u32 = 1;
+const HASH_FILE_HEADER_SIZE: u64 = 48;
+const ENTRIES_PER_BUCKET: usize = 3;
+const SERIALIZED_ENTRY_SIZE: usize = 16;
+
 pub struct Cache {
     pub entries: usize,
     pub buckets: usize,
@@ -123,6 +133,95 @@ impl Cache {
         (full as f64 / counted_entries as f64 * 1000.0) as usize
     }
 
+    pub fn size_mb(&self) -> usize {
+        self.buckets * 64 / (1024 * 1024)
+    }
+
+    //Writes the table to disk. Must not be called while a search is running.
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&HASH_FILE_MAGIC)?;
+        writer.write_all(&HASH_FILE_VERSION.to_le_bytes())?;
+        writer.write_all(&(std::mem::size_of::<CacheBucket>() as u32).to_le_bytes())?;
+        writer.write_all(&(ENTRIES_PER_BUCKET as u32).to_le_bytes())?;
+        writer.write_all(&(SERIALIZED_ENTRY_SIZE as u32).to_le_bytes())?;
+        writer.write_all(&(self.size_mb() as u64).to_le_bytes())?;
+        writer.write_all(&(self.buckets as u64).to_le_bytes())?;
+        writer.write_all(&[self.current_age, 0, 0, 0, 0, 0, 0, 0])?;
+        let mut checksum = 0u64;
+        for bucket in unsafe { (&*self.cache.get()).iter() } {
+            for entry in bucket.0.iter() {
+                let bytes = entry.to_bytes();
+                checksum = update_checksum(checksum, &bytes);
+                writer.write_all(&bytes)?;
+            }
+        }
+        writer.write_all(&checksum.to_le_bytes())?;
+        writer.flush()
+    }
+
+    //Reads a table written by `save_to`. The whole file is validated before the new table is returned,
+    //so a corrupt file never replaces the current table.
+    pub fn load_from<P: AsRef<Path>>(path: P, num_threads: usize) -> io::Result<Cache> {
+        let file = File::open(path)?;
+        let file_length = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut header = [0u8; HASH_FILE_HEADER_SIZE as usize];
+        if file_length < HASH_FILE_HEADER_SIZE {
+            return Err(invalid_hash_file("File is too short"));
+        }
+        reader.read_exact(&mut header)?;
+        let read_u32 = |offset: usize| {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(&header[offset..offset + 4]);
+            u32::from_le_bytes(bytes)
+        };
+        let read_u64 = |offset: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&header[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        if header[0..8] != HASH_FILE_MAGIC {
+            return Err(invalid_hash_file("Not a hash file"));
+        }
+        if read_u32(8) != HASH_FILE_VERSION {
+            return Err(invalid_hash_file(&format!("Unsupported version {}, expected {}", read_u32(8), HASH_FILE_VERSION)));
+        }
+        if read_u32(12) as usize != std::mem::size_of::<CacheBucket>() || read_u32(16) as usize != ENTRIES_PER_BUCKET || read_u32(20) as usize != SERIALIZED_ENTRY_SIZE {
+            return Err(invalid_hash_file("Bucket layout does not match"));
+        }
+        let size_mb = read_u64(24) as usize;
+        let buckets = read_u64(32);
+        let current_age = header[40];
+        if size_mb > MAX_HASH_SIZE || buckets != (1024 * 1024 * size_mb / 64) as u64 {
+            return Err(invalid_hash_file("Size does not match the bucket count"));
+        }
+        if current_age >= MAXIMUM_AGE {
+            return Err(invalid_hash_file("Invalid age"));
+        }
+        if file_length != HASH_FILE_HEADER_SIZE + buckets * (ENTRIES_PER_BUCKET * SERIALIZED_ENTRY_SIZE) as u64 + 8 {
+            return Err(invalid_hash_file("File length does not match the header"));
+        }
+
+        let mut res = Cache::with_size_threaded(size_mb, num_threads);
+        res.current_age = current_age;
+        let mut checksum = 0u64;
+        let mut bytes = [0u8; SERIALIZED_ENTRY_SIZE];
+        for bucket in res.cache.get_mut().iter_mut() {
+            for entry in bucket.0.iter_mut() {
+                reader.read_exact(&mut bytes)?;
+                checksum = update_checksum(checksum, &bytes);
+                *entry = CacheEntry::from_bytes(&bytes);
+            }
+        }
+        let mut stored_checksum = [0u8; 8];
+        reader.read_exact(&mut stored_checksum)?;
+        if u64::from_le_bytes(stored_checksum) != checksum {
+            return Err(invalid_hash_file("Checksum mismatch"));
+        }
+        Ok(res)
+    }
+
     pub fn clear_threaded(&self, num_threads: usize) {
         unsafe {
             *self.cache.get() = Cache::get_init_cache(self.buckets, num_threads);
@@ -140,7 +239,7 @@ impl Cache {
     }
 
     pub fn insert(&self, p: &CombinedSearchParameters, mv: GameMove, score: i16, original_alpha: i16, static_evaluation: i16) {
-        if self.entries == 0 {
+        if self.entries == 0 || p.excluded_move.is_some() {
             return;
         }
         let index = p.game_state.get_hash() as usize % self.buckets;
@@ -152,7 +251,8 @@ impl Cache {
     }
 
     pub fn lookup(&self, p: &CombinedSearchParameters, tt_entry: &mut Option<CacheEntry>) -> SearchInstruction {
-        if self.entries == 0 {
+        //The entry of the position belongs to the search with all moves
+        if self.entries == 0 || p.excluded_move.is_some() {
             return SearchInstruction::ContinueSearching;
         }
         let ce = self.get(p.game_state.get_hash()).probe(p.game_state.get_hash());
@@ -173,6 +273,18 @@ impl Cache {
     }
 }
 
+fn update_checksum(checksum: u64, bytes: &[u8; SERIALIZED_ENTRY_SIZE]) -> u64 {
+    let mut low = [0u8; 8];
+    let mut high = [0u8; 8];
+    low.copy_from_slice(&bytes[0..8]);
+    high.copy_from_slice(&bytes[8..16]);
+    (checksum.rotate_left(5) ^ u64::from_le_bytes(low)).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ u64::from_le_bytes(high)
+}
+
+fn invalid_hash_file(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Invalid hash file: {}", reason))
+}
+
 #[repr(align(64))]
 #[derive(Copy, Clone)]
 pub struct CacheBucket([CacheEntry; 3]);
@@ -331,6 +443,29 @@ impl CacheEntry {
     pub fn validate_hash(&self, hash: u64) -> bool {
         (self.upper_hash as u64) == (hash >> 32) && ((self.lower_hash ^ self.mv as u32) as u64) == (hash & 0xFFFF_FFFF)
     }
+    pub fn to_bytes(&self) -> [u8; SERIALIZED_ENTRY_SIZE] {
+        let mut res = [0u8; SERIALIZED_ENTRY_SIZE];
+        res[0] = self.flags;
+        res[1] = self.depth as u8;
+        res[2..4].copy_from_slice(&self.score.to_le_bytes());
+        res[4..8].copy_from_slice(&self.upper_hash.to_le_bytes());
+        res[8..12].copy_from_slice(&self.lower_hash.to_le_bytes());
+        res[12..14].copy_from_slice(&self.mv.to_le_bytes());
+        res[14..16].copy_from_slice(&self.static_evaluation.to_le_bytes());
+        res
+    }
+    pub fn from_bytes(bytes: &[u8; SERIALIZED_ENTRY_SIZE]) -> CacheEntry {
+        CacheEntry {
+            flags: bytes[0],
+            depth: bytes[1] as i8,
+            score: i16::from_le_bytes([bytes[2], bytes[3]]),
+            upper_hash: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            lower_hash: u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+            mv: u16::from_le_bytes([bytes[12], bytes[13]]),
+            static_evaluation: i16::from_le_bytes([bytes[14], bytes[15]]),
+        }
+    }
+
     //I know this is not idiomatic, but it saves memory...
     pub fn is_invalid(&self) -> bool {
         self.mv == 0u16
@@ -474,13 +609,14 @@ unsafe impl<T> Send for PtrWrapper<T> {}
 
 #[cfg(test)]
 mod tests {
-    use super::CacheEntry;
+    use super::{Cache, CacheEntry};
     use crate::board_representation::game_state::{GameMove, GameMoveType, GameState, PieceType};
     use crate::move_generation::makemove::make_move;
+    use crate::search::CombinedSearchParameters;
 
     #[test]
     fn mv_to_u16_test() {
-        let mut game_state = GameState::from_fen("k4b2/2p1P3/8/3P4/6b1/7P/8/R3K2R w KQ -");
+        let mut game_state = GameState::from_fen("1k3b2/2p1P3/8/3P4/6b1/7P/8/R3K2R w KQ -");
         {
             let h3h4 = GameMove {
                 from: 23,
@@ -683,4 +819,64 @@ mod tests {
             assert_eq!(d5d6res.piece_type, d5d6.piece_type);
         }
     }
+
+    #[test]
+    fn save_and_load_hash() {
+        let mut cache = Cache::with_size_threaded(1, 1);
+        cache.increase_age();
+        let game_state = GameState::standard();
+        let mv = GameMove {
+            from: 12,
+            to: 28,
+            piece_type: PieceType::Pawn,
+            move_type: GameMoveType::Quiet,
+        };
+        let hash = game_state.get_hash();
+        unsafe {
+            (&mut *cache.cache.get())[hash as usize % cache.buckets].0[1].write(hash, 7, 25, 13, true, false, true, mv, cache.current_age);
+        }
+        let path = std::env::temp_dir().join(format!("fabchess_hash_test_{}.bin", std::process::id()));
+        cache.save_to(&path).unwrap();
+
+        let loaded = Cache::load_from(&path, 1).unwrap();
+        assert_eq!(loaded.buckets, cache.buckets);
+        assert_eq!(loaded.current_age, 1);
+        let entry = loaded.get(hash).probe(hash).unwrap();
+        assert_eq!((entry.depth, entry.score, entry.static_evaluation), (7, 25, 13));
+        assert!(entry.is_pv_node() && entry.is_lower_bound());
+        assert_eq!(CacheEntry::u16_to_mv(entry.mv, &game_state), mv);
+
+        //A corrupted entry is caught by the checksum, a truncated file by the header
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[100] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(Cache::load_from(&path, 1).is_err());
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(Cache::load_from(&path, 1).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn excluded_move_bypasses_tt() {
+        let cache = Cache::with_size_threaded(1, 1);
+        let game_state = GameState::standard();
+        let mv = GameMove {
+            from: 12,
+            to: 28,
+            piece_type: PieceType::Pawn,
+            move_type: GameMoveType::Quiet,
+        };
+        let p = CombinedSearchParameters::from(-100, 100, 5, &game_state, 1, 1);
+        let verification = CombinedSearchParameters::from(-100, 100, 5, &game_state, 1, 1).excluding(mv);
+        let mut tt_entry = None;
+        cache.insert(&verification, mv, 50, -100, 20);
+        cache.lookup(&p, &mut tt_entry);
+        assert!(tt_entry.is_none());
+
+        cache.insert(&p, mv, 50, -100, 20);
+        cache.lookup(&verification, &mut tt_entry);
+        assert!(tt_entry.is_none());
+        cache.lookup(&p, &mut tt_entry);
+        assert_eq!(tt_entry.map(|ce| ce.score), Some(50));
+    }
 }
@@ -0,0 +1,80 @@
+use crate::board_representation::game_state::GameMove;
+
+const PIECE_SQUARES: usize = 6 * 64;
+
+//History of quiet moves, indexed by side to move, by piece and target square of an earlier move, and by piece and target square of the move itself.
+//With the previous move it measures how well a move answers it, with our own move before that how well it follows up on it.
+pub struct ContinuationHistory {
+    scores: Vec<isize>,
+}
+
+impl Default for ContinuationHistory {
+    fn default() -> Self {
+        ContinuationHistory {
+            scores: vec![0; 2 * PIECE_SQUARES * PIECE_SQUARES],
+        }
+    }
+}
+
+impl ContinuationHistory {
+    #[inline(always)]
+    fn index(side: usize, earlier: GameMove, mv: GameMove) -> usize {
+        ((side * PIECE_SQUARES + earlier.piece_type as usize * 64 + earlier.to as usize) * PIECE_SQUARES) + mv.piece_type as usize * 64 + mv.to as usize
+    }
+
+    #[inline(always)]
+    pub fn get(&self, side: usize, earlier: Option<GameMove>, mv: GameMove) -> isize {
+        match earlier {
+            Some(earlier) => self.scores[ContinuationHistory::index(side, earlier, mv)],
+            None => 0,
+        }
+    }
+
+    #[inline(always)]
+    pub fn update(&mut self, side: usize, earlier: Option<GameMove>, mv: GameMove, bonus: isize) {
+        if let Some(earlier) = earlier {
+            self.scores[ContinuationHistory::index(side, earlier, mv)] += bonus;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for score in self.scores.iter_mut() {
+            *score = 0;
+        }
+    }
+
+    pub fn age(&mut self) {
+        for score in self.scores.iter_mut() {
+            *score /= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ContinuationHistory;
+    use crate::board_representation::game_state::{GameMove, GameMoveType, PieceType};
+
+    #[test]
+    fn continuation_history_update_and_age() {
+        let mv = |from: u8, to: u8, piece_type: PieceType| GameMove {
+            from,
+            to,
+            piece_type,
+            move_type: GameMoveType::Quiet,
+        };
+        let (e4, e5, nf3) = (mv(12, 28, PieceType::Pawn), mv(52, 36, PieceType::Pawn), mv(6, 21, PieceType::Knight));
+        let mut history = ContinuationHistory::default();
+        history.update(0, Some(e5), nf3, 25);
+        history.update(0, Some(e5), nf3, -9);
+        history.update(0, None, nf3, 100);
+        assert_eq!(history.get(0, Some(e5), nf3), 16);
+        assert_eq!(history.get(1, Some(e5), nf3), 0);
+        assert_eq!(history.get(0, Some(e4), nf3), 0);
+        assert_eq!(history.get(0, None, nf3), 0);
+        history.age();
+        assert_eq!(history.get(0, Some(e5), nf3), 8);
+        history.clear();
+        assert_eq!(history.get(0, Some(e5), nf3), 0);
+    }
+}
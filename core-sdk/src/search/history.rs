@@ -6,6 +6,8 @@ pub struct History {
     pub hist: Vec<u64>,
     pub is_unique: Vec<bool>,
     pub pointer: usize,
+    //Index of the root of the search, hashes before it are from the game history
+    pub root: usize,
 }
 
 impl Default for History {
@@ -14,6 +16,7 @@ impl Default for History {
             hist: vec![0u64; MAX_SEARCH_DEPTH + 100],
             is_unique: vec![false; MAX_SEARCH_DEPTH + 100],
             pointer: 0,
+            root: 0,
         }
     }
 }
@@ -29,12 +32,22 @@ impl History {
         self.pointer -= 1;
     }
 
-    pub fn get_occurences(&self, game_state: &GameState) -> usize {
-        let mut occurences = 0;
+    //Marks everything pushed so far as game history before the root
+    pub fn set_root(&mut self) {
+        self.root = self.pointer;
+    }
+
+    //Earlier occurences of the position since the root and before the root
+    pub fn get_occurences(&self, game_state: &GameState) -> (usize, usize) {
+        let mut occurences = (0, 0);
         let mut index = self.pointer as isize - 1;
         while index >= 0 {
             if self.hist[index as usize] == game_state.get_hash() {
-                occurences += 1;
+                if index as usize >= self.root {
+                    occurences.0 += 1;
+                } else {
+                    occurences.1 += 1;
+                }
             }
             if self.is_unique[index as usize] {
                 break;
@@ -44,3 +57,63 @@ impl History {
         occurences
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+    use crate::board_representation::game_state::GameState;
+    use crate::board_representation::rules::DrawRules;
+    use crate::move_generation::makemove::make_move;
+
+    //Pushes the current position like the search does before playing mv
+    fn play(history: &mut History, state: &GameState, mv: &str) -> GameState {
+        history.push(state.get_hash(), state.get_half_moves() == 0);
+        make_move(state, state.legal_moves().find(|x| format!("{:?}", x) == mv).unwrap())
+    }
+
+    #[test]
+    fn repetitions_before_and_after_root() {
+        let mut history = History::default();
+        let start = GameState::standard();
+        let mut state = start.clone();
+        for mv in ["g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+            state = play(&mut history, &state, mv);
+        }
+        //The root is the start position, which the game already reached once
+        history.set_root();
+        assert_eq!(state.get_hash(), start.get_hash());
+        assert_eq!(history.get_occurences(&state), (0, 1));
+        assert!(!DrawRules::is_repetition_draw(0, 1));
+
+        //Nf3 was only played before the root, a single repetition of it is no draw yet
+        let after_nf3 = play(&mut history, &state, "g1f3");
+        let (in_search, before_root) = history.get_occurences(&after_nf3);
+        assert_eq!((in_search, before_root), (0, 1));
+        assert!(!DrawRules::is_repetition_draw(in_search, before_root));
+        history.pop();
+
+        //Nc3 is new at the root. Repeating it inside the search counts as a draw
+        state = play(&mut history, &state, "b1c3");
+        for mv in ["g8f6", "c3b1", "f6g8"].iter() {
+            state = play(&mut history, &state, mv);
+        }
+        assert_eq!(history.get_occurences(&state), (1, 1));
+        let nc3_again = play(&mut history, &state, "b1c3");
+        let (in_search, before_root) = history.get_occurences(&nc3_again);
+        assert_eq!((in_search, before_root), (1, 0));
+        assert!(DrawRules::is_repetition_draw(in_search, before_root));
+
+        //A third occurence in the game is a draw even without a repetition inside the search
+        let mut game = History::default();
+        let mut state = start.clone();
+        for _ in 0..2 {
+            for mv in ["g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+                state = play(&mut game, &state, mv);
+            }
+        }
+        game.set_root();
+        let (in_search, before_root) = game.get_occurences(&state);
+        assert_eq!((in_search, before_root), (0, 2));
+        assert!(DrawRules::is_repetition_draw(in_search, before_root));
+    }
+}
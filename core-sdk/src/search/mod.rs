@@ -1,16 +1,33 @@
+//Records a decision of the node at `ply` in the search tree, if tree logging is compiled in
+macro_rules! tree_event {
+    ($thread:expr, $ply:expr, $($event:tt)+) => {
+        #[cfg(feature = "search-tree")]
+        {
+            use crate::search::tree::TreeEvent::*;
+            $thread.search_tree.event($ply, $($event)+);
+        }
+    };
+}
+
 pub mod alphabeta;
 pub mod cache;
+pub mod continuation;
 pub mod history;
 pub mod moveordering;
+pub mod parameters;
 pub mod quiescence;
 pub mod reserved_memory;
 pub mod searcher;
+pub mod see;
 pub mod statistics;
 pub mod timecontrol;
+#[cfg(feature = "search-tree")]
+pub mod tree;
 
 use crate::board_representation::game_state::*;
+use crate::board_representation::rules::DrawRules;
+use crate::evaluation::eval_game_state;
 use crate::search::searcher::Thread;
-use crate::search::timecontrol::TimeControlInformation;
 use history::History;
 use std::fmt::{Display, Formatter, Result};
 
@@ -18,6 +35,10 @@ pub const MAX_SEARCH_DEPTH: usize = 100;
 pub const MATE_SCORE: i16 = 15000;
 pub const MATED_IN_MAX: i16 = -14000;
 pub const STANDARD_SCORE: i16 = -32767;
+//Contempt is kept far away from mate scores, so draw scores pass the mate adjustments of the TT unchanged
+pub const DEFAULT_CONTEMPT: i16 = 0;
+pub const MIN_CONTEMPT: i16 = -100;
+pub const MAX_CONTEMPT: i16 = 100;
 
 pub struct CombinedSearchParameters<'a> {
     pub alpha: i16,
@@ -26,6 +47,8 @@ pub struct CombinedSearchParameters<'a> {
     pub game_state: &'a GameState,
     pub color: i16,
     pub current_depth: usize,
+    //Set in singular extension verification searches. The move isn't searched and the node is kept out of the TT
+    pub excluded_move: Option<GameMove>,
 }
 
 impl<'a> CombinedSearchParameters<'a> {
@@ -37,8 +60,14 @@ impl<'a> CombinedSearchParameters<'a> {
             game_state,
             color,
             current_depth,
+            excluded_move: None,
         }
     }
+
+    pub fn excluding(mut self, mv: GameMove) -> Self {
+        self.excluded_move = Some(mv);
+        self
+    }
 }
 
 pub enum SearchInstruction {
@@ -47,6 +76,36 @@ pub enum SearchInstruction {
     StopSearching(i16),
 }
 
+//Whether a root score is exact or the result of a fail high/low, as reported to UCI
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ScoreBound {
+    Exact,
+    LowerBound,
+    UpperBound,
+}
+
+impl ScoreBound {
+    pub fn from_window(score: i16, alpha: i16, beta: i16) -> ScoreBound {
+        if score <= alpha {
+            ScoreBound::UpperBound
+        } else if score >= beta {
+            ScoreBound::LowerBound
+        } else {
+            ScoreBound::Exact
+        }
+    }
+}
+
+impl Display for ScoreBound {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        match self {
+            ScoreBound::Exact => Ok(()),
+            ScoreBound::LowerBound => write!(formatter, " lowerbound"),
+            ScoreBound::UpperBound => write!(formatter, " upperbound"),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ScoredPrincipalVariation {
     pub score: i16,
@@ -90,10 +149,37 @@ impl Display for PrincipalVariation {
 #[derive(Copy, Clone)]
 pub struct GradedMove(pub GameMove, pub Option<f64>);
 
+//Score of a draw for either side to move. With positive contempt the side to move at the root values a draw like being contempt centipawns behind.
+//Draw scores end up in the transposition table like any other score, so entries keep the contempt and root side of the search which stored them.
+//This is consistent during a game, where the engine always searches for the same side; analysing both sides with nonzero contempt
+//without clearing the table can reuse draw scores of the other side.
+pub fn draw_scores(root_side: usize, contempt: i16) -> [i16; 2] {
+    debug_assert!((MIN_CONTEMPT..=MAX_CONTEMPT).contains(&contempt));
+    let mut scores = [contempt; 2];
+    scores[root_side] = -contempt;
+    scores
+}
+
+//Static evaluation from white's point of view. Positions the evaluation knows to be drawn get the draw score of the side to move
 #[inline(always)]
-pub fn leaf_score(game_status: GameResult, color: i16, current_depth: i16) -> i16 {
+pub fn static_evaluation(game_state: &GameState, thread: &Thread) -> i16 {
+    let evaluation = eval_game_state(game_state);
+    if evaluation.is_guaranteed_draw {
+        let draw_score = thread.draw_score(game_state);
+        if game_state.get_color_to_move() == WHITE {
+            draw_score
+        } else {
+            -draw_score
+        }
+    } else {
+        evaluation.final_eval
+    }
+}
+
+#[inline(always)]
+pub fn leaf_score(game_status: GameResult, color: i16, current_depth: i16, draw_score: i16) -> i16 {
     if game_status == GameResult::Draw {
-        return 0;
+        return draw_score;
     } else if game_status == GameResult::WhiteWin {
         return (MATE_SCORE - current_depth) * color;
     } else if game_status == GameResult::BlackWin {
@@ -102,39 +188,26 @@ pub fn leaf_score(game_status: GameResult, color: i16, current_depth: i16) -> i1
     panic!("Invalid Leaf");
 }
 
-//Doesn't actually check for stalemate
+//Stalemate and checkmate need the legal moves of a node and are scored by check_end_condition
 #[inline(always)]
-pub fn check_for_draw(game_state: &GameState, history: &History) -> SearchInstruction {
-    if game_state.get_piece_bb(PieceType::Pawn) | game_state.get_piece_bb(PieceType::Rook) | game_state.get_piece_bb(PieceType::Queen) == 0u64
-        && (game_state.get_piece(PieceType::Knight, WHITE) | game_state.get_piece(PieceType::Bishop, WHITE)).count_ones() <= 1
-        && (game_state.get_piece(PieceType::Knight, BLACK) | game_state.get_piece(PieceType::Bishop, BLACK)).count_ones() <= 1
-    {
-        return SearchInstruction::StopSearching(0);
+pub fn check_for_draw(game_state: &GameState, history: &History, draw_score: i16) -> SearchInstruction {
+    if DrawRules::is_dead_position(game_state) || DrawRules::is_fifty_move_draw(game_state) {
+        return SearchInstruction::StopSearching(draw_score);
     }
-
-    if game_state.get_half_moves() >= 100 {
-        return SearchInstruction::StopSearching(0);
-    }
-
-    if history.get_occurences(game_state) >= 1 {
-        return SearchInstruction::StopSearching(0);
+    let (in_search, before_root) = history.get_occurences(game_state);
+    if DrawRules::is_repetition_draw(in_search, before_root) {
+        return SearchInstruction::StopSearching(draw_score);
     }
     SearchInstruction::ContinueSearching
 }
 
 #[inline(always)]
 pub fn check_end_condition(game_state: &GameState, has_legal_moves: bool, in_check: bool) -> GameResult {
-    if in_check && !has_legal_moves {
-        if game_state.get_color_to_move() == WHITE {
-            return GameResult::BlackWin;
-        } else {
-            return GameResult::WhiteWin;
-        }
+    if has_legal_moves {
+        GameResult::Ingame
+    } else {
+        game_state.no_legal_moves_result(in_check)
     }
-    if !in_check && !has_legal_moves {
-        return GameResult::Draw;
-    }
-    GameResult::Ingame
 }
 
 #[inline(always)]
@@ -161,20 +234,47 @@ pub fn concatenate_pv(at_depth: usize, thread: &mut Thread) {
 
 #[inline(always)]
 pub fn checkup(thread: &mut Thread) {
-    if (thread.id == 0
-        && thread.tc.time_over(
-            thread.itcs.get_time_elapsed(),
-            &TimeControlInformation {
-                time_saved: thread.time_saved,
-                stable_pv: thread.itcs.stable_pv.load(std::sync::atomic::Ordering::Relaxed),
-            },
-            thread.uci_options.move_overhead,
-        ))
+    if (thread.id == 0 && (thread.time_manager.tc.nodes_over(thread.search_statistics.nodes_searched) || thread.time_manager.time_over(thread.itcs.get_time_elapsed())))
         || *thread.itcs.timeout_flag.read().expect("Reading posioned timeoutflag")
     {
         if thread.id == 0 {
             *thread.itcs.timeout_flag.write().expect("Writing poisoned timeoutflag") = true;
         }
         thread.self_stop = true;
+    } else if thread.id == 0 && !thread.uci_options.quiet {
+        thread.itcs.report_progress(thread.root_depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_for_draw, draw_scores, SearchInstruction};
+    use crate::board_representation::game_state::{GameState, BLACK, WHITE};
+    use crate::move_generation::makemove::make_move;
+    use crate::search::history::History;
+
+    #[test]
+    fn repetition_draws_score_contempt_for_each_side() {
+        let contempt = 20;
+        for &root_side in [WHITE, BLACK].iter() {
+            let scores = draw_scores(root_side, contempt);
+            //Draws are worth -contempt to the side to move at the root and contempt to its opponent
+            let expected = |side: usize| if side == root_side { -contempt } else { contempt };
+            let mut history = History::default();
+            history.set_root();
+            let mut state = GameState::standard();
+            let mut draw_scores_found = Vec::new();
+            for mv in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3"].iter() {
+                history.push(state.get_hash(), state.get_half_moves() == 0);
+                state = make_move(&state, state.legal_moves().find(|x| format!("{:?}", x) == *mv).unwrap());
+                let side = state.get_color_to_move();
+                if let SearchInstruction::StopSearching(score) = check_for_draw(&state, &history, scores[side]) {
+                    assert_eq!(score, expected(side));
+                    draw_scores_found.push(score);
+                }
+            }
+            //The start position with white to move, then the position after Nf3 with black to move, repeat
+            assert_eq!(draw_scores_found, vec![expected(WHITE), expected(BLACK)]);
+        }
     }
 }
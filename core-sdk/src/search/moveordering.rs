@@ -1,5 +1,5 @@
 use crate::board_representation::game_state::{GameMove, PieceType};
-use crate::move_generation::movegen;
+use crate::move_generation::movegen::{self, GenerationType, MoveList};
 use crate::search::moveordering::MoveOrderingStage::{BadCapture, GoodCapture, GoodCaptureInitialization, Killer, PVMove, Quiet, QuietInitialization, TTMove};
 use crate::search::quiescence::{see, PIECE_VALUES};
 use crate::search::searcher::Thread;
@@ -9,6 +9,11 @@ use crate::search::{CombinedSearchParameters, GradedMove};
 pub const ATTACKER_VALUE: [i16; 6] = [0, 1, 2, 3, 4, 5];
 pub const TARGET_VALUE: [i16; 5] = [100, 400, 400, 650, 1100];
 
+//Quiet move ordering, in units of the butterfly history ratio
+pub const COUNTER_MOVE_BONUS: f64 = 2.;
+pub const CONTINUATION_HISTORY_WEIGHT: f64 = 1.;
+pub const CONTINUATION_HISTORY_SCALE: f64 = 512.;
+
 pub fn mvvlva(mv: GameMove) -> i16 {
     debug_assert!(mv.is_capture());
     TARGET_VALUE[mv.get_captured_piece() as usize] - ATTACKER_VALUE[mv.piece_type as usize]
@@ -32,9 +37,45 @@ pub struct MoveOrderer {
     pub stage: usize,
     pub stages: &'static [MoveOrderingStage],
     pub gen_only_captures: bool,
+    //Evasions are generated all at once, so the quiet stages don't generate anything
+    pub in_check: bool,
+    pub killer_index: usize,
+    //Moves which have been returned before their generation stage, and need to be removed from the generated list
+    pub early_moves: [Option<GameMove>; 4],
 }
 
 impl MoveOrderer {
+    pub fn normal(in_check: bool) -> Self {
+        MoveOrderer {
+            stage: 0,
+            stages: &NORMAL_STAGES,
+            gen_only_captures: false,
+            in_check,
+            killer_index: 0,
+            early_moves: [None; 4],
+        }
+    }
+
+    pub fn quiescence() -> Self {
+        MoveOrderer {
+            stage: 0,
+            stages: &QUIESCENCE_STAGES,
+            gen_only_captures: true,
+            in_check: false,
+            killer_index: 0,
+            early_moves: [None; 4],
+        }
+    }
+
+    fn remove_early_moves(&self, movelist: &mut MoveList) {
+        for mv in self.early_moves.iter().flatten() {
+            let mv_index = movelist.find_move(*mv, false);
+            if mv_index < movelist.move_list.len() {
+                movelist.move_list.remove(mv_index);
+            }
+        }
+    }
+
     pub fn next(
         &mut self,
         thread: &mut Thread,
@@ -49,6 +90,7 @@ impl MoveOrderer {
         match self.stages[self.stage] {
             MoveOrderingStage::PVMove => {
                 self.stage += 1;
+                self.early_moves[0] = pv_table_move;
                 if pv_table_move.is_some() && p.game_state.is_valid_tt_move(pv_table_move.unwrap()) {
                     Some((pv_table_move.unwrap(), 0.))
                 } else {
@@ -57,6 +99,7 @@ impl MoveOrderer {
             }
             MoveOrderingStage::TTMove => {
                 self.stage += 1;
+                self.early_moves[1] = tt_move;
                 if tt_move.is_some() && tt_move != pv_table_move && p.game_state.is_valid_tt_move(tt_move.unwrap()) {
                     Some((tt_move.unwrap(), 0.))
                 } else {
@@ -64,22 +107,18 @@ impl MoveOrderer {
                 }
             }
             MoveOrderingStage::GoodCaptureInitialization => {
-                //Generate moves first!
-                movegen::generate_moves(&p.game_state, self.gen_only_captures, &mut thread.movelist.move_lists[p.current_depth]);
+                //Generate captures and promotions first. Quiets are only generated if no earlier move caused a cutoff
+                let gen_type = if self.gen_only_captures {
+                    GenerationType::Captures
+                } else if self.in_check {
+                    GenerationType::Evasions
+                } else {
+                    GenerationType::Tactical
+                };
                 let our_mvlist = &mut thread.movelist.move_lists[p.current_depth];
-
-                if let Some(pv_move) = pv_table_move {
-                    let mv_index = our_mvlist.find_move(pv_move, false);
-                    if mv_index < our_mvlist.move_list.len() {
-                        our_mvlist.move_list.remove(mv_index);
-                    }
-                }
-                if let Some(tt_move) = tt_move {
-                    let mv_index = our_mvlist.find_move(tt_move, false);
-                    if mv_index < our_mvlist.move_list.len() {
-                        our_mvlist.move_list.remove(mv_index);
-                    }
-                }
+                our_mvlist.move_list.clear();
+                movegen::append_moves(p.game_state, gen_type, our_mvlist);
+                self.remove_early_moves(our_mvlist);
 
                 //Give any capture move in movelist its MVV-LVA score
                 for mv in our_mvlist.move_list.iter_mut() {
@@ -119,33 +158,45 @@ impl MoveOrderer {
             MoveOrderingStage::Killer => {
                 debug_assert!(thread.killer_moves[p.current_depth][0].is_none() || !thread.killer_moves[p.current_depth][0].unwrap().is_capture());
                 debug_assert!(thread.killer_moves[p.current_depth][1].is_none() || !thread.killer_moves[p.current_depth][1].unwrap().is_capture());
-                let our_list = &mut thread.movelist.move_lists[p.current_depth];
-                let mut found_index = our_list.move_list.len();
-                for (index, gmv) in our_list.move_list.iter().enumerate() {
-                    if gmv.1.is_none() && (Some(gmv.0) == thread.killer_moves[p.current_depth][0] || Some(gmv.0) == thread.killer_moves[p.current_depth][1]) {
-                        found_index = index;
-                        break;
+                //Killers are checked for legality instead of being looked up in the quiet moves, which aren't generated yet
+                while self.killer_index < 2 {
+                    let killer = thread.killer_moves[p.current_depth][self.killer_index];
+                    self.killer_index += 1;
+                    if let Some(mv) = killer {
+                        if !self.early_moves.contains(&killer) && p.game_state.is_valid_tt_move(mv) {
+                            self.early_moves[1 + self.killer_index] = killer;
+                            //Quiet promotions and evasions are already in the list
+                            let our_list = &mut thread.movelist.move_lists[p.current_depth];
+                            let mv_index = our_list.find_move(mv, false);
+                            if mv_index < our_list.move_list.len() {
+                                our_list.move_list.remove(mv_index);
+                            }
+                            return Some((mv, 0.));
+                        }
                     }
                 }
-                if found_index < our_list.move_list.len() {
-                    let res = our_list.move_list[found_index].0;
-                    our_list.move_list.remove(found_index);
-                    Some((res, 0.))
-                } else {
-                    self.stage += 1;
-                    self.next(thread, p, None, None, search_quiets)
-                }
+                self.stage += 1;
+                self.next(thread, p, None, None, search_quiets)
             }
             MoveOrderingStage::QuietInitialization => {
                 if search_quiets {
+                    if !self.in_check {
+                        let our_list = &mut thread.movelist.move_lists[p.current_depth];
+                        movegen::append_moves(p.game_state, GenerationType::Quiets, our_list);
+                        self.remove_early_moves(our_list);
+                    }
+                    let side = p.game_state.get_color_to_move();
+                    let counter_move = thread.counter_move(p.current_depth, side);
+                    let earlier = [thread.previous_move(p.current_depth, 1), thread.previous_move(p.current_depth, 2)];
                     for mv in thread.movelist.move_lists[p.current_depth].move_list.iter_mut() {
                         if mv.1.is_none() {
                             debug_assert!(!mv.0.is_capture());
-                            mv.1 = Some(
-                                thread.hh_score[p.game_state.get_color_to_move()][mv.0.from as usize][mv.0.to as usize] as f64
-                                    / thread.bf_score[p.game_state.get_color_to_move()][mv.0.from as usize][mv.0.to as usize] as f64
-                                    / 1000.0,
-                            );
+                            let butterfly = thread.hh_score[side][mv.0.from as usize][mv.0.to as usize] as f64 / thread.bf_score[side][mv.0.from as usize][mv.0.to as usize] as f64;
+                            let continuation = (thread.continuation_history[0].get(side, earlier[0], mv.0) + thread.continuation_history[1].get(side, earlier[1], mv.0)) as f64;
+                            //Quiet scores have to stay positive, negative scores mark bad captures
+                            let continuation = CONTINUATION_HISTORY_WEIGHT * (1. + (continuation / CONTINUATION_HISTORY_SCALE).tanh());
+                            let counter = if counter_move == Some(mv.0) { COUNTER_MOVE_BONUS } else { 0. };
+                            mv.1 = Some((butterfly + continuation + counter) / 1000.0);
                         }
                     }
                 }
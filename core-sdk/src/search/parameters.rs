@@ -0,0 +1,263 @@
+use crate::search::alphabeta::*;
+use crate::search::quiescence::DELTA_PRUNING;
+
+//Search constants which can be changed at runtime, e.g. for tuning them. The defaults are the constants of the search modules
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchParameters {
+    pub futility_margin: i16,
+    pub futility_depth: i16,
+    pub static_null_move_margin: i16,
+    pub static_null_move_depth: i16,
+    pub null_move_pruning_depth: i16,
+    pub history_pruning_depth: i16,
+    pub history_pruning_threshold: i16,
+    pub lmp_depth: i16,
+    pub see_pruning_depth: i16,
+    pub see_pruning_capture_mult: i16,
+    pub see_pruning_quiet_mult: i16,
+    pub delta_pruning: i16,
+    pub razoring_depth: i16,
+    pub razoring_margin: i16,
+    pub probcut_depth: i16,
+    pub probcut_margin: i16,
+    pub probcut_reduction: i16,
+    pub singular_extension_depth: i16,
+    pub singular_tt_depth_margin: i16,
+    //Per ply of depth, how far below the TT score the other moves have to stay for the TT move to be singular
+    pub singular_margin: i16,
+}
+
+impl Default for SearchParameters {
+    fn default() -> Self {
+        SearchParameters {
+            futility_margin: DEFAULT_FUTILITY_MARGIN,
+            futility_depth: FUTILITY_DEPTH,
+            static_null_move_margin: STATIC_NULL_MOVE_MARGIN,
+            static_null_move_depth: STATIC_NULL_MOVE_DEPTH,
+            null_move_pruning_depth: NULL_MOVE_PRUNING_DEPTH,
+            history_pruning_depth: HISTORY_PRUNING_DEPTH,
+            history_pruning_threshold: HISTORY_PRUNING_THRESHOLD,
+            lmp_depth: LMP_DEPTH,
+            see_pruning_depth: SEE_PRUNING_DEPTH,
+            see_pruning_capture_mult: SEE_PRUNING_CAPTURE_MULT,
+            see_pruning_quiet_mult: SEE_PRUNING_QUIET_MULT,
+            delta_pruning: DELTA_PRUNING,
+            razoring_depth: RAZORING_DEPTH,
+            razoring_margin: RAZORING_MARGIN,
+            probcut_depth: PROBCUT_DEPTH,
+            probcut_margin: PROBCUT_MARGIN,
+            probcut_reduction: PROBCUT_REDUCTION,
+            singular_extension_depth: SINGULAR_EXTENSION_DEPTH,
+            singular_tt_depth_margin: SINGULAR_TT_DEPTH_MARGIN,
+            singular_margin: DEFAULT_SINGULAR_MARGIN,
+        }
+    }
+}
+
+//A search parameter as UCI spin option
+pub struct SearchParameter {
+    pub name: &'static str,
+    pub min: i16,
+    pub max: i16,
+    pub get: fn(&SearchParameters) -> i16,
+    pub set: fn(&mut SearchParameters, i16),
+}
+
+impl SearchParameter {
+    pub fn default_value(&self) -> i16 {
+        (self.get)(&SearchParameters::default())
+    }
+}
+
+pub static SEARCH_PARAMETERS: [SearchParameter; 20] = [
+    SearchParameter {
+        name: "FutilityMargin",
+        min: MIN_FUTILITY_MARGIN,
+        max: MAX_FUTILITY_MARGIN,
+        get: |p| p.futility_margin,
+        set: |p, v| p.futility_margin = v,
+    },
+    SearchParameter {
+        name: "FutilityDepth",
+        min: 0,
+        max: 12,
+        get: |p| p.futility_depth,
+        set: |p, v| p.futility_depth = v,
+    },
+    SearchParameter {
+        name: "StaticNullMoveMargin",
+        min: 20,
+        max: 400,
+        get: |p| p.static_null_move_margin,
+        set: |p, v| p.static_null_move_margin = v,
+    },
+    SearchParameter {
+        name: "StaticNullMoveDepth",
+        min: 0,
+        max: 12,
+        get: |p| p.static_null_move_depth,
+        set: |p, v| p.static_null_move_depth = v,
+    },
+    SearchParameter {
+        name: "NullMovePruningDepth",
+        min: 1,
+        max: 12,
+        get: |p| p.null_move_pruning_depth,
+        set: |p, v| p.null_move_pruning_depth = v,
+    },
+    SearchParameter {
+        name: "HistoryPruningDepth",
+        min: 0,
+        max: 8,
+        get: |p| p.history_pruning_depth,
+        set: |p, v| p.history_pruning_depth = v,
+    },
+    SearchParameter {
+        name: "HistoryPruningThreshold",
+        min: -4000,
+        max: 4000,
+        get: |p| p.history_pruning_threshold,
+        set: |p, v| p.history_pruning_threshold = v,
+    },
+    SearchParameter {
+        name: "LmpDepth",
+        min: 0,
+        max: 8,
+        get: |p| p.lmp_depth,
+        set: |p, v| p.lmp_depth = v,
+    },
+    SearchParameter {
+        name: "SeePruningDepth",
+        min: 0,
+        max: 16,
+        get: |p| p.see_pruning_depth,
+        set: |p, v| p.see_pruning_depth = v,
+    },
+    SearchParameter {
+        name: "SeePruningCaptureMult",
+        min: -200,
+        max: 0,
+        get: |p| p.see_pruning_capture_mult,
+        set: |p, v| p.see_pruning_capture_mult = v,
+    },
+    SearchParameter {
+        name: "SeePruningQuietMult",
+        min: -50,
+        max: 0,
+        get: |p| p.see_pruning_quiet_mult,
+        set: |p, v| p.see_pruning_quiet_mult = v,
+    },
+    SearchParameter {
+        name: "DeltaPruning",
+        min: 0,
+        max: 500,
+        get: |p| p.delta_pruning,
+        set: |p, v| p.delta_pruning = v,
+    },
+    SearchParameter {
+        name: "RazoringDepth",
+        min: 0,
+        max: 6,
+        get: |p| p.razoring_depth,
+        set: |p, v| p.razoring_depth = v,
+    },
+    SearchParameter {
+        name: "RazoringMargin",
+        min: 50,
+        max: 1000,
+        get: |p| p.razoring_margin,
+        set: |p, v| p.razoring_margin = v,
+    },
+    SearchParameter {
+        name: "ProbCutDepth",
+        min: 2,
+        max: 16,
+        get: |p| p.probcut_depth,
+        set: |p, v| p.probcut_depth = v,
+    },
+    SearchParameter {
+        name: "ProbCutMargin",
+        min: 0,
+        max: 500,
+        get: |p| p.probcut_margin,
+        set: |p, v| p.probcut_margin = v,
+    },
+    SearchParameter {
+        name: "ProbCutReduction",
+        min: 1,
+        max: 8,
+        get: |p| p.probcut_reduction,
+        set: |p, v| p.probcut_reduction = v,
+    },
+    SearchParameter {
+        name: "SingularExtensionDepth",
+        //Below this the verification search would drop into quiescence search, which ignores the excluded move
+        min: 3,
+        max: 20,
+        get: |p| p.singular_extension_depth,
+        set: |p, v| p.singular_extension_depth = v,
+    },
+    SearchParameter {
+        name: "SingularTTDepthMargin",
+        min: 0,
+        max: 8,
+        get: |p| p.singular_tt_depth_margin,
+        set: |p, v| p.singular_tt_depth_margin = v,
+    },
+    SearchParameter {
+        name: "SingularMargin",
+        min: MIN_SINGULAR_MARGIN,
+        max: MAX_SINGULAR_MARGIN,
+        get: |p| p.singular_margin,
+        set: |p, v| p.singular_margin = v,
+    },
+];
+
+//Index of the parameter in SEARCH_PARAMETERS, names are case insensitive like all UCI option names
+pub fn find_search_parameter(name: &str) -> Option<usize> {
+    SEARCH_PARAMETERS.iter().position(|parameter| parameter.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_search_parameter, SearchParameters, SEARCH_PARAMETERS};
+    use crate::board_representation::game_state::GameState;
+    use crate::search::searcher::{search_move, InterThreadCommunicationSystem};
+    use crate::search::timecontrol::TimeControl;
+    use std::sync::Arc;
+
+    #[test]
+    fn search_parameter_registry() {
+        let mut parameters = SearchParameters::default();
+        for (index, parameter) in SEARCH_PARAMETERS.iter().enumerate() {
+            assert_eq!(find_search_parameter(&parameter.name.to_uppercase()), Some(index));
+            assert!(parameter.min <= parameter.default_value() && parameter.default_value() <= parameter.max);
+            (parameter.set)(&mut parameters, parameter.max);
+            assert_eq!((parameter.get)(&parameters), parameter.max);
+        }
+        //Every parameter is backed by its own field
+        for parameter in SEARCH_PARAMETERS.iter() {
+            assert_eq!((parameter.get)(&parameters), parameter.max);
+        }
+        assert_eq!(find_search_parameter("NoSuchParameter"), None);
+    }
+
+    //Every combination of the ranges has to keep the verification searches at a valid depth
+    #[test]
+    fn search_parameter_extremes() {
+        let mut parameters = SearchParameters::default();
+        for (name, value) in [("ProbCutDepth", 2), ("ProbCutReduction", 8), ("SingularExtensionDepth", 3), ("SingularTTDepthMargin", 8)].iter() {
+            let parameter = &SEARCH_PARAMETERS[find_search_parameter(name).unwrap()];
+            (parameter.set)(&mut parameters, *value);
+        }
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+        {
+            let mut uci_options = itcs.uci_options.write().unwrap();
+            uci_options.search_parameters = parameters;
+            uci_options.quiet = true;
+        }
+        let state = GameState::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        assert!(search_move(itcs, 8, state, Vec::new(), TimeControl::Infinite).is_some());
+    }
+}
@@ -1,17 +1,32 @@
 use super::super::board_representation::game_state::{GameMove, GameMoveType, GameState, PieceType, BLACK, WHITE};
-use super::super::evaluation::eval_game_state;
 use super::super::move_generation::movegen;
 use super::alphabeta::*;
 use super::*;
 use crate::bitboards::bitboards::constants::{KING_ATTACKS, KNIGHT_ATTACKS, RANKS};
 use crate::move_generation::makemove::make_move;
 use crate::search::cache::CacheEntry;
-use crate::search::moveordering::{MoveOrderer, QUIESCENCE_STAGES};
+use crate::search::moveordering::MoveOrderer;
 
 pub const DELTA_PRUNING: i16 = 100;
 pub const PIECE_VALUES: [i16; 6] = [100, 400, 400, 650, 1100, 30000];
 
-pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
+#[inline(always)]
+pub fn q_search(p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
+    #[cfg(feature = "search-tree")]
+    {
+        let mv = if p.current_depth > 0 { thread.current_moves[p.current_depth - 1] } else { None };
+        let entered = thread.search_tree.enter(p.current_depth, mv, p.alpha, p.beta, p.depth_left, true);
+        let score = q_search_node(p, thread);
+        if entered {
+            thread.search_tree.leave(score);
+        }
+        score
+    }
+    #[cfg(not(feature = "search-tree"))]
+    q_search_node(p, thread)
+}
+
+fn q_search_node(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
     //Step 0. Prepare variables
     thread.search_statistics.add_q_node(p.current_depth);
     clear_pv(p.current_depth, thread);
@@ -22,22 +37,25 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
     }
 
     //Step 2. Max search-depth reached
-    if let SearchInstruction::StopSearching(res) = max_depth(&p) {
+    if let SearchInstruction::StopSearching(res) = max_depth(&p, thread) {
         return res;
     }
 
     //Step 3. Check for draw
-    if let SearchInstruction::StopSearching(res) = check_for_draw(p.game_state, &thread.history) {
+    if let SearchInstruction::StopSearching(res) = check_for_draw(p.game_state, &thread.history, thread.draw_score(p.game_state)) {
         return res;
     }
 
     //Step 5. Get standing pat when not in check
-    let stand_pat = eval_game_state(&p.game_state).final_eval * p.color;
+    let stand_pat = static_evaluation(&p.game_state, thread) * p.color;
+    let delta_margin = thread.uci_options.search_parameters.delta_pruning;
 
     //Step 6. Preliminary pruning
     if let SearchInstruction::StopSearching(res) = adjust_standpat(&mut p, stand_pat) {
+        tree_event!(thread, p.current_depth, StandPat);
         return res;
-    } else if let SearchInstruction::StopSearching(res) = delta_pruning(&p, stand_pat) {
+    } else if let SearchInstruction::StopSearching(res) = delta_pruning(&p, stand_pat, delta_margin) {
+        tree_event!(thread, p.current_depth, DeltaPruning);
         return res;
     }
 
@@ -49,6 +67,7 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
             {
                 thread.search_statistics.add_cache_hit_aj_replace_ns();
             }
+            tree_event!(thread, p.current_depth, TtCutoff);
             return res;
         }
     }
@@ -75,25 +94,23 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
     let mut current_max_score = stand_pat;
 
     let mut has_pv = false;
-    let mut move_orderer = MoveOrderer {
-        stage: 0,
-        stages: &QUIESCENCE_STAGES,
-        gen_only_captures: true,
-    };
+    let mut move_orderer = MoveOrderer::quiescence();
 
-    //let mut index = 0;
+    #[cfg(feature = "search-statistics")]
+    let mut index = 0;
     loop {
         let mv = move_orderer.next(thread, &p, None, tt_move, false);
         if mv.is_none() {
             break;
         }
-        //index += 1;
         let (capture_move, _) = mv.unwrap();
-        if !passes_delta_pruning(capture_move, p.game_state.get_phase().phase, stand_pat, p.alpha) {
+        if !passes_delta_pruning(capture_move, p.game_state.get_phase().phase, stand_pat, p.alpha, delta_margin) {
+            tree_event!(thread, p.current_depth, DeltaPruningMove(format!("{:?}", capture_move)));
             continue;
         }
         debug_assert!(capture_move.is_capture());
         let next_g = make_move(p.game_state, capture_move);
+        thread.current_moves[p.current_depth] = Some(capture_move);
         //Step 8.4. Search move
         let score = -q_search(
             CombinedSearchParameters::from(-p.beta, -p.alpha, p.depth_left - 1, &next_g, -p.color, p.current_depth + 1),
@@ -115,6 +132,10 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
             }
             break;
         }
+        #[cfg(feature = "search-statistics")]
+        {
+            index += 1;
+        }
 
         //Step 8.7 Raise alpha if score > alpha
         if score > p.alpha {
@@ -156,8 +177,8 @@ pub fn adjust_standpat(p: &mut CombinedSearchParameters, stand_pat: i16) -> Sear
 }
 
 #[inline(always)]
-pub fn delta_pruning(p: &CombinedSearchParameters, stand_pat: i16) -> SearchInstruction {
-    let diff = p.alpha - stand_pat - DELTA_PRUNING;
+pub fn delta_pruning(p: &CombinedSearchParameters, stand_pat: i16, delta_margin: i16) -> SearchInstruction {
+    let diff = p.alpha - stand_pat - delta_margin;
     if diff > 0 && best_move_value(p.game_state) < diff {
         SearchInstruction::StopSearching(stand_pat)
     } else {
@@ -182,7 +203,7 @@ pub fn best_move_value(state: &GameState) -> i16 {
 }
 
 #[inline(always)]
-pub fn passes_delta_pruning(capture_move: GameMove, phase: f32, eval: i16, alpha: i16) -> bool {
+pub fn passes_delta_pruning(capture_move: GameMove, phase: f32, eval: i16, alpha: i16, delta_margin: i16) -> bool {
     if phase == 0.0 || eval >= alpha {
         return true;
     }
@@ -194,7 +215,7 @@ pub fn passes_delta_pruning(capture_move: GameMove, phase: f32, eval: i16, alpha
         GameMoveType::EnPassant => &PieceType::Pawn,
         _ => panic!("No capture!"),
     };
-    eval + captured_piece.to_piece_score().interpolate(phase) + DELTA_PRUNING >= alpha
+    eval + captured_piece.to_piece_score().interpolate(phase) + delta_margin >= alpha
 }
 
 #[inline(always)]
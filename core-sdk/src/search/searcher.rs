@@ -1,21 +1,24 @@
 use super::alphabeta::principal_variation_search;
 use super::cache::Cache;
+use super::continuation::ContinuationHistory;
 use super::history::History;
 use super::statistics::SearchStatistics;
-use super::timecontrol::TimeControl;
+use super::timecontrol::{TimeControl, TimeManager};
+#[cfg(feature = "search-tree")]
+use super::tree::SearchTree;
 use super::GameMove;
 use super::PrincipalVariation;
 use super::MATED_IN_MAX;
 use super::MAX_SEARCH_DEPTH;
 use crate::board_representation::game_state::{GameState, WHITE};
+use crate::evaluation::wdl::WdlModel;
 //use crate::logging::log;
 use crate::move_generation::makemove::make_move;
 use crate::move_generation::movegen::{generate_moves, MoveList};
 use crate::search::reserved_memory::ReservedMoveList;
-use crate::search::{CombinedSearchParameters, ScoredPrincipalVariation, MATE_SCORE};
-use crate::UCIOptions;
+use crate::search::{draw_scores, CombinedSearchParameters, ScoreBound, ScoredPrincipalVariation, MATE_SCORE};
+use crate::{Protocol, UCIOptions};
 use std::cell::UnsafeCell;
-use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
@@ -32,6 +35,12 @@ pub const DEFAULT_THREADS: usize = 1;
 pub const MAX_THREADS: usize = 65536;
 pub const MIN_THREADS: usize = 1;
 
+pub const DEFAULT_CURRMOVE_DELAY: u64 = 1000;
+pub const MIN_CURRMOVE_DELAY: u64 = 0;
+pub const MAX_CURRMOVE_DELAY: u64 = 60_000;
+//Milliseconds between two info lines that only report search progress
+pub const INFO_REPORT_INTERVAL: u64 = 1000;
+
 #[derive(Copy, Clone)]
 pub enum DepthInformation {
     FullySearched,
@@ -42,7 +51,7 @@ pub enum DepthInformation {
 pub struct InterThreadCommunicationSystem {
     pub uci_options: RwLock<UCIOptions>,
     pub best_pv: Mutex<ScoredPrincipalVariation>,
-    pub stable_pv: AtomicBool,
+    pub best_move_changes: AtomicUsize, //Since the main thread last finished an iteration
     pub depth_info: Mutex<[DepthInformation; MAX_SEARCH_DEPTH]>,
     pub start_time: RwLock<Instant>,                //Only used for reporting
     pub nodes_searched: UnsafeCell<Vec<AtomicU64>>, // Only used for reporting
@@ -50,9 +59,13 @@ pub struct InterThreadCommunicationSystem {
     pub cache: UnsafeCell<Cache>,                   //Only used for reporting
     pub cache_status: AtomicUsize,
     pub last_cache_status: Mutex<Option<Instant>>,
+    pub last_info_report: AtomicU64, //Time of the last progress report, in ms after the start of the search
     pub timeout_flag: RwLock<bool>,
-    pub saved_time: AtomicU64,
     pub tx: RwLock<Vec<Sender<ThreadInstruction>>>,
+    #[cfg(feature = "search-tree")]
+    pub search_tree: Mutex<SearchTree>, //Handed to the main thread for the next search, and back after it
+    #[cfg(feature = "search-statistics")]
+    pub search_statistics: Mutex<SearchStatistics>, //Of every thread, complete once the search finished
     rx_f: Receiver<()>,
     tx_f: Sender<()>,
 }
@@ -63,17 +76,21 @@ impl Default for InterThreadCommunicationSystem {
         InterThreadCommunicationSystem {
             uci_options: RwLock::new(UCIOptions::default()),
             best_pv: Mutex::new(ScoredPrincipalVariation::default()),
-            stable_pv: AtomicBool::new(false),
+            best_move_changes: AtomicUsize::new(0),
             depth_info: Mutex::new([DepthInformation::UnSearched; MAX_SEARCH_DEPTH]),
             nodes_searched: UnsafeCell::new(Vec::new()),
             seldepth: AtomicUsize::new(0),
             start_time: RwLock::new(Instant::now()),
             last_cache_status: Mutex::new(None),
             cache_status: AtomicUsize::new(0),
+            last_info_report: AtomicU64::new(0),
             cache: UnsafeCell::new(Cache::with_size_threaded(0, 1)),
             timeout_flag: RwLock::new(false),
-            saved_time: AtomicU64::new(0u64),
             tx: RwLock::new(Vec::new()),
+            #[cfg(feature = "search-tree")]
+            search_tree: Mutex::new(SearchTree::default()),
+            #[cfg(feature = "search-statistics")]
+            search_statistics: Mutex::new(SearchStatistics::default()),
             rx_f,
             tx_f,
         }
@@ -133,30 +150,65 @@ impl InterThreadCommunicationSystem {
         self.nodes_searched().iter().map(|x| x.load(Ordering::Relaxed)).sum()
     }
 
-    pub fn register_pv(&self, scored_pv: &ScoredPrincipalVariation, no_fail: bool) {
-        let mut curr_best = self.best_pv.lock().unwrap();
-        self.stable_pv.store(false, Ordering::Relaxed);
-        //Update pv stability
-        if let Some(other_mv) = curr_best.pv.pv[0] {
-            if other_mv == scored_pv.pv.pv[0].unwrap() && no_fail {
-                self.stable_pv.store(true, Ordering::Relaxed);
-            }
+    //Permille of the cache in use. Counting is expensive, so the value is only refreshed every 200ms
+    pub fn hashfull(&self) -> usize {
+        let mut cache_status = self.last_cache_status.lock().unwrap();
+        if cache_status.is_none() || Instant::now().duration_since(cache_status.unwrap()).as_millis() > 200 {
+            *cache_status = Some(Instant::now());
+            self.cache_status.store(self.cache().fill_status(), Ordering::Relaxed);
         }
+        self.cache_status.load(Ordering::Relaxed)
+    }
+
+    fn search_info(&self) -> String {
+        let searched_nodes: u64 = self.get_nodes_sum();
+        let elapsed_time = self.get_time_elapsed();
+        format!(
+            "seldepth {} nodes {} nps {} hashfull {:.0} time {}",
+            self.seldepth.load(Ordering::Relaxed),
+            searched_nodes,
+            (searched_nodes as f64 / (elapsed_time.max(1) as f64 / 1000.0)) as u64,
+            self.hashfull(),
+            elapsed_time
+        )
+    }
+
+    //Keeps GUIs updated while a long iteration does not produce a new pv
+    pub fn report_progress(&self, depth: usize) {
+        let elapsed_time = self.get_time_elapsed();
+        if self.get_current_uci_options().protocol != Protocol::Uci || elapsed_time < self.last_info_report.load(Ordering::Relaxed) + INFO_REPORT_INTERVAL {
+            return;
+        }
+        self.last_info_report.store(elapsed_time, Ordering::Relaxed);
+        println!("info depth {} {}", depth, self.search_info());
+    }
+
+    pub fn register_pv(&self, root: &GameState, scored_pv: &ScoredPrincipalVariation, bound: ScoreBound) {
+        let no_fail = bound != ScoreBound::UpperBound;
+        let mut curr_best = self.best_pv.lock().unwrap();
         if curr_best.depth < scored_pv.depth || (curr_best.depth == scored_pv.depth && curr_best.score < scored_pv.score) {
             if no_fail {
+                if curr_best.pv.pv[0].is_some() && curr_best.pv.pv[0] != scored_pv.pv.pv[0] {
+                    self.best_move_changes.fetch_add(1, Ordering::Relaxed);
+                }
                 *curr_best = scored_pv.clone();
             }
+            if self.get_current_uci_options().quiet {
+                return;
+            }
+            if self.get_current_uci_options().protocol == Protocol::Cecp {
+                //Thinking output: ply, score, time in centiseconds, nodes and pv
+                let score = if scored_pv.score > MATE_SCORE - 200 {
+                    100_000 + i32::from((MATE_SCORE - scored_pv.score) / 2 + 1)
+                } else if scored_pv.score < -MATE_SCORE + 200 {
+                    -100_000 - i32::from((MATE_SCORE + scored_pv.score) / 2)
+                } else {
+                    i32::from(scored_pv.score)
+                };
+                println!("{} {} {} {} {}", scored_pv.depth, score, self.get_time_elapsed() / 10, self.get_nodes_sum(), scored_pv.pv);
+                return;
+            }
             //Report to UCI
-            let searched_nodes: u64 = self.get_nodes_sum();
-            let elapsed_time = self.get_time_elapsed();
-            let mut cache_status = self.last_cache_status.lock().unwrap();
-            let fill_status = if cache_status.is_none() || Instant::now().duration_since(cache_status.unwrap()).as_millis() > 200 {
-                *cache_status = Some(Instant::now());
-                self.cache_status.store(self.cache().fill_status(), Ordering::Relaxed);
-                self.cache_status.load(Ordering::Relaxed)
-            } else {
-                self.cache_status.load(Ordering::Relaxed)
-            };
             let score_string = if cfg!(feature = "avoid-adj") {
                 let score = scored_pv.score.min(200).max(-200);
                 let score = if score.abs() < 10 { 25 } else { score };
@@ -171,21 +223,29 @@ impl InterThreadCommunicationSystem {
             } else {
                 format!("score cp {}", scored_pv.score)
             };
+            let wdl_string = if self.get_current_uci_options().show_wdl {
+                let (win, draw, loss) = WdlModel::default().wdl(scored_pv.score, WdlModel::material(root));
+                format!(" wdl {} {} {}", win, draw, loss)
+            } else {
+                String::new()
+            };
+            self.last_info_report.store(self.get_time_elapsed(), Ordering::Relaxed);
             println!(
-                "info depth {} seldepth {} nodes {} nps {} hashfull {:.0} time {} {} pv {}",
+                "info depth {} {} {}{}{} pv {}",
                 scored_pv.depth,
-                self.seldepth.load(Ordering::Relaxed),
-                searched_nodes,
-                (searched_nodes as f64 / (elapsed_time.max(1) as f64 / 1000.0)) as u64,
-                fill_status,
-                self.get_time_elapsed(),
+                self.search_info(),
                 score_string,
+                bound,
+                wdl_string,
                 scored_pv.pv
             );
         }
     }
 
     pub fn report_bestmove(&self) {
+        if self.get_current_uci_options().quiet || self.get_current_uci_options().protocol != Protocol::Uci {
+            return;
+        }
         println!("bestmove {:?}", self.best_pv.lock().unwrap().pv.pv[0].as_ref().expect("Could not unwrap pv for bestmove!"));
     }
 
@@ -227,7 +287,7 @@ unsafe impl std::marker::Sync for InterThreadCommunicationSystem {}
 
 pub enum ThreadInstruction {
     Exit,
-    StartSearch(i16, GameState, TimeControl, History, u64),
+    StartSearch(i16, GameState, TimeManager, History),
 }
 
 pub struct Thread {
@@ -243,22 +303,32 @@ pub struct Thread {
     pub hh_score: [[[usize; 64]; 64]; 2],
     pub bf_score: [[[usize; 64]; 64]; 2],
     pub history_score: [[[isize; 64]; 64]; 2],
+    //Move made at every ply of the search, None for null moves
+    pub current_moves: [Option<GameMove>; MAX_SEARCH_DEPTH],
+    //Quiet move which refuted a move, by side to move, piece and target square of the refuted move
+    pub counter_moves: [[[Option<GameMove>; 64]; 6]; 2],
+    //Continuation history with the previous move, and with our own move before it
+    pub continuation_history: [ContinuationHistory; 2],
     pub see_buffer: Vec<i16>,
     pub search_statistics: SearchStatistics,
-    pub tc: TimeControl, //Only thread 0 takes care of Timecontrol though
-    pub time_saved: u64,
-    pub self_stop: bool, //This is set when timeout_stop is set(timeout_stop isn't always polled)
+    pub time_manager: TimeManager,        //Only thread 0 takes care of Timecontrol though
+    pub root_move_nodes: [[u64; 64]; 64], //Nodes spent below every root move, by from and to square
+    pub self_stop: bool,                  //This is set when timeout_stop is set(timeout_stop isn't always polled)
     pub current_pv: ScoredPrincipalVariation,
     pub pv_applicable: Vec<u64>, //Hashes of gamestates the pv plays along
     pub main_thread_in_depth: bool,
+    pub root_depth: usize,       //Depth of the current iteration
     pub uci_options: UCIOptions, //UCIOptions that were supplied last time we started searching. Will not update during search
+    pub draw_scores: [i16; 2],   //Score of a draw by side to move, see draw_scores
+    #[cfg(feature = "search-tree")]
+    pub search_tree: SearchTree,
     rx: Receiver<ThreadInstruction>,
     tx: Sender<()>,
 }
 
 impl Thread {
-    pub fn replace_current_pv(&mut self, root: &GameState, scored_pv: ScoredPrincipalVariation, no_fail: bool) {
-        self.itcs.register_pv(&scored_pv, no_fail);
+    pub fn replace_current_pv(&mut self, root: &GameState, scored_pv: ScoredPrincipalVariation, bound: ScoreBound) {
+        self.itcs.register_pv(root, &scored_pv, bound);
         self.current_pv = scored_pv;
         self.pv_applicable.clear();
         self.pv_applicable.push(root.get_hash());
@@ -276,6 +346,35 @@ impl Thread {
             }
         }
     }
+    //Move made `plies_ago` plies before reaching the node at current_depth
+    #[inline(always)]
+    pub fn previous_move(&self, current_depth: usize, plies_ago: usize) -> Option<GameMove> {
+        if current_depth >= plies_ago {
+            self.current_moves[current_depth - plies_ago]
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    pub fn counter_move(&self, current_depth: usize, side: usize) -> Option<GameMove> {
+        self.previous_move(current_depth, 1)
+            .and_then(|previous| self.counter_moves[side][previous.piece_type as usize][previous.to as usize])
+    }
+
+    #[inline(always)]
+    pub fn draw_score(&self, game_state: &GameState) -> i16 {
+        self.draw_scores[game_state.get_color_to_move()]
+    }
+
+    //Butterfly history combined with both continuation histories
+    #[inline(always)]
+    pub fn quiet_history(&self, current_depth: usize, side: usize, mv: GameMove) -> isize {
+        self.history_score[side][mv.from as usize][mv.to as usize]
+            + self.continuation_history[0].get(side, self.previous_move(current_depth, 1), mv)
+            + self.continuation_history[1].get(side, self.previous_move(current_depth, 2), mv)
+    }
+
     fn new(id: usize, itcs: Arc<InterThreadCommunicationSystem>, rx: Receiver<ThreadInstruction>, tx: Sender<()>) -> Self {
         let mut pv_table = Vec::with_capacity(MAX_SEARCH_DEPTH);
         for i in 0..MAX_SEARCH_DEPTH {
@@ -294,15 +393,22 @@ impl Thread {
             hh_score: [[[0; 64]; 64]; 2],
             bf_score: [[[1; 64]; 64]; 2],
             history_score: [[[0; 64]; 64]; 2],
+            current_moves: [None; MAX_SEARCH_DEPTH],
+            counter_moves: [[[None; 64]; 6]; 2],
+            continuation_history: [ContinuationHistory::default(), ContinuationHistory::default()],
             see_buffer: vec![0i16; MAX_SEARCH_DEPTH],
             search_statistics: SearchStatistics::default(),
-            tc: TimeControl::MoveTime(0u64),
-            time_saved: 0u64,
+            time_manager: TimeManager::new(TimeControl::MoveTime(0u64), 1, 0),
+            root_move_nodes: [[0; 64]; 64],
             self_stop: false,
             current_pv: ScoredPrincipalVariation::default(),
             pv_applicable: Vec::with_capacity(MAX_SEARCH_DEPTH),
             main_thread_in_depth: false,
+            root_depth: 0,
             uci_options: UCIOptions::default(),
+            draw_scores: [0; 2],
+            #[cfg(feature = "search-tree")]
+            search_tree: SearchTree::default(),
             rx,
             tx,
         }
@@ -316,10 +422,9 @@ impl Thread {
                     self.tx.send(()).expect("Error sending exit flag!");
                     break;
                 }
-                ThreadInstruction::StartSearch(max_depth, state, tc, history, time_saved) => {
+                ThreadInstruction::StartSearch(max_depth, state, time_manager, history) => {
                     self.root_plies_played = (state.get_full_moves() - 1) * 2 + state.get_color_to_move();
                     self.history = history;
-                    self.time_saved = time_saved;
                     self.pv_applicable.clear();
                     self.current_pv = ScoredPrincipalVariation::default();
                     self.main_thread_in_depth = false;
@@ -327,20 +432,69 @@ impl Thread {
                     self.hh_score = [[[0; 64]; 64]; 2];
                     self.bf_score = [[[1; 64]; 64]; 2];
                     self.history_score = [[[0; 64]; 64]; 2];
+                    self.counter_moves = [[[None; 64]; 6]; 2];
+                    for continuation_history in self.continuation_history.iter_mut() {
+                        continuation_history.clear();
+                    }
                     self.search_statistics = SearchStatistics::default();
-                    self.tc = tc;
+                    self.time_manager = time_manager;
+                    self.root_move_nodes = [[0; 64]; 64];
                     self.self_stop = false;
                     self.uci_options = self.itcs.get_current_uci_options();
+                    //Analysis asks for neutral scores unless contempt is wanted there as well
+                    let contempt = if matches!(time_manager.tc, TimeControl::Infinite) && !self.uci_options.analysis_contempt {
+                        0
+                    } else {
+                        self.uci_options.contempt
+                    };
+                    self.draw_scores = draw_scores(state.get_color_to_move(), contempt);
+                    #[cfg(feature = "search-tree")]
+                    {
+                        if self.id == 0 {
+                            self.search_tree = std::mem::take(&mut *self.itcs.search_tree.lock().unwrap());
+                        }
+                    }
                     self.search(max_depth, state);
+                    #[cfg(feature = "search-tree")]
+                    {
+                        if self.id == 0 {
+                            *self.itcs.search_tree.lock().unwrap() = std::mem::take(&mut self.search_tree);
+                        }
+                    }
+                    #[cfg(feature = "search-statistics")]
+                    self.itcs.search_statistics.lock().unwrap().merge(&self.search_statistics);
                     self.tx.send(()).expect("Error sending finish flag!");
                 }
             }
         }
     }
 
+    //Scales the time limits with the results of the finished iteration and decides whether to start the next one
+    fn continue_iterating(&mut self) -> bool {
+        let elapsed = self.itcs.get_time_elapsed();
+        let (best_move, score) = {
+            let best_pv = self.itcs.best_pv.lock().unwrap();
+            (best_pv.pv.pv[0], best_pv.score)
+        };
+        let best_move_nodes = best_move.map_or(0, |mv| self.root_move_nodes[mv.from as usize][mv.to as usize]);
+        self.time_manager.update(
+            elapsed,
+            score,
+            self.itcs.best_move_changes.swap(0, Ordering::Relaxed),
+            best_move_nodes as f64 / self.search_statistics.nodes_searched.max(1) as f64,
+        );
+        if self.uci_options.debug_output() {
+            println!(
+                "info string Soft limit {} hard limit {} after {}ms",
+                self.time_manager.soft_limit, self.time_manager.hard_limit, elapsed
+            );
+        }
+        self.time_manager.start_next_iteration(elapsed)
+    }
+
     fn search(&mut self, max_depth: i16, state: GameState) {
-        if self.uci_options.debug_print {
-            println!("info String Thread {} starting the search of state!", self.id);
+        if self.uci_options.debug_output() {
+            println!("info string Thread {} starting the search of state!", self.id);
         }
         let mut curr_depth = 0;
         let mut previous_score: Option<i16> = None;
@@ -348,12 +502,13 @@ impl Thread {
             let temp = self.itcs.get_next_depth(curr_depth);
             curr_depth = temp.0;
             self.main_thread_in_depth = temp.1;
+            self.root_depth = curr_depth;
             if curr_depth as i16 > max_depth {
                 break;
             }
             //Start Aspiration Window
-            if self.uci_options.debug_print {
-                println!("info String Thread {} starting aspiration window with depth {}", self.id, curr_depth);
+            if self.uci_options.debug_output() {
+                println!("info string Thread {} starting aspiration window with depth {}", self.id, curr_depth);
             }
             let mut delta = if let Some(ps) = previous_score { ps.abs() / 50 } else { 0 } + 14;
             let mut alpha = if curr_depth == 1 { -16000 } else { self.current_pv.score - delta };
@@ -369,6 +524,17 @@ impl Thread {
                 if self.current_pv.score > alpha && self.current_pv.score < beta {
                     break;
                 }
+                if self.uci_options.debug_output() {
+                    println!(
+                        "info string Thread {} failed {} with score {} in window ({}, {}) at depth {}",
+                        self.id,
+                        if self.current_pv.score <= alpha { "low" } else { "high" },
+                        self.current_pv.score,
+                        alpha,
+                        beta,
+                        curr_depth
+                    );
+                }
 
                 if self.current_pv.score <= alpha {
                     if alpha < -10000 || self.current_pv.score < MATED_IN_MAX {
@@ -393,22 +559,24 @@ impl Thread {
                                 }
                             }
                         }
+                        for continuation_history in self.continuation_history.iter_mut() {
+                            continuation_history.age();
+                        }
                         beta += delta;
                     }
                 }
                 delta = (f64::from(delta) * 1.5) as i16;
             }
             previous_score = Some(self.current_pv.score);
-            #[cfg(feature = "search-statistics")]
-            {
-                println!("{}", self.search_statistics);
-            }
             if self.self_stop {
                 break;
             }
+            if self.id == 0 && !self.continue_iterating() {
+                break;
+            }
         }
-        if self.uci_options.debug_print {
-            println!("info String Thread {} stopping the search of state!", self.id);
+        if self.uci_options.debug_output() {
+            println!("info string Thread {} stopping the search of state!", self.id);
         }
 
         //Report nodes in the end
@@ -424,17 +592,22 @@ pub fn search_move(itcs: Arc<InterThreadCommunicationSystem>, max_depth: i16, ga
     let uci_options = itcs.uci_options.read().unwrap();
     //1. Prepare itcs (reset things from previous search)
     *itcs.best_pv.lock().unwrap() = ScoredPrincipalVariation::default();
-    itcs.stable_pv.store(false, Ordering::Relaxed);
+    itcs.best_move_changes.store(0, Ordering::Relaxed);
     *itcs.depth_info.lock().unwrap() = [DepthInformation::UnSearched; MAX_SEARCH_DEPTH];
     itcs.nodes_searched().iter().for_each(|x| x.store(0u64, Ordering::Relaxed));
     itcs.seldepth.store(0, Ordering::Relaxed);
     *itcs.start_time.write().unwrap() = Instant::now();
     *itcs.last_cache_status.lock().unwrap() = None;
+    itcs.last_info_report.store(0, Ordering::Relaxed);
     itcs.cache_status.store(0, Ordering::Relaxed);
     itcs.cache().increase_age();
     *itcs.timeout_flag.write().unwrap() = false;
+    #[cfg(feature = "search-statistics")]
+    {
+        *itcs.search_statistics.lock().unwrap() = SearchStatistics::default();
+    }
 
-    let time_saved_before = itcs.saved_time.load(Ordering::Relaxed);
+    let time_manager = TimeManager::new(tc, game_state.get_full_moves(), uci_options.move_overhead);
     //Step 1. Check how many legal moves there are
     let mut movelist = MoveList::default();
     generate_moves(&game_state, false, &mut movelist);
@@ -444,14 +617,14 @@ pub fn search_move(itcs: Arc<InterThreadCommunicationSystem>, max_depth: i16, ga
         panic!("The root position given does not have any legal move!");
     } else if movelist.move_list.len() == 1
         && match tc {
-            TimeControl::Infinite | TimeControl::MoveTime(_) => false,
+            TimeControl::Infinite | TimeControl::MoveTime(_) | TimeControl::Nodes(_) => false,
             _ => true,
         }
     {
-        println!("bestmove {:?}", movelist.move_list[0].0);
-
-        let new_timesaved: u64 = (time_saved_before as i64 + tc.time_saved(0, time_saved_before, uci_options.move_overhead)).max(0) as u64;
-        itcs.saved_time.store(new_timesaved, Ordering::Relaxed);
+        itcs.best_pv.lock().unwrap().pv.pv[0] = Some(movelist.move_list[0].0);
+        if !uci_options.quiet && uci_options.protocol == Protocol::Uci {
+            println!("bestmove {:?}", movelist.move_list[0].0);
+        }
         return None;
     }
 
@@ -467,10 +640,11 @@ pub fn search_move(itcs: Arc<InterThreadCommunicationSystem>, max_depth: i16, ga
     for hashes in relevant_hashes.iter().rev() {
         hist.push(*hashes, false);
     }
+    hist.set_root();
 
     //Step 4. Send search command
     for tx in itcs.tx.read().unwrap().iter() {
-        tx.send(ThreadInstruction::StartSearch(max_depth, game_state.clone(), tc, hist.clone(), time_saved_before))
+        tx.send(ThreadInstruction::StartSearch(max_depth, game_state.clone(), time_manager, hist.clone()))
             .expect("Couldn't send search command!");
     }
 
@@ -481,10 +655,6 @@ pub fn search_move(itcs: Arc<InterThreadCommunicationSystem>, max_depth: i16, ga
 
     //Step 6. Report to UCI
     itcs.report_bestmove();
-    //Store new saved time
-    let elapsed_time = itcs.get_time_elapsed();
-    let new_timesaved: u64 = (time_saved_before as i64 + tc.time_saved(elapsed_time, time_saved_before, uci_options.move_overhead)).max(0) as u64;
-    itcs.saved_time.store(new_timesaved, Ordering::Relaxed);
     //And return
     let best_score = itcs.best_pv.lock().unwrap().score;
     Some(best_score)
@@ -0,0 +1,198 @@
+use crate::bitboards::bitboards::constants::{square, BISHOP_RAYS, RANKS, ROOK_RAYS};
+use crate::board_representation::game_state::{swap_side, GameMove, GameMoveType, GameState, PieceType};
+use crate::move_generation::movegen::{self, bishop_attack, rook_attack, xray_bishop_attacks, xray_rook_attacks, MoveList};
+use crate::search::quiescence::{attacks_to, least_valuable_piece, piece_value, PIECE_VALUES};
+use std::fs;
+use std::io;
+
+//Every capture of an exchange removes a piece, so it can't be longer than the number of pieces
+const MAX_EXCHANGE_LENGTH: usize = 32;
+//At most 8 pieces of a side can be pinned to their king
+const MAX_PINS: usize = 16;
+
+//A piece pinned to its king may only capture along the pin line while the pinner is still on the board
+#[derive(Clone, Copy, Default)]
+struct Pin {
+    pinned: u64,
+    pinner: u64,
+    line: u64,
+}
+
+#[derive(Default)]
+struct Pins {
+    pins: [Pin; MAX_PINS],
+    count: usize,
+}
+
+impl Pins {
+    fn add(&mut self, game_state: &GameState, side: usize) {
+        let king_square = game_state.get_king_square(side);
+        let side_pieces = game_state.get_pieces_from_side(side);
+        let all_pieces = game_state.get_all_pieces();
+        let enemy = swap_side(side);
+        let mut rook_pinners = xray_rook_attacks(rook_attack(king_square, all_pieces), all_pieces, side_pieces, king_square) & game_state.get_rook_like_bb(enemy);
+        while rook_pinners != 0u64 {
+            let pinner_square = rook_pinners.trailing_zeros() as usize;
+            self.push(ROOK_RAYS[king_square][pinner_square] & side_pieces, pinner_square, ROOK_RAYS[king_square][pinner_square]);
+            rook_pinners ^= square(pinner_square);
+        }
+        let mut bishop_pinners = xray_bishop_attacks(bishop_attack(king_square, all_pieces), all_pieces, side_pieces, king_square) & game_state.get_bishop_like_bb(enemy);
+        while bishop_pinners != 0u64 {
+            let pinner_square = bishop_pinners.trailing_zeros() as usize;
+            self.push(
+                BISHOP_RAYS[king_square][pinner_square] & side_pieces,
+                pinner_square,
+                BISHOP_RAYS[king_square][pinner_square],
+            );
+            bishop_pinners ^= square(pinner_square);
+        }
+    }
+
+    fn push(&mut self, pinned: u64, pinner_square: usize, ray: u64) {
+        self.pins[self.count] = Pin {
+            pinned,
+            pinner: square(pinner_square),
+            line: ray | square(pinner_square),
+        };
+        self.count += 1;
+    }
+
+    //Removes the attackers which can't capture on the target square without leaving their king in check
+    fn filter(&self, mut attackers: u64, target: u64, occ: u64) -> u64 {
+        for pin in self.pins[..self.count].iter() {
+            if attackers & pin.pinned != 0u64 && pin.pinner & occ != 0u64 && pin.line & target == 0u64 {
+                attackers &= !pin.pinned;
+            }
+        }
+        attackers
+    }
+}
+
+impl GameState {
+    //Static exchange evaluation of a move, in PIECE_VALUES. Both sides capture on the target square with their least valuable piece and may stop at any time.
+    //Pins are taken from the position before the move
+    pub fn see_value(&self, mv: GameMove) -> i16 {
+        if mv.move_type == GameMoveType::Castle {
+            return 0;
+        }
+        let target = square(mv.to as usize);
+        let promotion_squares = RANKS[0] | RANKS[7];
+        let may_xray = self.get_piece_bb(PieceType::Pawn) | self.get_piece_bb(PieceType::Rook) | self.get_piece_bb(PieceType::Bishop) | self.get_piece_bb(PieceType::Queen);
+        let bishop_like = self.get_bishop_like_bb(0) | self.get_bishop_like_bb(1);
+        let rook_like = self.get_rook_like_bb(0) | self.get_rook_like_bb(1);
+        let mut pins = Pins::default();
+        pins.add(self, 0);
+        pins.add(self, 1);
+
+        let mut occ = self.get_all_pieces() ^ square(mv.from as usize);
+        if mv.move_type == GameMoveType::EnPassant {
+            occ ^= square(mv.to as usize ^ 8);
+        }
+        let mut attackers = attacks_to(self, mv.to as usize, occ) & occ;
+        let mut gain = [0i16; MAX_EXCHANGE_LENGTH];
+        gain[0] = initial_gain(mv);
+        let mut on_target = piece_on_target(mv);
+        let mut side = swap_side(self.get_color_to_move());
+        let mut index = 0;
+        loop {
+            let side_attackers = pins.filter(attackers & self.get_pieces_from_side(side), target, occ);
+            if side_attackers == 0u64 {
+                break;
+            }
+            let (from_set, piece) = least_valuable_piece(side_attackers, side, self);
+            //The king can't capture a defended piece
+            if piece == PieceType::King as usize && attackers & self.get_pieces_from_side(swap_side(side)) != 0u64 {
+                break;
+            }
+            index += 1;
+            if piece == PieceType::Pawn as usize && target & promotion_squares != 0u64 {
+                gain[index] = on_target + piece_value(PieceType::Queen) - piece_value(PieceType::Pawn) - gain[index - 1];
+                on_target = piece_value(PieceType::Queen);
+            } else {
+                gain[index] = on_target - gain[index - 1];
+                on_target = PIECE_VALUES[piece];
+            }
+            occ ^= from_set;
+            attackers ^= from_set;
+            if from_set & may_xray != 0u64 {
+                attackers |= (bishop_attack(mv.to as usize, occ) & bishop_like | rook_attack(mv.to as usize, occ) & rook_like) & occ;
+            }
+            side = swap_side(side);
+        }
+        while index > 0 {
+            gain[index - 1] = -((-gain[index - 1]).max(gain[index]));
+            index -= 1;
+        }
+        gain[0]
+    }
+
+    //Whether the static exchange evaluation of the move is at least `threshold`
+    pub fn see_ge(&self, mv: GameMove, threshold: i16) -> bool {
+        //The opponent may stop after our capture
+        let best_case = initial_gain(mv);
+        if best_case < threshold {
+            return false;
+        }
+        //If the opponent can't promote on the target square, we lose at most the moved piece and stop
+        if square(mv.to as usize) & (RANKS[0] | RANKS[7]) == 0u64 && best_case - piece_on_target(mv) >= threshold {
+            return true;
+        }
+        self.see_value(mv) >= threshold
+    }
+}
+
+fn initial_gain(mv: GameMove) -> i16 {
+    match mv.move_type {
+        GameMoveType::Capture(captured) => piece_value(captured),
+        GameMoveType::EnPassant => piece_value(PieceType::Pawn),
+        GameMoveType::Promotion(promotion, captured) => piece_value(promotion) - piece_value(PieceType::Pawn) + captured.map_or(0, piece_value),
+        _ => 0,
+    }
+}
+
+//Value of the piece standing on the target square after the move
+fn piece_on_target(mv: GameMove) -> i16 {
+    match mv.move_type {
+        GameMoveType::Promotion(promotion, _) => piece_value(promotion),
+        _ => piece_value(mv.piece_type),
+    }
+}
+
+pub struct SeePosition {
+    pub fen: String,
+    pub mv: GameMove,
+    pub expected: i16,
+}
+
+//Parses a line of a seesuite.epd file, e.g. "<fen> ; <move> ; <value>" with the move in coordinate notation
+pub fn parse_see_epd(line: &str) -> Option<SeePosition> {
+    let mut parts = line.split(';');
+    let fen = parts.next()?.trim();
+    if fen.is_empty() || fen.starts_with('#') {
+        return None;
+    }
+    let mv_str = parts.next()?.trim();
+    let expected = parts.next()?.trim().parse::<i16>().ok()?;
+    let mut movelist = MoveList::default();
+    movegen::generate_moves(&GameState::from_fen(fen), false, &mut movelist);
+    let mv = movelist.move_list.iter().map(|graded_move| graded_move.0).find(|mv| format!("{:?}", mv) == mv_str)?;
+    Some(SeePosition {
+        fen: fen.to_owned(),
+        mv,
+        expected,
+    })
+}
+
+pub fn load_see_suite(path: &str) -> io::Result<Vec<SeePosition>> {
+    let mut res = Vec::new();
+    for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_see_epd(line) {
+            Some(position) => res.push(position),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid see entry in line {}: {}", index + 1, line))),
+        }
+    }
+    Ok(res)
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "search-statistics")]
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Result};
 
 pub struct SearchStatistics {
@@ -28,6 +30,8 @@ pub struct SearchStatistics {
     pub iid_nodes: u64,
     pub futil_nodes: u64,
     pub history_pruned: u64,
+    pub razored: u64,
+    pub probcut_pruned: u64,
 }
 
 impl Default for SearchStatistics {
@@ -60,11 +64,79 @@ impl Default for SearchStatistics {
             iid_nodes: 0,
             futil_nodes: 0,
             history_pruned: 0,
+            razored: 0,
+            probcut_pruned: 0,
         }
     }
 }
 
 impl SearchStatistics {
+    //Adds the counters of another thread searching the same position
+    pub fn merge(&mut self, other: &SearchStatistics) {
+        fn add<T: Copy + std::ops::AddAssign>(to: &mut [T], from: &[T]) {
+            to.iter_mut().zip(from.iter()).for_each(|(to, from)| *to += *from);
+        }
+        add(&mut self.improving2, &other.improving2);
+        self.depth = self.depth.max(other.depth);
+        self.seldepth = self.seldepth.max(other.seldepth);
+        self.nodes_searched += other.nodes_searched;
+        self.q_nodes_searched += other.q_nodes_searched;
+        self.normal_nodes_searched += other.normal_nodes_searched;
+        add(&mut self.nodes_improving, &other.nodes_improving);
+        self.q_delta_cutoffs += other.q_delta_cutoffs;
+        self.q_see_cutoffs += other.q_see_cutoffs;
+        self.q_beta_cutoffs += other.q_beta_cutoffs;
+        add(&mut self.q_beta_cutoffs_index, &other.q_beta_cutoffs_index);
+        self.q_non_beta_cutoffs += other.q_non_beta_cutoffs;
+        self.normal_nodes_beta_cutoffs += other.normal_nodes_beta_cutoffs;
+        add(&mut self.normal_nodes_beta_cutoffs_index, &other.normal_nodes_beta_cutoffs_index);
+        self.normal_nodes_non_beta_cutoffs += other.normal_nodes_non_beta_cutoffs;
+        self.normal_nodes_fail_lows += other.normal_nodes_fail_lows;
+        add(&mut self.normal_nodes_improv_cutoffs, &other.normal_nodes_improv_cutoffs);
+        add(&mut self.normal_nodes_improv_faillows, &other.normal_nodes_improv_faillows);
+        self.cache_hit += other.cache_hit;
+        self.cache_hit_aj_replaces += other.cache_hit_aj_replaces;
+        self.nm_pruned += other.nm_pruned;
+        self.snm_pruned += other.snm_pruned;
+        self.static_eval_nodes += other.static_eval_nodes;
+        self.cache_replace_eval += other.cache_replace_eval;
+        self.iid_nodes += other.iid_nodes;
+        self.futil_nodes += other.futil_nodes;
+        self.history_pruned += other.history_pruned;
+        self.razored += other.razored;
+        self.probcut_pruned += other.probcut_pruned;
+    }
+
+    #[cfg(feature = "search-statistics")]
+    pub fn snapshot(&self) -> SearchStatisticsSnapshot {
+        let normal_nodes = self.normal_nodes_searched as f64;
+        let q_nodes = self.q_nodes_searched as f64;
+        SearchStatisticsSnapshot {
+            searches: 1,
+            depth: self.depth as f64,
+            seldepth: self.seldepth as f64,
+            nodes: self.nodes_searched as f64,
+            normal_nodes,
+            q_nodes,
+            normal_beta_cutoff_rate: share(self.normal_nodes_beta_cutoffs as f64, normal_nodes),
+            normal_fail_low_rate: share(self.normal_nodes_fail_lows as f64, normal_nodes),
+            normal_beta_cutoffs_index: distribution(&self.normal_nodes_beta_cutoffs_index),
+            cache_hit_rate: share(self.cache_hit as f64, normal_nodes),
+            static_eval_rate: share(self.static_eval_nodes as f64, normal_nodes),
+            iid_rate: share(self.iid_nodes as f64, normal_nodes),
+            static_null_move_rate: share(self.snm_pruned as f64, normal_nodes),
+            null_move_rate: share(self.nm_pruned as f64, normal_nodes),
+            razoring_rate: share(self.razored as f64, normal_nodes),
+            probcut_rate: share(self.probcut_pruned as f64, normal_nodes),
+            futility_rate: share(self.futil_nodes as f64, normal_nodes),
+            history_rate: share(self.history_pruned as f64, normal_nodes),
+            q_beta_cutoff_rate: share(self.q_beta_cutoffs as f64, q_nodes),
+            q_delta_cutoff_rate: share(self.q_delta_cutoffs as f64, q_nodes),
+            q_see_cutoff_rate: share(self.q_see_cutoffs as f64, q_nodes),
+            q_beta_cutoffs_index: distribution(&self.q_beta_cutoffs_index),
+        }
+    }
+
     #[inline(always)]
     pub fn add_normal_node(&mut self, depth: usize) {
         self.nodes_searched += 1;
@@ -121,7 +193,7 @@ impl SearchStatistics {
     #[inline(always)]
     pub fn add_q_beta_cutoff(&mut self, index: usize) {
         self.q_beta_cutoffs += 1;
-        self.q_beta_cutoffs_index[index] += 1;
+        self.q_beta_cutoffs_index[index.min(31)] += 1;
     }
     #[inline(always)]
     pub fn add_q_beta_noncutoff(&mut self) {
@@ -153,6 +225,103 @@ impl SearchStatistics {
     pub fn add_nm_pruning(&mut self) {
         self.nm_pruned += 1;
     }
+    #[inline(always)]
+    pub fn add_razoring(&mut self) {
+        self.razored += 1;
+    }
+    #[inline(always)]
+    pub fn add_probcut(&mut self) {
+        self.probcut_pruned += 1;
+    }
+}
+
+#[cfg(feature = "search-statistics")]
+fn share(part: f64, total: f64) -> f64 {
+    if total > 0. {
+        part / total
+    } else {
+        0.
+    }
+}
+
+//Share of the cutoffs produced by the move at every index of the move list
+#[cfg(feature = "search-statistics")]
+fn distribution(cutoffs_index: &[usize]) -> Vec<f64> {
+    let total = cutoffs_index.iter().sum::<usize>() as f64;
+    cutoffs_index.iter().map(|&cutoffs| share(cutoffs as f64, total)).collect()
+}
+
+//Statistics of a search over all threads, or their average over several searches. Rates of the normal search are relative to its nodes like in the Display output,
+//even where the counter is one of pruned moves
+#[cfg(feature = "search-statistics")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SearchStatisticsSnapshot {
+    pub searches: usize,
+    pub depth: f64,
+    pub seldepth: f64,
+    pub nodes: f64,
+    pub normal_nodes: f64,
+    pub q_nodes: f64,
+    pub normal_beta_cutoff_rate: f64,
+    pub normal_fail_low_rate: f64,
+    pub normal_beta_cutoffs_index: Vec<f64>,
+    pub cache_hit_rate: f64,
+    pub static_eval_rate: f64,
+    pub iid_rate: f64,
+    pub static_null_move_rate: f64,
+    pub null_move_rate: f64,
+    pub razoring_rate: f64,
+    pub probcut_rate: f64,
+    pub futility_rate: f64,
+    pub history_rate: f64,
+    pub q_beta_cutoff_rate: f64,
+    pub q_delta_cutoff_rate: f64,
+    pub q_see_cutoff_rate: f64,
+    pub q_beta_cutoffs_index: Vec<f64>,
+}
+
+#[cfg(feature = "search-statistics")]
+impl SearchStatisticsSnapshot {
+    //Every value is the mean over the searches, so each position weighs the same no matter how many nodes it took
+    pub fn average(snapshots: &[SearchStatisticsSnapshot]) -> SearchStatisticsSnapshot {
+        let count = snapshots.len().max(1) as f64;
+        let mean = |value: fn(&SearchStatisticsSnapshot) -> f64| snapshots.iter().map(value).sum::<f64>() / count;
+        let mean_index = |value: fn(&SearchStatisticsSnapshot) -> &Vec<f64>| {
+            let mut res = vec![0.; snapshots.iter().map(|snapshot| value(snapshot).len()).max().unwrap_or(0)];
+            for snapshot in snapshots.iter() {
+                res.iter_mut().zip(value(snapshot).iter()).for_each(|(res, share)| *res += share / count);
+            }
+            res
+        };
+        SearchStatisticsSnapshot {
+            searches: snapshots.iter().map(|snapshot| snapshot.searches).sum(),
+            depth: mean(|s| s.depth),
+            seldepth: mean(|s| s.seldepth),
+            nodes: mean(|s| s.nodes),
+            normal_nodes: mean(|s| s.normal_nodes),
+            q_nodes: mean(|s| s.q_nodes),
+            normal_beta_cutoff_rate: mean(|s| s.normal_beta_cutoff_rate),
+            normal_fail_low_rate: mean(|s| s.normal_fail_low_rate),
+            normal_beta_cutoffs_index: mean_index(|s| &s.normal_beta_cutoffs_index),
+            cache_hit_rate: mean(|s| s.cache_hit_rate),
+            static_eval_rate: mean(|s| s.static_eval_rate),
+            iid_rate: mean(|s| s.iid_rate),
+            static_null_move_rate: mean(|s| s.static_null_move_rate),
+            null_move_rate: mean(|s| s.null_move_rate),
+            razoring_rate: mean(|s| s.razoring_rate),
+            probcut_rate: mean(|s| s.probcut_rate),
+            futility_rate: mean(|s| s.futility_rate),
+            history_rate: mean(|s| s.history_rate),
+            q_beta_cutoff_rate: mean(|s| s.q_beta_cutoff_rate),
+            q_delta_cutoff_rate: mean(|s| s.q_delta_cutoff_rate),
+            q_see_cutoff_rate: mean(|s| s.q_see_cutoff_rate),
+            q_beta_cutoffs_index: mean_index(|s| &s.q_beta_cutoffs_index),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Could not serialize search statistics")
+    }
 }
 
 impl Display for SearchStatistics {
@@ -227,6 +396,16 @@ impl Display for SearchStatistics {
             self.history_pruned,
             (self.history_pruned as f64 / self.normal_nodes_searched as f64 * 100.0)
         ));
+        res_str.push_str(&format!(
+            "Normal-Search Razored : {} ({}%)\n",
+            self.razored,
+            (self.razored as f64 / self.normal_nodes_searched as f64 * 100.0)
+        ));
+        res_str.push_str(&format!(
+            "Normal-Search ProbCut-Pruned : {} ({}%)\n",
+            self.probcut_pruned,
+            (self.probcut_pruned as f64 / self.normal_nodes_searched as f64 * 100.0)
+        ));
 
         res_str.push_str("\n");
         res_str.push_str(&format!(
@@ -258,3 +437,37 @@ impl Display for SearchStatistics {
         write!(formatter, "{}", res_str)
     }
 }
+
+#[cfg(all(test, feature = "search-statistics"))]
+mod tests {
+    use super::{SearchStatistics, SearchStatisticsSnapshot};
+
+    #[test]
+    fn statistics_aggregation() {
+        let mut first = SearchStatistics::default();
+        first.add_normal_node(3);
+        first.add_normal_node(5);
+        first.add_normal_node_beta_cutoff(0);
+        let mut second = SearchStatistics::default();
+        second.add_normal_node(4);
+        second.add_normal_node_beta_cutoff(40);
+        second.add_q_node(9);
+        first.merge(&second);
+        assert_eq!(first.nodes_searched, 4);
+        assert_eq!((first.depth, first.seldepth), (5, 9));
+        assert_eq!(first.normal_nodes_beta_cutoffs, 2);
+
+        let snapshot = first.snapshot();
+        assert!((snapshot.normal_beta_cutoff_rate - 2. / 3.).abs() < 1e-9);
+        assert_eq!(snapshot.normal_beta_cutoffs_index[0], 0.5);
+        assert_eq!(snapshot.normal_beta_cutoffs_index[31], 0.5);
+        assert_eq!(snapshot.q_beta_cutoff_rate, 0.);
+
+        let average = SearchStatisticsSnapshot::average(&[snapshot.clone(), SearchStatistics::default().snapshot()]);
+        assert_eq!(average.searches, 2);
+        assert_eq!(average.nodes, 2.);
+        assert_eq!(average.normal_beta_cutoffs_index[0], 0.25);
+        let json = average.to_json();
+        assert_eq!(serde_json::from_str::<SearchStatisticsSnapshot>(&json).unwrap(), average);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::board_representation::game_state::{GameMove, GameState};
+use crate::search::cache::{Cache, DEFAULT_HASH_SIZE};
+use crate::search::searcher::{search_move, InterThreadCommunicationSystem};
+use crate::search::timecontrol::TimeControl;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::sync::Arc;
+
+//Null moves are written like in UCI
+pub const NULL_MOVE: &str = "0000";
+
+//Prunings, reductions and extensions applied in a node. Decisions about a single move carry the move
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TreeEvent {
+    TtHit,
+    TtCutoff,
+    CheckExtension,
+    StaticNullMovePruning,
+    NullMovePruning,
+    Razoring,
+    ProbCut,
+    MultiCut,
+    StandPat,
+    DeltaPruning,
+    SingularExtension(String),
+    FutilityPruning(String),
+    HistoryPruning(String),
+    LateMovePruning(String),
+    SeePruning(String),
+    DeltaPruningMove(String),
+    LateMoveReduction(String, i16),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TreeNode {
+    //None for the root
+    pub mv: Option<String>,
+    pub ply: usize,
+    pub depth: i16,
+    pub alpha: i16,
+    pub beta: i16,
+    //From the view of the side to move
+    pub score: i16,
+    pub quiescence: bool,
+    //Set for the verification search of a singular extension, which searches the position of its parent without this move
+    pub excluded: Option<String>,
+    pub events: Vec<TreeEvent>,
+    //Every search of a move is a child of its own, e.g. a reduced search and its re-search
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    //Follows the moves of `path`, taking the last search of every move
+    pub fn find(&self, path: &[String]) -> Option<&TreeNode> {
+        let mut node = self;
+        for mv in path.iter() {
+            node = node.child(mv)?;
+        }
+        Some(node)
+    }
+
+    //The last search of the move, verification searches excluded
+    pub fn child(&self, mv: &str) -> Option<&TreeNode> {
+        self.children.iter().rev().find(|child| child.excluded.is_none() && child.mv.as_deref() == Some(mv))
+    }
+
+    //The moves of the last child with the score of its parent, as long as such a child was recorded
+    pub fn pv(&self) -> Vec<String> {
+        let mut res = Vec::new();
+        let mut node = self;
+        while let Some(child) = node
+            .children
+            .iter()
+            .rev()
+            .find(|child| -child.score == node.score && child.excluded.is_none() && child.mv.as_deref() != Some(NULL_MOVE))
+        {
+            res.push(child.mv.clone().unwrap());
+            node = child;
+        }
+        res
+    }
+
+    //Copy of the node without the children deeper than `depth` plies
+    pub fn truncated(&self, depth: usize) -> TreeNode {
+        TreeNode {
+            children: if depth == 0 {
+                Vec::new()
+            } else {
+                self.children.iter().map(|child| child.truncated(depth - 1)).collect()
+            },
+            ..self.clone()
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut res = String::from("digraph SearchTree {\n    node [shape=box, fontname=\"monospace\"];\n");
+        let mut next_id = 0;
+        self.write_dot(&mut res, &mut next_id);
+        res.push_str("}\n");
+        res
+    }
+
+    fn write_dot(&self, res: &mut String, next_id: &mut usize) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+        let mut label = format!(
+            "{}\\nd {} [{}, {}]\\nscore {}",
+            self.mv.as_deref().unwrap_or("root"),
+            self.depth,
+            self.alpha,
+            self.beta,
+            self.score
+        );
+        if let Some(excluded) = self.excluded.as_ref() {
+            write!(label, "\\nexcluding {}", excluded).unwrap();
+        }
+        for event in self.events.iter() {
+            write!(label, "\\n{:?}", event).unwrap();
+        }
+        writeln!(
+            res,
+            "    n{} [label=\"{}\"{}];",
+            id,
+            label.replace('"', "'"),
+            if self.quiescence { ", style=dashed" } else { "" }
+        )
+        .unwrap();
+        for child in self.children.iter() {
+            let child_id = child.write_dot(res, next_id);
+            writeln!(res, "    n{} -> n{};", id, child_id).unwrap();
+        }
+        id
+    }
+}
+
+//Nodes of the main thread up to `max_ply`, for every search of the root
+#[derive(Default)]
+pub struct SearchTree {
+    pub max_ply: usize,
+    pub roots: Vec<TreeNode>,
+    stack: Vec<TreeNode>,
+}
+
+impl SearchTree {
+    pub fn new(max_ply: usize) -> Self {
+        SearchTree {
+            max_ply,
+            roots: Vec::new(),
+            stack: Vec::with_capacity(max_ply),
+        }
+    }
+
+    //Returns whether a node was opened, which has to be closed with `leave`. Quiescence search at the same ply continues the open node
+    pub fn enter(&mut self, ply: usize, mv: Option<GameMove>, alpha: i16, beta: i16, depth: i16, quiescence: bool) -> bool {
+        if ply >= self.max_ply {
+            return false;
+        }
+        if let Some(node) = self.stack.last_mut().filter(|node| node.ply == ply) {
+            node.quiescence |= quiescence;
+            return false;
+        }
+        self.push(ply, mv, alpha, beta, depth, quiescence);
+        true
+    }
+
+    //A search of the open node's position without the excluded move, recorded as a child of its own
+    pub fn enter_excluded(&mut self, ply: usize, mv: Option<GameMove>, excluded: GameMove, alpha: i16, beta: i16, depth: i16) -> bool {
+        if ply >= self.max_ply {
+            return false;
+        }
+        self.push(ply, mv, alpha, beta, depth, false);
+        self.stack.last_mut().unwrap().excluded = Some(format!("{:?}", excluded));
+        true
+    }
+
+    fn push(&mut self, ply: usize, mv: Option<GameMove>, alpha: i16, beta: i16, depth: i16, quiescence: bool) {
+        self.stack.push(TreeNode {
+            mv: if ply == 0 {
+                None
+            } else {
+                Some(mv.map_or(NULL_MOVE.to_owned(), |mv| format!("{:?}", mv)))
+            },
+            ply,
+            depth,
+            alpha,
+            beta,
+            score: 0,
+            quiescence,
+            excluded: None,
+            events: Vec::new(),
+            children: Vec::new(),
+        });
+    }
+
+    pub fn leave(&mut self, score: i16) {
+        let mut node = self.stack.pop().expect("Leaving a node of the search tree which was not entered");
+        node.score = score;
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children.push(node);
+        } else {
+            self.roots.push(node);
+        }
+    }
+
+    //Goes to the innermost open node of the ply, which is the verification search while one is running
+    pub fn event(&mut self, ply: usize, event: TreeEvent) {
+        if let Some(node) = self.stack.iter_mut().rev().find(|node| node.ply == ply) {
+            node.events.push(event);
+        }
+    }
+
+    //Graphviz if the path ends with .dot, else JSON. Graphviz only shows the last search of the root
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let content = if path.ends_with(".dot") {
+            self.roots.last().map(|root| root.to_dot()).unwrap_or_default()
+        } else {
+            serde_json::to_string_pretty(&self.roots)?
+        };
+        fs::write(path, content)
+    }
+}
+
+pub fn load_search_tree(path: &str) -> io::Result<Vec<TreeNode>> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+//Searches the position to `depth` on a single thread and writes its tree up to `max_ply` plies to `path`
+pub fn dump_search_tree(game_state: GameState, depth: i16, max_ply: usize, path: &str) -> io::Result<()> {
+    let itcs = Arc::new(InterThreadCommunicationSystem::default());
+    InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+    *itcs.cache() = Cache::with_size_threaded(DEFAULT_HASH_SIZE, 1);
+    *itcs.search_tree.lock().unwrap() = SearchTree::new(max_ply);
+    search_move(Arc::clone(&itcs), depth, game_state, Vec::new(), TimeControl::Infinite);
+    let tree = itcs.search_tree.lock().unwrap();
+    tree.write(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SearchTree, TreeEvent};
+    use crate::board_representation::game_state::{GameMove, GameMoveType, PieceType};
+
+    #[test]
+    fn search_tree_recording() {
+        let mv = GameMove {
+            from: 12,
+            to: 28,
+            move_type: GameMoveType::Quiet,
+            piece_type: PieceType::Pawn,
+        };
+        let mut tree = SearchTree::new(2);
+        assert!(tree.enter(0, None, -100, 100, 3, false));
+        assert!(tree.enter(1, Some(mv), -100, 100, 2, false));
+        //Too deep, and quiescence search continuing the open node
+        assert!(!tree.enter(2, None, -100, 100, 1, false));
+        assert!(!tree.enter(1, Some(mv), -100, 100, 0, true));
+        tree.event(1, TreeEvent::StandPat);
+        tree.leave(-20);
+        assert!(tree.enter(1, None, -101, -100, 0, false));
+        tree.leave(-30);
+        //A verification search gets a node of its own, and the events while it runs go there
+        assert!(tree.enter_excluded(0, None, mv, -51, -50, 1));
+        assert!(tree.enter(1, None, 50, 51, 0, false));
+        tree.leave(60);
+        tree.event(0, TreeEvent::TtHit);
+        tree.leave(-60);
+        tree.event(0, TreeEvent::LateMoveReduction("e2e4".to_owned(), 1));
+        tree.leave(20);
+
+        let root = &tree.roots[0];
+        assert_eq!(root.children.len(), 3);
+        assert_eq!(root.events, vec![TreeEvent::LateMoveReduction("e2e4".to_owned(), 1)]);
+        assert_eq!(root.children[2].excluded.as_deref(), Some("e2e4"));
+        assert_eq!(root.children[2].events, vec![TreeEvent::TtHit]);
+        assert_eq!(root.children[2].children.len(), 1);
+        assert_eq!(root.children[1].mv.as_deref(), Some("0000"));
+        assert!(root.children[0].quiescence);
+        assert_eq!(root.children[0].events, vec![TreeEvent::StandPat]);
+        assert_eq!(root.pv(), vec!["e2e4".to_owned()]);
+        assert_eq!(root.find(&["e2e4".to_owned()]).unwrap().score, -20);
+        assert!(root.truncated(0).children.is_empty());
+        assert!(root.to_dot().contains("n0 -> n1"));
+    }
+}
@@ -0,0 +1,224 @@
+use core_sdk::board_representation::game_state::{GameMove, GameMoveType, GameResult, GameState, WHITE};
+use core_sdk::move_generation::makemove::make_move;
+use core_sdk::search::cache::Cache;
+use core_sdk::search::searcher::{search_move, InterThreadCommunicationSystem};
+use core_sdk::search::timecontrol::TimeControl;
+use core_sdk::search::{MATED_IN_MAX, MAX_SEARCH_DEPTH};
+use extended_sdk::openings::load_db_until;
+use extended_sdk::packed::PackedWriter;
+use rand::{thread_rng, Rng};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+pub const DEFAULT_NODES: u64 = 10_000;
+pub const DEFAULT_RANDOM_PLIES: usize = 8;
+pub const DEFAULT_HASH_SIZE: usize = 8;
+pub const MAX_GAME_PLIES: usize = 400;
+//A game is adjudicated once the score stayed above the threshold for enough plies in a row
+pub const WIN_ADJUDICATION_SCORE: i16 = 1500;
+pub const WIN_ADJUDICATION_PLIES: usize = 6;
+pub const REPORT_INTERVAL: usize = 100;
+
+pub struct Config {
+    pub threads: usize,
+    pub games: usize,
+    pub nodes: u64,
+    pub hash_size: usize,
+    pub random_plies: usize,
+    pub book: Option<String>,
+    pub book_plies: usize,
+    pub output: String,
+}
+
+const USAGE: &str = "Usage: datagen [t <threads>] [g <games>] [n <nodes>] [h <hash mb>] [r <random plies>] [book <pgn> <plies>] [o <output file>]";
+
+//The value following the flag at `index`, or None after printing the usage if it is missing or invalid
+fn flag_value<T: FromStr>(args: &[String], index: usize) -> Option<T> {
+    let value = args.get(index + 1).and_then(|value| value.parse::<T>().ok());
+    if value.is_none() {
+        println!("Missing or invalid value for {}\n{}", args[index], USAGE);
+    }
+    value
+}
+
+fn parse_config(args: &[String]) -> Option<Config> {
+    let mut config = Config {
+        threads: 1,
+        games: 1000,
+        nodes: DEFAULT_NODES,
+        hash_size: DEFAULT_HASH_SIZE,
+        random_plies: DEFAULT_RANDOM_PLIES,
+        book: None,
+        book_plies: 0,
+        output: "datagen.pack".to_owned(),
+    };
+    let mut index = 0;
+    while index < args.len() {
+        match args[index].as_str() {
+            "t" => config.threads = flag_value(args, index)?,
+            "g" => config.games = flag_value(args, index)?,
+            "n" => config.nodes = flag_value(args, index)?,
+            "h" => config.hash_size = flag_value(args, index)?,
+            "r" => config.random_plies = flag_value(args, index)?,
+            "book" => {
+                config.book = Some(flag_value(args, index)?);
+                config.book_plies = match args.get(index + 2).and_then(|plies| plies.parse::<usize>().ok()) {
+                    Some(plies) => plies,
+                    None => {
+                        println!("Missing or invalid ply count for book\n{}", USAGE);
+                        return None;
+                    }
+                };
+                index += 1;
+            }
+            "o" => config.output = flag_value(args, index)?,
+            other => {
+                println!("Unknown argument {}\n{}", other, USAGE);
+                return None;
+            }
+        }
+        index += 2;
+    }
+    Some(config)
+}
+
+//Plays fixed node self-play games and writes the quiet positions, labelled with the game result and the search score,
+//in the packed format the tuner reads.
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let config = match parse_config(&args) {
+        Some(config) => config,
+        None => return,
+    };
+
+    let openings = Arc::new(match config.book.as_ref() {
+        Some(book) => {
+            let (_, openings) = load_db_until(book, config.book_plies);
+            println!("Loaded {} openings from {}", openings.len(), book);
+            assert!(!openings.is_empty(), "Opening book does not contain any game with enough plies");
+            openings
+        }
+        None => Vec::new(),
+    });
+    let writer = Arc::new(Mutex::new(PackedWriter::create(&config.output).expect("Could not create output file")));
+    let games_started = Arc::new(AtomicUsize::new(0));
+    let games_finished = Arc::new(AtomicUsize::new(0));
+    let config = Arc::new(config);
+    let start = Instant::now();
+
+    let mut handles = Vec::with_capacity(config.threads);
+    for _ in 0..config.threads {
+        let config = Arc::clone(&config);
+        let openings = Arc::clone(&openings);
+        let writer = Arc::clone(&writer);
+        let games_started = Arc::clone(&games_started);
+        let games_finished = Arc::clone(&games_finished);
+        handles.push(
+            thread::Builder::new()
+                .stack_size(12 * 1024 * 1024)
+                .spawn(move || {
+                    //Every worker has its own search, so games don't share a transposition table
+                    let itcs = Arc::new(InterThreadCommunicationSystem::default());
+                    InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+                    *itcs.cache() = Cache::with_size_threaded(config.hash_size, 1);
+                    itcs.uci_options.write().unwrap().quiet = true;
+                    while games_started.fetch_add(1, Ordering::Relaxed) < config.games {
+                        let opening = if openings.is_empty() {
+                            random_opening(config.random_plies)
+                        } else {
+                            openings[thread_rng().gen_range(0, openings.len())].clone()
+                        };
+                        let (positions, result) = play_game(&itcs, &opening, config.nodes);
+                        itcs.cache().clear_threaded(1);
+                        let label = match result {
+                            GameResult::WhiteWin => 1.,
+                            GameResult::BlackWin => 0.,
+                            _ => 0.5,
+                        };
+                        let written = {
+                            let mut writer = writer.lock().unwrap();
+                            for (state, score) in positions.iter() {
+                                writer.write(state, label, Some(*score)).expect("Could not write position");
+                            }
+                            writer.written
+                        };
+                        let finished = games_finished.fetch_add(1, Ordering::Relaxed) + 1;
+                        if finished % REPORT_INTERVAL == 0 {
+                            let secs = Instant::now().duration_since(start).as_secs_f64();
+                            println!("Games: {}, positions: {} ({:.0} positions/s)", finished, written, written as f64 / secs);
+                        }
+                    }
+                })
+                .expect("Couldn't start thread"),
+        );
+    }
+    for handle in handles {
+        handle.join().expect("Could not join thread");
+    }
+    let writer = Arc::try_unwrap(writer).ok().expect("Writer still in use").into_inner().unwrap();
+    println!("Wrote {} positions from {} games to {}", writer.written, config.games, config.output);
+    writer.finish().expect("Could not flush output file");
+}
+
+//Plays random legal moves from the starting position. Openings which end the game early are discarded.
+pub fn random_opening(plies: usize) -> Vec<GameMove> {
+    'outer: loop {
+        let mut state = GameState::standard();
+        let mut moves = Vec::with_capacity(plies);
+        for _ in 0..plies {
+            let legal_moves = state.legal_moves().collect::<Vec<GameMove>>();
+            if legal_moves.is_empty() {
+                continue 'outer;
+            }
+            let mv = legal_moves[thread_rng().gen_range(0, legal_moves.len())];
+            state = make_move(&state, mv);
+            moves.push(mv);
+        }
+        return moves;
+    }
+}
+
+//Returns the recorded positions with their search score from white's point of view, and the result of the game.
+pub fn play_game(itcs: &Arc<InterThreadCommunicationSystem>, opening: &[GameMove], nodes: u64) -> (Vec<(GameState, i16)>, GameResult) {
+    let mut history = Vec::with_capacity(MAX_GAME_PLIES);
+    let mut state = GameState::standard();
+    for mv in opening {
+        history.push(state.clone());
+        state = make_move(&state, *mv);
+    }
+    let mut positions = Vec::with_capacity(MAX_GAME_PLIES);
+    let mut adjudication_plies = 0;
+    loop {
+        let result = state.game_result(&history);
+        if result != GameResult::Ingame {
+            return (positions, result);
+        }
+        if history.len() >= MAX_GAME_PLIES {
+            return (positions, GameResult::Draw);
+        }
+        let score =
+            search_move(Arc::clone(itcs), MAX_SEARCH_DEPTH as i16, state.clone(), history.clone(), TimeControl::Nodes(nodes)).expect("Node limited search did not return a score");
+        let mv = itcs.best_pv.lock().unwrap().pv.pv[0].expect("Search did not return a best move");
+        let white_score = if state.get_color_to_move() == WHITE { score } else { -score };
+
+        if score.abs() >= WIN_ADJUDICATION_SCORE {
+            adjudication_plies += 1;
+            if adjudication_plies >= WIN_ADJUDICATION_PLIES {
+                return (positions, if white_score > 0 { GameResult::WhiteWin } else { GameResult::BlackWin });
+            }
+        } else {
+            adjudication_plies = 0;
+        }
+
+        //Only quiet positions are useful for tuning, so positions in check or with a tactical best move are skipped
+        let is_quiet = matches!(mv.move_type, GameMoveType::Quiet | GameMoveType::Castle);
+        if !state.in_check() && is_quiet && score.abs() < -MATED_IN_MAX {
+            positions.push((state.clone(), white_score));
+        }
+        history.push(state.clone());
+        state = make_move(&state, mv);
+    }
+}
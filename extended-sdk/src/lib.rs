@@ -1,3 +1,4 @@
 pub mod misc;
 pub mod openings;
+pub mod packed;
 pub mod pgn;
@@ -0,0 +1,209 @@
+use core_sdk::bitboards::bitboards::constants::square;
+use core_sdk::board_representation::game_state::{file_of, GameState, Irreversible, PieceType, BLACK, PIECE_TYPES, WHITE};
+use core_sdk::evaluation::phase::Phase;
+use core_sdk::evaluation::EvaluationScore;
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind, Read, Result, Write};
+
+pub const PACKED_MAGIC: &[u8; 4] = b"FCPP";
+pub const PACKED_VERSION: u32 = 1;
+pub const PACKED_POSITION_SIZE: usize = 32;
+pub const NO_SCORE: i16 = i16::MIN;
+
+//Layout of a packed position (all integers little endian):
+// [0..8)   occupancy bitboard
+// [8..24)  one nibble per occupied square in ascending square order: side << 3 | piece type
+// [24]     bit 0: side to move, bits 1-4: castle permissions
+// [25]     en passant file + 1, or 0 if there is no en passant square
+// [26]     half move clock (saturated at 255)
+// [27..29) full moves
+// [29]     game result from white's point of view: 0 = loss, 1 = draw, 2 = win
+// [30..32) search score in centipawns from white's point of view, or NO_SCORE
+pub struct PackedPosition(pub [u8; PACKED_POSITION_SIZE]);
+
+impl PackedPosition {
+    pub fn pack(state: &GameState, label: f32, score: Option<i16>) -> Self {
+        let mut res = [0u8; PACKED_POSITION_SIZE];
+        let occupancy = state.get_all_pieces();
+        assert!(occupancy.count_ones() <= 32, "Can't pack a position with more than 32 pieces");
+        res[0..8].copy_from_slice(&occupancy.to_le_bytes());
+        let mut occ = occupancy;
+        let mut index = 0;
+        while occ != 0u64 {
+            let sq = occ.trailing_zeros() as usize;
+            let side = if state.get_pieces_from_side(WHITE) & square(sq) != 0u64 { WHITE } else { BLACK };
+            let piece_type = PIECE_TYPES
+                .iter()
+                .find(|&&pt| state.get_piece_bb(pt) & square(sq) != 0u64)
+                .expect("Occupied square without piece");
+            let nibble = (side << 3 | *piece_type as usize) as u8;
+            res[8 + index / 2] |= nibble << (4 * (index % 2));
+            index += 1;
+            occ ^= square(sq);
+        }
+        res[24] = state.get_color_to_move() as u8 | state.castle_permissions() << 1;
+        res[25] = if state.get_en_passant() == 0u64 {
+            0
+        } else {
+            file_of(state.get_en_passant().trailing_zeros() as usize) as u8 + 1
+        };
+        res[26] = state.get_half_moves().min(255) as u8;
+        res[27..29].copy_from_slice(&(state.get_full_moves().min(u16::MAX as usize) as u16).to_le_bytes());
+        res[29] = (label * 2.).round() as u8;
+        res[30..32].copy_from_slice(&score.unwrap_or(NO_SCORE).to_le_bytes());
+        PackedPosition(res)
+    }
+
+    //Returns the position together with its label and score, or InvalidData if the bytes can't come from pack
+    pub fn unpack(&self) -> Result<(GameState, f32, Option<i16>)> {
+        let bytes = &self.0;
+        let mut occupancy_bytes = [0u8; 8];
+        occupancy_bytes.copy_from_slice(&bytes[0..8]);
+        let occupancy = u64::from_le_bytes(occupancy_bytes);
+        if occupancy.count_ones() > 32 {
+            return Err(Error::new(ErrorKind::InvalidData, "Packed position has more than 32 pieces"));
+        }
+        let mut occ = occupancy;
+        let mut piece_bb = [0u64; 6];
+        let mut color_bb = [0u64; 2];
+        let mut index = 0;
+        while occ != 0u64 {
+            let sq = occ.trailing_zeros() as usize;
+            let nibble = (bytes[8 + index / 2] >> (4 * (index % 2))) & 0xF;
+            if (nibble & 0x7) as usize >= piece_bb.len() {
+                return Err(Error::new(ErrorKind::InvalidData, format!("Invalid piece nibble {} in packed position", nibble)));
+            }
+            color_bb[(nibble >> 3) as usize] |= square(sq);
+            piece_bb[(nibble & 0x7) as usize] |= square(sq);
+            index += 1;
+            occ ^= square(sq);
+        }
+        let kings = piece_bb[PieceType::King as usize];
+        if (kings & color_bb[WHITE]).count_ones() != 1 || (kings & color_bb[BLACK]).count_ones() != 1 {
+            return Err(Error::new(ErrorKind::InvalidData, "Packed position needs exactly one king per side"));
+        }
+        if bytes[25] > 8 {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Invalid en passant file {} in packed position", bytes[25])));
+        }
+        if bytes[29] > 2 {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Invalid game result {} in packed position", bytes[29])));
+        }
+        let color_to_move = (bytes[24] & 1) as usize;
+        let castle_permissions = bytes[24] >> 1;
+        let en_passant = if bytes[25] == 0 {
+            0u64
+        } else {
+            let file = (bytes[25] - 1) as usize;
+            square(if color_to_move == WHITE { 40 + file } else { 16 + file })
+        };
+        let full_moves = u16::from_le_bytes([bytes[27], bytes[28]]) as usize;
+        let mut game_state = GameState::new(
+            color_to_move,
+            piece_bb,
+            color_bb,
+            Irreversible::new(0u64, en_passant, u16::from(bytes[26]), castle_permissions, Phase::default(), EvaluationScore(0, 0)),
+            full_moves,
+        );
+        game_state.initialize();
+        let score = i16::from_le_bytes([bytes[30], bytes[31]]);
+        Ok((game_state, f32::from(bytes[29]) / 2., if score == NO_SCORE { None } else { Some(score) }))
+    }
+}
+
+pub fn read_packed_header(reader: &mut dyn Read) -> Result<()> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    if &header[0..4] != PACKED_MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, "Not a packed position file"));
+    }
+    let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    if version != PACKED_VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Packed position file has version {}, expected {}", version, PACKED_VERSION),
+        ));
+    }
+    Ok(())
+}
+
+//Returns Ok(None) at the end of the file
+pub fn read_packed_position(reader: &mut dyn Read) -> Result<Option<PackedPosition>> {
+    let mut buf = [0u8; PACKED_POSITION_SIZE];
+    match reader.read_exact(&mut buf) {
+        Ok(()) => Ok(Some(PackedPosition(buf))),
+        Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub struct PackedWriter {
+    writer: BufWriter<File>,
+    pub written: usize,
+}
+
+impl PackedWriter {
+    pub fn create(to_file: &str) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(to_file)?);
+        writer.write_all(PACKED_MAGIC)?;
+        writer.write_all(&PACKED_VERSION.to_le_bytes())?;
+        Ok(PackedWriter { writer, written: 0 })
+    }
+
+    pub fn write(&mut self, state: &GameState, label: f32, score: Option<i16>) -> Result<()> {
+        self.writer.write_all(&PackedPosition::pack(state, label, score).0)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_packed_position, PackedPosition, PACKED_POSITION_SIZE};
+    use core_sdk::board_representation::game_state::GameState;
+
+    #[test]
+    fn packed_roundtrip() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 3 12",
+            "rnbqkbnr/pp1ppppp/8/2pP4/8/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 3",
+            "8/8/3k4/1p5R/4r3/1K6/8/8 b - - 47 90",
+        ];
+        for (i, fen) in fens.iter().enumerate() {
+            let state = GameState::from_fen(fen);
+            let label = i as f32 % 3. / 2.;
+            let score = if i % 2 == 0 { Some(-37 * i as i16) } else { None };
+            let (unpacked, unpacked_label, unpacked_score) = PackedPosition::pack(&state, label, score).unpack().unwrap();
+            assert_eq!(unpacked.to_fen(), *fen);
+            assert_eq!(unpacked.get_hash(), state.get_hash());
+            assert_eq!(unpacked_label, label);
+            assert_eq!(unpacked_score, score);
+        }
+    }
+
+    #[test]
+    fn packed_corrupt() {
+        let state = GameState::from_fen("8/8/3k4/1p5R/4r3/1K6/8/8 b - - 47 90");
+        let packed = PackedPosition::pack(&state, 0.5, None);
+        for nibble in [6u8, 7, 14, 15].iter() {
+            let mut corrupt = PackedPosition(packed.0);
+            corrupt.0[8] = (corrupt.0[8] & 0xF0) | nibble;
+            assert!(corrupt.unpack().is_err());
+        }
+        let mut corrupt = PackedPosition(packed.0);
+        corrupt.0[0..8].copy_from_slice(&0u64.to_le_bytes());
+        assert!(corrupt.unpack().is_err());
+        let mut corrupt = PackedPosition(packed.0);
+        corrupt.0[25] = 9;
+        assert!(corrupt.unpack().is_err());
+        let mut corrupt = PackedPosition(packed.0);
+        corrupt.0[29] = 3;
+        assert!(corrupt.unpack().is_err());
+        let mut reader: &[u8] = &packed.0[..PACKED_POSITION_SIZE - 1];
+        assert!(read_packed_position(&mut reader).unwrap().is_none());
+    }
+}
@@ -1,6 +1,5 @@
 use core_sdk::board_representation::game_state::{char_to_file, char_to_rank, file_of, rank_of, GameMove, GameMoveType, GameState, PieceType, WHITE};
 use core_sdk::move_generation::makemove::make_move;
-use core_sdk::move_generation::movegen;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -8,7 +7,6 @@ pub struct GameParser {
     pub pgn_parser: PGNParser,
     pub is_opening: bool,
     pub opening_load_untilply: usize,
-    pub move_list: movegen::MoveList,
 }
 
 impl Iterator for GameParser {
@@ -38,7 +36,7 @@ impl Iterator for GameParser {
                     }
                     //println!("{} || len: {}", move_str, move_str.len());
                     let last_state = &vec_gs[vec_gs.len() - 1];
-                    let parsed_move = parse_move(last_state, &move_str, &mut self.move_list);
+                    let parsed_move = parse_move(last_state, &move_str);
                     vec_gs.push(parsed_move.1);
                     vec_res.push(parsed_move.0);
                     if self.is_opening && vec_res.len() == self.opening_load_untilply {
@@ -60,9 +58,8 @@ impl Iterator for GameParser {
     }
 }
 
-pub fn find_castle(movelist: &movegen::MoveList, g: &GameState, king_side: bool) -> Result<(GameMove, GameState), ()> {
-    for gmv in movelist.move_list.iter() {
-        let mv = gmv.0;
+pub fn find_castle(g: &GameState, king_side: bool) -> Result<(GameMove, GameState), ()> {
+    for mv in g.legal_moves() {
         if mv.move_type == GameMoveType::Castle && mv.to as isize - mv.from as isize == 2 * if king_side { 1 } else { -1 } {
             let state = make_move(g, mv);
             return Ok((mv, state));
@@ -71,9 +68,9 @@ pub fn find_castle(movelist: &movegen::MoveList, g: &GameState, king_side: bool)
     Err(())
 }
 
-pub fn find_move(movelist: &movegen::MoveList, g: &GameState, ms: MoveSpecification) -> Result<(GameMove, GameState), ()> {
-    for gmv in movelist.move_list.iter() {
-        /*println!("Checking: {:?}", gmv.0);
+pub fn find_move(g: &GameState, ms: MoveSpecification) -> Result<(GameMove, GameState), ()> {
+    for mv in g.legal_moves() {
+        /*println!("Checking: {:?}", mv);
         if &format!("{:?}", gmv.0) == "e4d4" {
             println!("{:?} ", ms.target_square);
             println!("{:?} ", ms.from_square);
@@ -83,9 +80,9 @@ pub fn find_move(movelist: &movegen::MoveList, g: &GameState, ms: MoveSpecificat
             println!("{:?} ", ms.promotion_piece);
         }*/
 
-        if ms.matches(&gmv.0) {
-            let state = make_move(g, gmv.0);
-            return Ok((gmv.0, state));
+        if ms.matches(&mv) {
+            let state = make_move(g, mv);
+            return Ok((mv, state));
         }
     }
     Err(())
@@ -164,10 +161,9 @@ impl MoveSpecification {
     }
 }
 
-pub fn parse_move(g: &GameState, move_str: &str, movelist: &mut movegen::MoveList) -> (GameMove, GameState) {
+pub fn parse_move(g: &GameState, move_str: &str) -> (GameMove, GameState) {
     let mut my_string = move_str.to_string();
     my_string = my_string.replace("#", "").replace("+", "").replace("=", "").replace("x", "");
-    movegen::generate_moves(&g, false, movelist);
     if my_string.contains('-') {
         //Castle
         //Kingside
@@ -177,7 +173,7 @@ pub fn parse_move(g: &GameState, move_str: &str, movelist: &mut movegen::MoveLis
             } else {
                 assert_eq!(true, g.castle_black_kingside());
             }
-            if let Ok(res) = find_castle(movelist, g, true) {
+            if let Ok(res) = find_castle(g, true) {
                 return res;
             }
         } else {
@@ -186,7 +182,7 @@ pub fn parse_move(g: &GameState, move_str: &str, movelist: &mut movegen::MoveLis
             } else {
                 assert_eq!(true, g.castle_black_queenside());
             }
-            if let Ok(res) = find_castle(movelist, g, false) {
+            if let Ok(res) = find_castle(g, false) {
                 return res;
             }
         }
@@ -206,7 +202,7 @@ pub fn parse_move(g: &GameState, move_str: &str, movelist: &mut movegen::MoveLis
         } else if my_string.len() == 4 {
             ms.from_square = Some(8 * match_rank(my_string.chars().nth(1)) + match_file(my_string.chars().nth(0)));
         }
-        if let Ok(res) = find_move(movelist, g, ms) {
+        if let Ok(res) = find_move(g, ms) {
             return res;
         }
     }
@@ -214,8 +210,8 @@ pub fn parse_move(g: &GameState, move_str: &str, movelist: &mut movegen::MoveLis
     println!("{}", my_string);
     println!("{}", g);
 
-    for gmv in movelist.move_list.iter() {
-        println!("{:?}", gmv.0);
+    for mv in g.legal_moves() {
+        println!("{:?}", mv);
     }
     panic!("Shouldn't get here");
 }
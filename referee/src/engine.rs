@@ -1,6 +1,5 @@
 use crate::async_communication::{expect_output, expect_output_and_listen_for_info, stderr_listener, write_all};
 use core_sdk::board_representation::game_state::*;
-use core_sdk::move_generation::movegen::MoveList;
 use core_sdk::search::timecontrol::TimeControl;
 use log::{info, warn};
 use std::collections::HashMap;
@@ -184,7 +183,7 @@ impl Engine {
         stdin: &mut BufWriter<ChildStdin>,
         stdout: &mut BufReader<ChildStdout>,
         task_id: usize,
-        movelist: &MoveList,
+        state: &GameState,
     ) -> EngineReaction<(GameMove, EngineStatus)> {
         write_all(stdin, position_description).await;
         let reaction = self.valid_isready_reaction(stdin, stdout, task_id).await;
@@ -218,7 +217,7 @@ impl Engine {
         let split_line: Vec<&str> = line.split_whitespace().collect();
         let game_move: GameMove = if split_line[0] == "bestmove" {
             let mv = GameMove::string_to_move(split_line[1]);
-            let found_move = find_move(mv.0, mv.1, mv.2, &movelist);
+            let found_move = find_move(mv.0, mv.1, mv.2, state);
             if found_move.is_none() {
                 info!("Engine {} sent illegal move ({}) in game {}\n", self.name, line, task_id);
                 return EngineReaction::DisqualifyEngine;
@@ -297,9 +296,8 @@ impl Engine {
     }
 }
 
-pub fn find_move(from: usize, to: usize, promo_pieces: Option<PieceType>, move_list: &MoveList) -> Option<GameMove> {
-    for gmv in move_list.move_list.iter() {
-        let mv = gmv.0;
+pub fn find_move(from: usize, to: usize, promo_pieces: Option<PieceType>, state: &GameState) -> Option<GameMove> {
+    for mv in state.legal_moves() {
         if mv.from as usize == from && mv.to as usize == to {
             if let GameMoveType::Promotion(ps, _) = mv.move_type {
                 match promo_pieces {
@@ -320,41 +318,49 @@ pub fn find_move(from: usize, to: usize, promo_pieces: Option<PieceType>, move_l
 }
 
 pub fn fetch_info(info: &str) -> UCIInfo {
-    let split_line: Vec<&str> = info.split_whitespace().collect();
     let mut depth = None;
     let mut nps = None;
     let mut cp_score = None;
     let mut positive_mate_found = false;
     let mut negative_mate_found = false;
-    let mut index = 0;
-    while index < split_line.len() {
-        match split_line[index] {
-            "depth" => {
-                depth = split_line[index + 1].parse::<usize>().ok();
-                index += 1;
-            }
-            "cp" => {
-                cp_score = split_line[index + 1].parse::<isize>().ok();
-                index += 1;
-            }
-            "nps" => {
-                nps = split_line[index + 1].parse::<usize>().ok();
-                index += 1;
-            }
-            "mate" => {
-                let mate_score = match split_line[index + 1].parse::<isize>() {
-                    Ok(s) => s,
-                    _ => 0,
-                };
-                if mate_score < 0 {
-                    negative_mate_found = true;
-                } else if mate_score > 0 {
-                    positive_mate_found = true;
+    for line in info.lines() {
+        let split_line: Vec<&str> = line.split_whitespace().collect();
+        //Debug output is free text, and bounded scores from aspiration fails are not the engine's evaluation
+        if split_line.get(1) == Some(&"string") {
+            continue;
+        }
+        let bounded = split_line.iter().any(|token| *token == "lowerbound" || *token == "upperbound");
+        let mut index = 0;
+        while index + 1 < split_line.len() {
+            match split_line[index] {
+                "depth" => {
+                    depth = split_line[index + 1].parse::<usize>().ok();
+                    index += 1;
+                }
+                "cp" if !bounded => {
+                    cp_score = split_line[index + 1].parse::<isize>().ok();
+                    index += 1;
+                }
+                "nps" => {
+                    nps = split_line[index + 1].parse::<usize>().ok();
+                    index += 1;
                 }
+                "mate" if !bounded => {
+                    let mate_score = match split_line[index + 1].parse::<isize>() {
+                        Ok(s) => s,
+                        _ => 0,
+                    };
+                    if mate_score < 0 {
+                        negative_mate_found = true;
+                    } else if mate_score > 0 {
+                        positive_mate_found = true;
+                    }
+                }
+                "pv" => break,
+                _ => {}
             }
-            _ => {}
+            index += 1;
         }
-        index += 1;
     }
     UCIInfo {
         depth,
@@ -406,3 +412,22 @@ impl TaskResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::fetch_info;
+
+    #[test]
+    fn fetch_info_skips_bounds_and_strings() {
+        let info = "info depth 7 seldepth 12 nodes 5000 nps 100000 hashfull 3 time 50 score cp 35 pv e2e4 e7e5
+info depth 8 seldepth 14 nodes 9000 nps 110000 hashfull 4 time 82 score cp -120 upperbound pv e2e4
+info string Thread 0 failed low with score -120 in window (20, 50) at depth 8
+info depth 8 currmove d2d4 currmovenumber 2
+info depth 8 seldepth 15 nodes 12000 nps 120000 hashfull 5 time 100";
+        let res = fetch_info(info);
+        assert_eq!(res.depth, Some(8));
+        assert_eq!(res.nps, Some(120000));
+        assert_eq!(res.cp_score, Some(35));
+        assert!(!res.negative_mate_found && !res.positive_mate_found);
+    }
+}
@@ -10,6 +10,7 @@ pub mod openings;
 pub mod queue;
 pub mod selfplay;
 pub mod selfplay_splitter;
+pub mod spsa;
 
 //STS
 pub const STS_SUB_SUITS: [&str; 15] = [
@@ -61,6 +62,7 @@ Win/Elo Gain:-------------------------------------------------------------------
 */
 fn main() {
     let mut config_path = "REFEREE_CONFIG.json";
+    let mut spsa_config_path = None;
     let args: Vec<String> = env::args().collect();
     let mut index: usize = 1;
     while index < args.len() {
@@ -70,12 +72,29 @@ fn main() {
                 index += 2;
                 continue;
             }
+            "spsa" => {
+                spsa_config_path = Some(&args[index + 1]);
+                index += 2;
+                continue;
+            }
             _ => {
-                println!("Invalid argument {}, use config CONFIG_FILE to specify", &args[index]);
+                println!("Invalid argument {}, use config CONFIG_FILE to specify or spsa SPSA_CONFIG_FILE to tune", &args[index]);
                 index += 1;
             }
         }
     }
+    if let Some(spsa_config_path) = spsa_config_path {
+        let config_content = fs::read_to_string(spsa_config_path).expect("Unable to read SPSA config file!");
+        let config: spsa::SpsaConfig = serde_json::from_str(&config_content).unwrap();
+        let mut runtime = tokio::runtime::Builder::new()
+            .threaded_scheduler()
+            .core_threads(config.processors)
+            .enable_all()
+            .build()
+            .expect("Could not create tokio runtime");
+        runtime.block_on(spsa::start_spsa(config));
+        return;
+    }
     let config_content = fs::read_to_string(config_path).expect("Unable to read config file!");
     let config: Config = serde_json::from_str(&config_content).unwrap();
     let mut runtime = tokio::runtime::Builder::new()
@@ -1,8 +1,8 @@
 use crate::async_communication::{stderr_listener, write_all};
 use crate::engine::{EndConditionInformation, EngineReaction, EngineStatus, PlayTask, TaskResult};
 use core_sdk::board_representation::game_state::*;
+use core_sdk::board_representation::rules::EndReason;
 use core_sdk::move_generation::makemove::make_move;
-use core_sdk::move_generation::movegen;
 use log::warn;
 use std::time::Duration;
 use tokio::process::Child;
@@ -16,13 +16,11 @@ pub async fn cleanup(mut e1: Child, mut e2: Child, e1_err: JoinHandle<()>, e2_er
     e2_err.await.unwrap_or_else(|msg| warn!("Could not join e2_err task: {}", msg));
 }
 pub async fn play_game(mut task: PlayTask) -> TaskResult {
-    let mut movelist = movegen::MoveList::default();
     //-------------------------------------------------------------
     //Set game up
     let opening_fen = task.opening.to_fen();
-    let agsi = movegen::generate_moves(&task.opening, false, &mut movelist);
     let mut history: Vec<GameState> = Vec::with_capacity(100);
-    let mut status = check_end_condition(&task.opening, !movelist.move_list.is_empty(), agsi.stm_incheck, &history).0;
+    let mut status = check_end_condition(&task.opening, &history).0;
     assert_eq!(status, GameResult::Ingame);
     history.push(task.opening.clone());
     let mut move_history: Vec<GameMove> = Vec::with_capacity(100);
@@ -89,7 +87,7 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
         if player1_move {
             let reaction = task
                 .engine1
-                .request_move(&position_string, &go_string, &mut e1_input, &mut e1_output, task.id, &movelist)
+                .request_move(&position_string, &go_string, &mut e1_input, &mut e1_output, task.id, latest_state)
                 .await;
             let engine_status;
             match reaction {
@@ -128,7 +126,7 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
         } else {
             let reaction = task
                 .engine2
-                .request_move(&position_string, &go_string, &mut e2_input, &mut e2_output, task.id, &movelist)
+                .request_move(&position_string, &go_string, &mut e2_input, &mut e2_output, task.id, latest_state)
                 .await;
             let engine_status;
             match reaction {
@@ -172,8 +170,7 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
         if state.get_full_moves() < 35 {
             draw_adjudication = 0;
         }
-        let agsi = movegen::generate_moves(&state, false, &mut movelist);
-        let check = check_end_condition(&state, !movelist.move_list.is_empty(), agsi.stm_incheck, &history);
+        let check = check_end_condition(&state, &history);
         history.push(state);
         status = check.0;
         endcondition = check.1;
@@ -232,39 +229,14 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
     }
 }
 
-pub fn check_end_condition(game_state: &GameState, has_legal_moves: bool, in_check: bool, history: &[GameState]) -> (GameResult, Option<EndConditionInformation>) {
-    let enemy_win = if game_state.get_color_to_move() == 0 {
-        GameResult::BlackWin
-    } else {
-        GameResult::WhiteWin
-    };
-    if in_check && !has_legal_moves {
-        return (enemy_win, Some(EndConditionInformation::Mate));
-    }
-    if !in_check && !has_legal_moves {
-        return (GameResult::Draw, Some(EndConditionInformation::StaleMate));
-    }
-
-    //Missing pieces
-    if game_state.get_pieces_from_side_without_king(WHITE) | game_state.get_pieces_from_side_without_king(BLACK) == 0u64 {
-        return (GameResult::Draw, Some(EndConditionInformation::DrawByMissingPieces));
-    }
-    if game_state.get_half_moves() >= 100 {
-        return (GameResult::Draw, Some(EndConditionInformation::HundredMoveDraw));
-    }
-    if get_occurences(history, game_state) >= 2 {
-        return (GameResult::Draw, Some(EndConditionInformation::ThreeFoldRepetition));
-    }
-
-    (GameResult::Ingame, None)
-}
-
-pub fn get_occurences(history: &[GameState], state: &GameState) -> usize {
-    let mut occ = 0;
-    for other in history {
-        if other.get_hash() == state.get_hash() {
-            occ += 1;
-        }
-    }
-    occ
+pub fn check_end_condition(game_state: &GameState, history: &[GameState]) -> (GameResult, Option<EndConditionInformation>) {
+    let end_reason = game_state.end_reason(history);
+    let info = end_reason.map(|reason| match reason {
+        EndReason::Checkmate => EndConditionInformation::Mate,
+        EndReason::Stalemate => EndConditionInformation::StaleMate,
+        EndReason::InsufficientMaterial => EndConditionInformation::DrawByMissingPieces,
+        EndReason::FiftyMoveRule => EndConditionInformation::HundredMoveDraw,
+        EndReason::ThreefoldRepetition => EndConditionInformation::ThreeFoldRepetition,
+    });
+    (game_state.end_reason_result(end_reason), info)
 }
@@ -0,0 +1,279 @@
+//Tunes the search parameters of an engine with SPSA (simultaneous perturbation stochastic approximation). Every iteration plays
+//a mini-match between two instances of the engine whose parameters are perturbed in opposite directions, and moves the parameters
+//towards the instance which scored better.
+use crate::engine::{Engine, PlayTask, TaskResult};
+use crate::logging::FileLogger;
+use crate::queue::ThreadSafeQueue;
+use crate::selfplay_splitter::start_self_play_thread;
+use core_sdk::board_representation::game_state::*;
+use core_sdk::search::parameters::{find_search_parameter, SEARCH_PARAMETERS};
+use core_sdk::search::timecontrol::TimeControl;
+use extended_sdk::openings::load_db_until;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::sync::Arc;
+
+//Decay of the step size and of the perturbation, as recommended by Spall
+pub const SPSA_ALPHA: f64 = 0.602;
+pub const SPSA_GAMMA: f64 = 0.101;
+//Stability constant of the step size, as fraction of the iterations
+pub const SPSA_STABILITY: f64 = 0.1;
+
+#[derive(Serialize, Deserialize)]
+pub struct SpsaConfig {
+    pub processors: usize,
+    pub engine_path: (String, HashMap<String, String>),
+    pub opening_databases: Vec<String>,
+    pub opening_load_untilply: usize,
+    pub timecontrol_time: u64,
+    pub timecontrol_inc: u64,
+    pub iterations: usize,
+    //Every pair plays one opening with both colors
+    pub game_pairs_per_iteration: usize,
+    pub parameters: Vec<SpsaParameterConfig>,
+    pub output_path: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SpsaParameterConfig {
+    //Name of the UCI option
+    pub name: String,
+    //Defaults to the default of the engine
+    pub start: Option<f64>,
+    //Perturbation in the last iteration
+    pub c_end: f64,
+    //Step size relative to the squared perturbation in the last iteration
+    pub r_end: f64,
+}
+
+pub struct SpsaParameter {
+    pub name: &'static str,
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+    //Step size and perturbation before decay
+    pub a: f64,
+    pub c: f64,
+}
+
+#[derive(Serialize)]
+pub struct SpsaReport {
+    pub iteration: usize,
+    pub games: usize,
+    pub values: BTreeMap<String, f64>,
+    //Rounded values, which can be used as engine options in the referee config
+    pub uci_options: BTreeMap<String, String>,
+}
+
+pub struct Spsa {
+    pub parameters: Vec<SpsaParameter>,
+    pub iterations: usize,
+    stability: f64,
+}
+
+impl Spsa {
+    //Parameters are looked up in the search parameter registry, which also supplies their range
+    pub fn new(config: &[SpsaParameterConfig], iterations: usize) -> Result<Spsa, String> {
+        if iterations == 0 {
+            return Err("SPSA needs at least one iteration".to_owned());
+        }
+        let stability = SPSA_STABILITY * iterations as f64;
+        let mut parameters = Vec::with_capacity(config.len());
+        for parameter in config {
+            let registered = &SEARCH_PARAMETERS[find_search_parameter(&parameter.name).ok_or_else(|| format!("Unknown search parameter {}", parameter.name))?];
+            let (min, max) = (f64::from(registered.min), f64::from(registered.max));
+            let value = parameter.start.unwrap_or_else(|| f64::from(registered.default_value()));
+            if value < min || value > max {
+                return Err(format!("Start value {} of {} is outside of [{}, {}]", value, registered.name, min, max));
+            }
+            if parameter.c_end <= 0. || parameter.r_end <= 0. {
+                return Err(format!("c_end and r_end of {} have to be positive", registered.name));
+            }
+            parameters.push(SpsaParameter {
+                name: registered.name,
+                value,
+                min,
+                max,
+                a: parameter.r_end * parameter.c_end * parameter.c_end * (stability + iterations as f64).powf(SPSA_ALPHA),
+                c: parameter.c_end * (iterations as f64).powf(SPSA_GAMMA),
+            });
+        }
+        Ok(Spsa {
+            parameters,
+            iterations,
+            stability,
+        })
+    }
+
+    //Perturbation of every parameter in iteration k, starting at 1
+    pub fn perturbations(&self, k: usize) -> Vec<f64> {
+        self.parameters.iter().map(|parameter| parameter.c / (k as f64).powf(SPSA_GAMMA)).collect()
+    }
+
+    //Engine options with every parameter moved by sign times its perturbation in the direction of deltas.
+    //Values are rounded, as spin options are integers.
+    pub fn perturbed_options(&self, k: usize, deltas: &[f64], sign: f64) -> HashMap<String, String> {
+        self.parameters
+            .iter()
+            .zip(self.perturbations(k).iter())
+            .zip(deltas.iter())
+            .map(|((parameter, c_k), delta)| {
+                let value = (parameter.value + sign * c_k * delta).max(parameter.min).min(parameter.max);
+                (parameter.name.to_owned(), format!("{}", value.round()))
+            })
+            .collect()
+    }
+
+    //Result is the score of the instance perturbed in the direction of deltas against the other one, in wins minus losses
+    pub fn update(&mut self, k: usize, deltas: &[f64], result: f64) {
+        let perturbations = self.perturbations(k);
+        let stability = self.stability;
+        for ((parameter, c_k), delta) in self.parameters.iter_mut().zip(perturbations.iter()).zip(deltas.iter()) {
+            let a_k = parameter.a / (stability + k as f64).powf(SPSA_ALPHA);
+            parameter.value = (parameter.value + a_k * result / (c_k * delta)).max(parameter.min).min(parameter.max);
+        }
+    }
+
+    pub fn report(&self, iteration: usize, games: usize) -> SpsaReport {
+        SpsaReport {
+            iteration,
+            games,
+            values: self.parameters.iter().map(|parameter| (parameter.name.to_owned(), parameter.value)).collect(),
+            uci_options: self
+                .parameters
+                .iter()
+                .map(|parameter| (parameter.name.to_owned(), format!("{}", parameter.value.round())))
+                .collect(),
+        }
+    }
+}
+
+//Score of engine1 in wins minus losses. Disqualifications count as losses
+pub fn task_score(result: &TaskResult) -> f64 {
+    let (engine1, engine2) = (&result.task.engine1, &result.task.engine2);
+    engine1.wins as f64 - engine1.losses as f64 + engine2.disqs as f64 - engine1.disqs as f64
+}
+
+pub async fn start_spsa(config: SpsaConfig) {
+    FileLogger::new("referee_error_log.txt", false).init().expect("Could not create File Logger");
+    let mut spsa = Spsa::new(&config.parameters, config.iterations).unwrap_or_else(|e| panic!("Invalid SPSA config: {}", e));
+    let tc = TimeControl::Incremental(config.timecontrol_time, config.timecontrol_inc);
+    let engine = Engine::from_path(&config.engine_path.0, 0, tc, config.engine_path.1.clone()).await;
+    let mut db: Vec<GameState> = Vec::with_capacity(100_000);
+    let mut db_sequences: Vec<Vec<GameMove>> = Vec::with_capacity(100_000);
+    for database in config.opening_databases.iter() {
+        let mut database_loaded = load_db_until(database, config.opening_load_untilply);
+        db.append(&mut database_loaded.0);
+        db_sequences.append(&mut database_loaded.1);
+    }
+    if db.is_empty() {
+        panic!("No openings found in the opening databases!");
+    }
+    println!("Loaded database with {} games found! Starting SPSA with {} iterations...", db.len(), config.iterations);
+
+    let mut rng = rand::thread_rng();
+    let mut games = 0;
+    for k in 1..=config.iterations {
+        let deltas: Vec<f64> = (0..spsa.parameters.len()).map(|_| if rng.gen::<bool>() { 1. } else { -1. }).collect();
+        let mut plus = engine.clone();
+        plus.name = format!("{} (SPSA+)", engine.name);
+        plus.uci_options.extend(spsa.perturbed_options(k, &deltas, 1.));
+        let mut minus = engine.clone();
+        minus.id = 1;
+        minus.name = format!("{} (SPSA-)", engine.name);
+        minus.uci_options.extend(spsa.perturbed_options(k, &deltas, -1.));
+
+        let mut tasks = Vec::with_capacity(2 * config.game_pairs_per_iteration);
+        for _ in 0..config.game_pairs_per_iteration {
+            let index = rng.gen_range(0, db.len());
+            for &p1_is_white in [true, false].iter() {
+                tasks.push(PlayTask {
+                    opening: db[index].clone(),
+                    opening_sequence: db_sequences[index].clone(),
+                    p1_is_white,
+                    id: tasks.len(),
+                    engine1: plus.clone(),
+                    engine2: minus.clone(),
+                });
+            }
+        }
+        let queue = Arc::new(ThreadSafeQueue::new(tasks));
+        let result_queue: Arc<ThreadSafeQueue<TaskResult>> = Arc::new(ThreadSafeQueue::new(Vec::with_capacity(2 * config.game_pairs_per_iteration)));
+        let mut childs = Vec::with_capacity(config.processors);
+        for _ in 0..config.processors {
+            let queue_clone = queue.clone();
+            let res_clone = result_queue.clone();
+            childs.push(tokio::spawn(async move { start_self_play_thread(queue_clone, res_clone).await }));
+        }
+        for child in childs {
+            child.await.expect("Couldn't join thread");
+        }
+
+        let mut result = 0.;
+        while let Some(task_result) = result_queue.pop() {
+            result += task_score(&task_result);
+            games += 1;
+        }
+        spsa.update(k, &deltas, result);
+        println!("-------------------------------------------------");
+        println!("Iteration {}/{} finished with result {:+} for SPSA+", k, config.iterations, result);
+        for parameter in spsa.parameters.iter() {
+            println!("{:25}{:.2}", parameter.name, parameter.value);
+        }
+        println!("-------------------------------------------------");
+        let report = serde_json::to_string_pretty(&spsa.report(k, games)).expect("Could not serialize SPSA report");
+        fs::write(&config.output_path, report).unwrap_or_else(|e| println!("Could not write SPSA results to {}: {}", config.output_path, e));
+    }
+    println!("SPSA finished! Results written to {}", config.output_path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Spsa, SpsaParameterConfig};
+
+    fn parameter(name: &str, start: Option<f64>, c_end: f64) -> SpsaParameterConfig {
+        SpsaParameterConfig {
+            name: name.to_owned(),
+            start,
+            c_end,
+            r_end: 0.02,
+        }
+    }
+
+    #[test]
+    fn spsa_config_validation() {
+        let spsa = Spsa::new(&[parameter("futilitymargin", None, 4.)], 100).unwrap();
+        assert_eq!(spsa.parameters[0].name, "FutilityMargin");
+        assert_eq!(spsa.parameters[0].value, 90.);
+        assert!(Spsa::new(&[parameter("NoSuchParameter", None, 4.)], 100).is_err());
+        assert!(Spsa::new(&[parameter("FutilityMargin", Some(1000.), 4.)], 100).is_err());
+        assert!(Spsa::new(&[parameter("FutilityMargin", None, 4.)], 0).is_err());
+        //Perturbations decay to c_end in the last iteration
+        assert!(spsa.perturbations(1)[0] > spsa.perturbations(100)[0]);
+        assert!((spsa.perturbations(100)[0] - 4.).abs() < 1e-9);
+    }
+
+    //With a noiseless match result SPSA has to find the optimum of a simple objective and respect the parameter ranges
+    #[test]
+    fn spsa_converges() {
+        let iterations = 400;
+        let mut spsa = Spsa::new(&[parameter("FutilityMargin", Some(60.), 4.), parameter("RazoringMargin", Some(900.), 20.)], iterations).unwrap();
+        let strength = |options: &std::collections::HashMap<String, String>| {
+            let futility: f64 = options["FutilityMargin"].parse().unwrap();
+            let razoring: f64 = options["RazoringMargin"].parse().unwrap();
+            -(futility - 120.) * (futility - 120.) - (razoring - 1200.) * (razoring - 1200.) / 100.
+        };
+        for k in 1..=iterations {
+            let deltas = [if k % 2 == 0 { 1. } else { -1. }, if k % 3 == 0 { 1. } else { -1. }];
+            let (plus, minus) = (spsa.perturbed_options(k, &deltas, 1.), spsa.perturbed_options(k, &deltas, -1.));
+            let result = ((strength(&plus) - strength(&minus)) / 50.).clamp(-8., 8.);
+            spsa.update(k, &deltas, result);
+        }
+        assert!((spsa.parameters[0].value - 120.).abs() < 5.);
+        assert_eq!(spsa.parameters[1].value, 1000.);
+        let report = spsa.report(iterations, 0);
+        assert_eq!(report.uci_options["RazoringMargin"], "1000");
+    }
+}
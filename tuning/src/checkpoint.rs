@@ -0,0 +1,235 @@
+use crate::optimizer::Optimizer;
+use crate::Tuner;
+use core_sdk::evaluation::parameters::normal_parameters::NORMAL_PARAMS;
+use core_sdk::evaluation::parameters::special_parameters::SPECIAL_PARAMS;
+use core_sdk::evaluation::parameters::Parameters;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+
+pub const CHECKPOINT_MAGIC: &[u8; 4] = b"FCTC";
+pub const CHECKPOINT_VERSION: u32 = 2;
+
+pub struct TuningProgress {
+    pub epoch: usize,
+    pub best_error: f64,
+    pub best_validation_error: f64,
+    pub epochs_without_improvement: usize,
+}
+
+impl TuningProgress {
+    pub fn new(initial_error: f64, initial_validation_error: f64) -> Self {
+        TuningProgress {
+            epoch: 0,
+            best_error: initial_error,
+            best_validation_error: initial_validation_error,
+            epochs_without_improvement: 0,
+        }
+    }
+}
+
+pub fn write_u64(writer: &mut dyn Write, value: u64) -> Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+pub fn read_u64(reader: &mut dyn Read) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+pub fn write_f64(writer: &mut dyn Write, value: f64) -> Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+pub fn read_f64(reader: &mut dyn Read) -> Result<f64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(f64::from_le_bytes(buf))
+}
+
+pub fn write_parameters(writer: &mut dyn Write, params: &Parameters) -> Result<()> {
+    for phase in 0..2 {
+        for &value in params.normal[phase].iter() {
+            write_f64(writer, value)?;
+        }
+    }
+    for &value in params.special.iter() {
+        write_f64(writer, value)?;
+    }
+    Ok(())
+}
+pub fn read_parameters(reader: &mut dyn Read) -> Result<Parameters> {
+    let mut params = Parameters::zero();
+    for phase in 0..2 {
+        for value in params.normal[phase].iter_mut() {
+            *value = read_f64(reader)?;
+        }
+    }
+    for value in params.special.iter_mut() {
+        *value = read_f64(reader)?;
+    }
+    Ok(params)
+}
+
+//The learning rate given on the command line wins over the one the checkpoint was written with
+pub fn read_learning_rate(reader: &mut dyn Read, lr: f64) -> Result<()> {
+    let saved_lr = read_f64(reader)?;
+    if saved_lr != lr {
+        println!("Checkpoint was written with learning rate {}, continuing with {}", saved_lr, lr);
+    }
+    Ok(())
+}
+
+fn invalid_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+//Reads everything up to the optimizer name and returns the seed of the validation split and the number of positions it was made from
+fn read_header(reader: &mut dyn Read, path: &str) -> Result<(u64, u64)> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != CHECKPOINT_MAGIC {
+        return Err(invalid_data(format!("{} is not a tuning checkpoint", path)));
+    }
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != CHECKPOINT_VERSION {
+        return Err(invalid_data(format!("Checkpoint version {} is not supported (expected {})", version, CHECKPOINT_VERSION)));
+    }
+    let (normal_params, special_params) = (read_u64(reader)?, read_u64(reader)?);
+    if normal_params != NORMAL_PARAMS as u64 || special_params != SPECIAL_PARAMS as u64 {
+        return Err(invalid_data(format!(
+            "Checkpoint has {}/{} parameters, but this build has {}/{}",
+            normal_params, special_params, NORMAL_PARAMS, SPECIAL_PARAMS
+        )));
+    }
+    Ok((read_u64(reader)?, read_u64(reader)?))
+}
+
+//The validation split has to be redrawn with the seed of the checkpoint before resuming from it
+pub fn read_checkpoint_split_seed(path: &str) -> Result<u64> {
+    Ok(read_header(&mut BufReader::new(File::open(path)?), path)?.0)
+}
+
+fn position_count(tuner: &Tuner) -> u64 {
+    (tuner.positions.len() + tuner.validation_positions.len()) as u64
+}
+
+//The checkpoint is first written to a temporary file and then renamed, so a crash while saving never
+//destroys the previous checkpoint.
+pub fn save_checkpoint(path: &str, tuner: &Tuner, optimizer: &dyn Optimizer, progress: &TuningProgress) -> Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+        write_u64(&mut writer, NORMAL_PARAMS as u64)?;
+        write_u64(&mut writer, SPECIAL_PARAMS as u64)?;
+        write_u64(&mut writer, tuner.split_seed)?;
+        write_u64(&mut writer, position_count(tuner))?;
+        let name = optimizer.name().as_bytes();
+        write_u64(&mut writer, name.len() as u64)?;
+        writer.write_all(name)?;
+        write_u64(&mut writer, progress.epoch as u64)?;
+        write_f64(&mut writer, progress.best_error)?;
+        write_f64(&mut writer, progress.best_validation_error)?;
+        write_u64(&mut writer, progress.epochs_without_improvement as u64)?;
+        write_f64(&mut writer, tuner.k)?;
+        write_parameters(&mut writer, &tuner.params)?;
+        optimizer.save_state(&mut writer)?;
+        writer.flush()?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+pub fn load_checkpoint(path: &str, tuner: &mut Tuner, optimizer: &mut dyn Optimizer) -> Result<TuningProgress> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let (split_seed, positions) = read_header(&mut reader, path)?;
+    if split_seed != tuner.split_seed || positions != position_count(tuner) {
+        return Err(invalid_data(format!(
+            "Checkpoint was written for {} positions split with seed {}, but {} positions are split with seed {}",
+            positions,
+            split_seed,
+            position_count(tuner),
+            tuner.split_seed
+        )));
+    }
+    let name_len = read_u64(&mut reader)? as usize;
+    let mut name = vec![0u8; name_len];
+    reader.read_exact(&mut name)?;
+    let name = String::from_utf8_lossy(&name);
+    if name != optimizer.name() {
+        return Err(invalid_data(format!("Checkpoint was written by optimizer {}, but {} is used", name, optimizer.name())));
+    }
+    let progress = TuningProgress {
+        epoch: read_u64(&mut reader)? as usize,
+        best_error: read_f64(&mut reader)?,
+        best_validation_error: read_f64(&mut reader)?,
+        epochs_without_improvement: read_u64(&mut reader)? as usize,
+    };
+    tuner.k = read_f64(&mut reader)?;
+    tuner.params = read_parameters(&mut reader)?;
+    optimizer.load_state(&mut reader)?;
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_checkpoint, read_checkpoint_split_seed, save_checkpoint, TuningProgress};
+    use crate::optimizer::{Adam, Optimizer};
+    use crate::Tuner;
+    use core_sdk::evaluation::parameters::Parameters;
+
+    fn tuner(split_seed: u64) -> Tuner {
+        Tuner {
+            k: 1.,
+            positions: Vec::new(),
+            validation_positions: Vec::new(),
+            params: Parameters::zero(),
+            split_seed,
+        }
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        std::thread::Builder::new()
+            .stack_size(12 * 1024 * 1024)
+            .spawn(check_round_trip)
+            .expect("Couldn't start thread")
+            .join()
+            .expect("Checkpoint round trip failed");
+    }
+
+    fn check_round_trip() {
+        let path = std::env::temp_dir().join("fc_checkpoint_round_trip.bin").to_str().unwrap().to_owned();
+        let mut saved = tuner(42);
+        saved.k = 1.3;
+        saved.params = Parameters::default();
+        let mut optimizer = Adam::new(2.);
+        optimizer.step(&mut saved.params, &Parameters::default());
+        let progress = TuningProgress {
+            epoch: 7,
+            best_error: 0.1,
+            best_validation_error: 0.2,
+            epochs_without_improvement: 3,
+        };
+        save_checkpoint(&path, &saved, &optimizer, &progress).unwrap();
+        assert_eq!(read_checkpoint_split_seed(&path).unwrap(), 42);
+
+        let mut resumed = tuner(42);
+        let mut resumed_optimizer = Adam::new(0.5);
+        let resumed_progress = load_checkpoint(&path, &mut resumed, &mut resumed_optimizer).unwrap();
+        assert_eq!(resumed_progress.epoch, 7);
+        assert_eq!(resumed_progress.best_validation_error, 0.2);
+        assert_eq!(resumed_progress.epochs_without_improvement, 3);
+        assert_eq!(resumed.k, 1.3);
+        assert_eq!(resumed.params.normal[0][..], saved.params.normal[0][..]);
+        assert_eq!(resumed.params.special[..], saved.params.special[..]);
+        assert_eq!(resumed_optimizer.steps, 1.);
+        assert_eq!(resumed_optimizer.second_moment.normal[1][..], optimizer.second_moment.normal[1][..]);
+        //The learning rate of the command line is kept
+        assert_eq!(resumed_optimizer.lr, 0.5);
+
+        //A different validation split must not be resumed
+        assert!(load_checkpoint(&path, &mut tuner(43), &mut Adam::new(2.)).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
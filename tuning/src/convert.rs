@@ -0,0 +1,26 @@
+use extended_sdk::packed::PackedWriter;
+use std::time::Instant;
+use tuning::loading::{FileFormatSupported, PositionLoader};
+
+//Converts EPD or own encoding position files into the packed binary format.
+//Usage: convert <epd|own> <input file> <output file>
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    if args.len() != 3 {
+        println!("Usage: convert <epd|own> <input file> <output file>");
+        return;
+    }
+    let file_format = match args[0].as_str() {
+        "epd" => FileFormatSupported::EPD,
+        "own" => FileFormatSupported::OwnEncoding,
+        other => panic!("Unsupported input format {}", other),
+    };
+    let now = Instant::now();
+    let mut loader = PositionLoader::new(&args[1], file_format);
+    let mut writer = PackedWriter::create(&args[2]).expect("Could not create output file");
+    while let Some(position) = loader.next_position() {
+        writer.write(&position.game_state, position.label, position.score).expect("Could not write position");
+    }
+    println!("Converted {} positions in {}ms", writer.written, Instant::now().duration_since(now).as_millis());
+    writer.finish().expect("Could not flush output file");
+}
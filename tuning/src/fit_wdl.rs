@@ -0,0 +1,58 @@
+use core_sdk::evaluation::wdl::WdlModel;
+use std::time::Instant;
+use tuning::loading::{FileFormatSupported, PositionLoader};
+use tuning::wdl::{fit_wdl_model, WdlSample, WDL_LEARNING_RATE};
+
+pub const DEFAULT_ITERATIONS: usize = 20_000;
+
+//Fits the win/draw/loss model on positions labelled with a game result and a search score, as written by datagen.
+//Usage: fit_wdl [i <iterations>] [lr <learning rate>] <position files...>
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let mut iterations = DEFAULT_ITERATIONS;
+    let mut lr = WDL_LEARNING_RATE;
+    let mut files = Vec::new();
+    let mut index = 0;
+    while index < args.len() {
+        match args[index].as_str() {
+            "i" => {
+                iterations = args[index + 1].parse::<usize>().expect("Invalid iteration count");
+                index += 1;
+            }
+            "lr" => {
+                lr = args[index + 1].parse::<f64>().expect("Invalid learning rate");
+                index += 1;
+            }
+            file => files.push(file.to_owned()),
+        }
+        index += 1;
+    }
+    if files.is_empty() {
+        println!("Usage: fit_wdl [i <iterations>] [lr <learning rate>] <position files...>");
+        return;
+    }
+
+    let now = Instant::now();
+    let mut samples = Vec::new();
+    let mut skipped = 0;
+    for file in files.iter() {
+        let mut loader = PositionLoader::new(file, FileFormatSupported::from_file_name(file));
+        while let Some(position) = loader.next_position() {
+            match WdlSample::from_labelled(&position) {
+                Some(sample) => samples.push(sample),
+                None => skipped += 1,
+            }
+        }
+    }
+    println!(
+        "Loaded {} positions in {}ms, skipped {} without score or game result",
+        samples.len(),
+        Instant::now().duration_since(now).as_millis(),
+        skipped
+    );
+    assert!(!samples.is_empty(), "No usable positions found");
+
+    let model = fit_wdl_model(WdlModel::default(), &samples, iterations, lr, true);
+    println!("pub const WDL_A: [f64; 4] = {:?};", model.a.iter().map(|c| (c * 100.).round() / 100.).collect::<Vec<f64>>());
+    println!("pub const WDL_B: [f64; 4] = {:?};", model.b.iter().map(|c| (c * 100.).round() / 100.).collect::<Vec<f64>>());
+}
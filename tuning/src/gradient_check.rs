@@ -0,0 +1,233 @@
+//Checks the hand derived gradients of the tuner against finite differences of `CollapsedTrace::evaluate` and of the weighted error.
+//Run with `cargo test -p tuning gradient -- --nocapture` to see the error of every parameter group.
+use crate::*;
+use core_sdk::board_representation::game_state::GameState;
+use core_sdk::evaluation::eval_game_state;
+use core_sdk::move_generation::makemove::make_move;
+use core_sdk::move_generation::movegen::{self, MoveList};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const RANDOM_POSITIONS: usize = 150;
+const TOLERANCE: f64 = 1e-6;
+//The evaluation is linear in all tuned parameters except the attack constants, which only select a safety table entry.
+//A step of one matches the central difference used for those in `dsafetytabledconstant`.
+const STEP: f64 = 1.;
+//The error is not linear in the parameters, so its finite differences need a smaller step to stay within the tolerance
+const ERROR_STEP: f64 = 1e-3;
+
+const FENS: [&str; 5] = [
+    //Rook against bishop, scaled by SLIGHTLY_WINNING_NO_PAWN
+    "8/8/4k3/8/8/2R1K3/8/2b5 w - - 0 50",
+    //Knight and pawn against bishop, scaled by SLIGHTLY_WINNING_ENEMY_CAN_SAC
+    "8/8/4k3/8/3PK3/2N5/8/2b5 w - - 0 50",
+    //Guaranteed draw
+    "8/8/4k3/8/8/2N1K3/8/8 b - - 0 60",
+    //Both kings under attack
+    "r1b2rk1/pp3ppp/2n1pq2/3p4/3P1Q2/2PB1N2/P4PPP/R4RK1 w - - 0 14",
+    "2kr3r/ppp2ppp/2n5/2bqp1B1/4n1b1/2NP1N2/PPPQBPPP/R3K2R b KQ - 0 10",
+];
+
+#[derive(Clone, Copy)]
+enum Param {
+    Normal(usize, usize),
+    Special(usize),
+}
+
+fn get(params: &Parameters, param: Param) -> f64 {
+    match param {
+        Param::Normal(phase, idx) => params.normal[phase][idx],
+        Param::Special(idx) => params.special[idx],
+    }
+}
+
+fn set(params: &mut Parameters, param: Param, value: f64) {
+    match param {
+        Param::Normal(phase, idx) => params.normal[phase][idx] = value,
+        Param::Special(idx) => params.special[idx] = value,
+    }
+}
+
+fn parameter_groups() -> Vec<(&'static str, Vec<Param>)> {
+    vec![
+        ("normal midgame", (0..NORMAL_PARAMS).map(|i| Param::Normal(0, i)).collect()),
+        ("normal endgame", (0..NORMAL_PARAMS).map(|i| Param::Normal(1, i)).collect()),
+        ("attack weight", (IDX_ATTACK_WEIGHT..IDX_ATTACK_WEIGHT + SIZE_ATTACK_WEIGHT).map(Param::Special).collect()),
+        ("safety table", (IDX_SAFETY_TABLE..IDX_SAFETY_TABLE + SIZE_SAFETY_TABLE).map(Param::Special).collect()),
+        ("attack constants", (IDX_KNIGHT_ATTACK_VALUE..IDX_SLIGHTLY_WINNING_NO_PAWN).map(Param::Special).collect()),
+    ]
+}
+
+fn random_positions(rng: &mut StdRng) -> Vec<GameState> {
+    let mut res: Vec<GameState> = FENS.iter().map(|fen| GameState::from_fen(fen)).collect();
+    let mut movelist = MoveList::default();
+    while res.len() < RANDOM_POSITIONS + FENS.len() {
+        let mut state = GameState::standard();
+        for _ in 0..rng.gen_range(4, 120) {
+            movegen::generate_moves(&state, false, &mut movelist);
+            if movelist.move_list.is_empty() {
+                break;
+            }
+            state = make_move(&state, movelist.move_list[rng.gen_range(0, movelist.move_list.len())].0);
+        }
+        res.push(state);
+    }
+    res
+}
+
+//Perturbs everything the evaluation is linear in. The attack constants stay integral, so that the rounding of the
+//safety table index agrees between the analytic and the numerical derivative.
+fn perturbed_parameters(rng: &mut StdRng) -> Parameters {
+    let mut params = Parameters::default();
+    for phase in 0..2 {
+        for value in params.normal[phase].iter_mut() {
+            *value += rng.gen_range(-20., 20.);
+        }
+    }
+    for value in params.special[IDX_ATTACK_WEIGHT..IDX_SAFETY_TABLE + SIZE_SAFETY_TABLE].iter_mut() {
+        *value += rng.gen_range(-20., 20.);
+    }
+    params
+}
+
+fn traces(positions: &[GameState], tunable_params: &[bool; NORMAL_PARAMS], params: &Parameters) -> Vec<CollapsedTrace> {
+    positions.iter().map(|state| eval_game_state(state).trace.collapse(tunable_params, params)).collect()
+}
+
+fn numerical_derivative(trace: &CollapsedTrace, params: &mut Parameters, param: Param) -> f64 {
+    let value = get(params, param);
+    set(params, param, value + STEP);
+    let upper = trace.evaluate(params);
+    set(params, param, value - STEP);
+    let lower = trace.evaluate(params);
+    set(params, param, value);
+    (upper - lower) / (2. * STEP)
+}
+
+//Returns the relative error of every parameter group
+fn compare(analytic: &[Parameters], numerical: &[Parameters]) -> Vec<(&'static str, f64)> {
+    let mut res = Vec::new();
+    for (name, group) in parameter_groups() {
+        let (mut error, mut norm) = (0., 0.);
+        for (analytic, numerical) in analytic.iter().zip(numerical.iter()) {
+            for &param in group.iter() {
+                error += (get(analytic, param) - get(numerical, param)).powf(2.);
+                norm += get(numerical, param).powf(2.);
+            }
+        }
+        let relative_error = error.sqrt() / norm.sqrt().max(1e-9);
+        println!(
+            "{:>16}: gradient norm {:.3e}, absolute error {:.3e}, relative error {:.3e}",
+            name,
+            norm.sqrt(),
+            error.sqrt(),
+            relative_error
+        );
+        res.push((name, relative_error));
+    }
+    res
+}
+
+//Parameters are large arrays, so the checks run on a thread with the same stack size the tuner uses
+fn run_with_large_stack(f: fn()) {
+    std::thread::Builder::new()
+        .stack_size(12 * 1024 * 1024)
+        .spawn(f)
+        .expect("Couldn't start thread")
+        .join()
+        .expect("Gradient check failed");
+}
+
+fn assert_within_tolerance(errors: &[(&'static str, f64)]) {
+    let failed = errors.iter().filter(|(_, error)| *error > TOLERANCE).map(|(name, _)| *name).collect::<Vec<&str>>();
+    assert!(failed.is_empty(), "Gradient mismatch in: {}", failed.join(", "));
+}
+
+#[test]
+fn evaluation_gradient_matches_finite_differences() {
+    run_with_large_stack(check_evaluation_gradient);
+}
+
+fn check_evaluation_gradient() {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    let positions = random_positions(&mut rng);
+    let mut params = perturbed_parameters(&mut rng);
+    let mut analytic = Vec::with_capacity(positions.len());
+    let mut numerical = Vec::with_capacity(positions.len());
+    for trace in traces(&positions, &[true; NORMAL_PARAMS], &params).iter() {
+        let mut gradient = Parameters::zero();
+        add_evaluation_gradient(&params, trace, 1., true, true, &mut gradient);
+        analytic.push(gradient);
+        let mut gradient = Parameters::zero();
+        for (_, group) in parameter_groups() {
+            for param in group {
+                set(&mut gradient, param, numerical_derivative(trace, &mut params, param));
+            }
+        }
+        numerical.push(gradient);
+    }
+    assert_within_tolerance(&compare(&analytic, &numerical));
+}
+
+//The weighted error the tuner minimizes, with the evaluations of all positions under params
+fn weighted_error(tuner: &mut Tuner, params: &Parameters) -> f64 {
+    for pos in tuner.positions.iter_mut() {
+        pos.eval = pos.trace.evaluate(params);
+    }
+    average_evaluation_error(tuner)
+}
+
+//Checks the chain rule and the weighting in `calculate_gradient` itself against finite differences of the weighted error. Special parameters are only compared if this build tunes them.
+#[test]
+fn calculate_gradient_matches_finite_differences() {
+    run_with_large_stack(check_calculate_gradient);
+}
+
+fn check_calculate_gradient() {
+    let mut rng = StdRng::seed_from_u64(0xc0ffee);
+    let positions = random_positions(&mut rng);
+    let mut params = perturbed_parameters(&mut rng);
+    let mut tuner = Tuner {
+        k: DEFAULT_K,
+        positions: Vec::with_capacity(positions.len()),
+        validation_positions: Vec::new(),
+        params: params.clone(),
+        split_seed: 0,
+    };
+    for trace in traces(&positions, &[true; NORMAL_PARAMS], &params) {
+        tuner.positions.push(TexelState {
+            label: rng.gen_range(0., 1.),
+            result: 0.,
+            score: None,
+            weight: rng.gen_range(0.1, 2.),
+            eval: 0.,
+            trace,
+        });
+    }
+    let analytic = calculate_gradient(&mut tuner, 0, positions.len());
+    //calculate_gradient leaves out the constant factor of the derivative of the weighted error: -2 * k * ln(10) / 400 / total weight
+    let scale = -400. * total_weight(&tuner.positions) / (2. * tuner.k * 10f64.ln());
+    let mut numerical = Parameters::zero();
+    for (_, group) in parameter_groups() {
+        for param in group {
+            let tuned = match param {
+                Param::Normal(_, _) => true,
+                Param::Special(idx) => TUNE_ATTACK && (idx < IDX_KNIGHT_ATTACK_VALUE || TUNE_ATTACK_INDEX),
+            };
+            if tuned {
+                let step = match param {
+                    Param::Special(idx) if idx >= IDX_KNIGHT_ATTACK_VALUE => STEP,
+                    _ => ERROR_STEP,
+                };
+                let value = get(&params, param);
+                set(&mut params, param, value + step);
+                let upper = weighted_error(&mut tuner, &params);
+                set(&mut params, param, value - step);
+                let lower = weighted_error(&mut tuner, &params);
+                set(&mut params, param, value);
+                set(&mut numerical, param, scale * (upper - lower) / (2. * step));
+            }
+        }
+    }
+    assert_within_tolerance(&compare(&[analytic], &[numerical]));
+}
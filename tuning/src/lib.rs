@@ -1,14 +1,23 @@
-#![feature(str_split_once)]
 extern crate core;
 extern crate rand;
 
+pub mod checkpoint;
+#[cfg(test)]
+mod gradient_check;
 pub mod loading;
+pub mod optimizer;
+pub mod trace_cache;
+pub mod wdl;
 
+pub use crate::checkpoint::{load_checkpoint, read_checkpoint_split_seed, save_checkpoint, TuningProgress};
 pub use crate::loading::{FileFormatSupported, LabelledGameState, Statistics};
-use core_sdk::board_representation::game_state::{BLACK, WHITE};
+pub use crate::optimizer::{optimizer_from_name, AdaGrad, Adam, Optimizer, Sgd};
+pub use crate::trace_cache::load_texel_positions_cached;
+use core_sdk::board_representation::game_state::{GameState, BLACK, WHITE};
 pub use core_sdk::evaluation::parameters::{normal_parameters::*, special_parameters::*, *};
 use core_sdk::evaluation::trace::CollapsedTrace;
-use rand::{seq::SliceRandom, thread_rng};
+use rand::rngs::StdRng;
+use rand::{seq::SliceRandom, thread_rng, SeedableRng};
 use std::cell::UnsafeCell;
 use std::sync::Arc;
 use std::thread;
@@ -40,10 +49,22 @@ pub const TUNE_PSQT: bool = false;
 pub const TUNABLE_PARAM: [bool; NORMAL_PARAMS] = init_tunable_param();
 
 pub const OPTIMIZE_K: bool = false;
+pub const DEFAULT_K: f64 = 1.1155;
+//Share of the label taken from the game result. The rest comes from the search score of the position, if it has one.
+//Can be overridden on the command line
+pub const LAMBDA: f32 = 0.7;
+//Positions before this full move count with a reduced weight towards the error
+pub const EARLY_GAME_FULL_MOVES: usize = 10;
+pub const EARLY_GAME_WEIGHT: f32 = 0.5;
 pub const BATCH_SIZE: usize = 5_000_000;
 pub const START_LEARNING_RATE: f64 = 2.;
 pub const L1_REGULARIZATION: f64 = 0.;
 pub const L2_REGULARIZATION: f64 = 0.;
+//Portion of the loaded positions held back to measure the validation error
+pub const VALIDATION_SPLIT: f64 = 0.05;
+//Stop after this many epochs without improvement of the validation error
+pub const EARLY_STOPPING_PATIENCE: usize = 25;
+pub const CHECKPOINT_INTERVAL: usize = 5;
 pub const fn init_tunable_param() -> [bool; NORMAL_PARAMS] {
     let mut res = [false; NORMAL_PARAMS];
     if TUNE_ALL {
@@ -178,7 +199,11 @@ pub const fn init_tunable_param() -> [bool; NORMAL_PARAMS] {
     res
 }
 pub struct TexelState {
+    //The game result until the labels are blended with the search score
     pub label: f32,
+    pub result: f32,
+    pub score: Option<i16>,
+    pub weight: f32,
     pub eval: f64,
     pub trace: CollapsedTrace,
 }
@@ -186,7 +211,10 @@ pub struct TexelState {
 pub struct Tuner {
     pub k: f64,
     pub positions: Vec<TexelState>,
+    pub validation_positions: Vec<TexelState>,
     pub params: Parameters,
+    //Seed of the validation split, so a resumed run holds back the same positions
+    pub split_seed: u64,
 }
 pub struct TunerWrapper(UnsafeCell<Tuner>);
 impl TunerWrapper {
@@ -196,6 +224,34 @@ impl TunerWrapper {
 }
 unsafe impl std::marker::Sync for TunerWrapper {}
 
+//The score is mapped to an expected result with the same k as the evaluation
+pub fn blend_label(result: f32, score: Option<i16>, k: f64, lambda: f32) -> f32 {
+    match score {
+        Some(score) => lambda * result + (1. - lambda) * sigmoid(k, f64::from(score)) as f32,
+        None => result,
+    }
+}
+
+//Has to be called once k is final, after fitting it or restoring it from a checkpoint
+pub fn blend_labels(tuner: &mut Tuner, lambda: f32) {
+    let k = tuner.k;
+    for pos in tuner.positions.iter_mut().chain(tuner.validation_positions.iter_mut()) {
+        pos.label = blend_label(pos.result, pos.score, k, lambda);
+    }
+}
+
+pub fn position_weight(game_state: &GameState) -> f32 {
+    if game_state.get_full_moves() < EARLY_GAME_FULL_MOVES {
+        EARLY_GAME_WEIGHT
+    } else {
+        1.
+    }
+}
+
+pub fn total_weight(positions: &[TexelState]) -> f64 {
+    positions.iter().map(|pos| f64::from(pos.weight)).sum()
+}
+
 pub fn update_evaluations(tuner: &mut Tuner) {
     for pos in tuner.positions.iter_mut() {
         pos.eval = pos.trace.evaluate(&tuner.params);
@@ -206,6 +262,12 @@ pub fn shuffle_positions(tuner: &mut Tuner) {
     tuner.positions.shuffle(&mut thread_rng());
 }
 
+//Shuffles the positions with the seed and splits off the validation positions. The positions have to be loaded in the same order every time
+pub fn split_validation_positions(positions: &mut Vec<TexelState>, split_seed: u64) -> Vec<TexelState> {
+    positions.shuffle(&mut StdRng::seed_from_u64(split_seed));
+    positions.split_off(positions.len() - (positions.len() as f64 * VALIDATION_SPLIT) as usize)
+}
+
 pub fn add_regularization(gradient: &mut Parameters, parameters: &Parameters, portion: f64) {
     for i in 0..gradient.normal[0].len() {
         gradient.normal[0][i] -= portion * regularization(parameters.normal[0][i]);
@@ -229,204 +291,218 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
     for pos in tuner.positions[from..to].iter() {
         //Step 2. Calculate first half of gradient
         let s = sigmoid(tuner.k, pos.eval);
-        let start_of_gradient = (pos.label as f64 - s) * s * (1. - s);
-        let devaldmg = pos.trace.phase as f64 / 128.0;
-        let devaldeg = 1. - pos.trace.phase as f64 / 128.0;
-        for entry in pos.trace.normal_tunable_coeffs.iter() {
-            //All entries left in the trace are tunable, others are collapsed.
-            gradient.normal[0][entry.0 as usize] += start_of_gradient * devaldmg * f64::from(entry.1);
-            gradient.normal[1][entry.0 as usize] += start_of_gradient * devaldeg * f64::from(entry.1);
-        }
-        //Safety
-        if TUNE_ATTACK {
-            for i in 0..2 {
-                let devaldg = if i == 0 { devaldmg } else { devaldeg };
-                let attack_knight_white = f64::from(pos.trace.knight_attacked_sq[WHITE]) * tuner.params.special[IDX_KNIGHT_ATTACK_VALUE + i];
-                let attack_bishop_white = f64::from(pos.trace.bishop_attacked_sq[WHITE]) * tuner.params.special[IDX_BISHOP_ATTACK_VALUE + i];
-                let attack_rook_white = f64::from(pos.trace.rook_attacked_sq[WHITE]) * tuner.params.special[IDX_ROOK_ATTACK_VALUE + i];
-                let attack_queen_white = f64::from(pos.trace.queen_attacked_sq[WHITE]) * tuner.params.special[IDX_QUEEN_ATTACK_VALUE + i];
-                let knight_check_white = f64::from(pos.trace.knight_safe_check[WHITE]) * tuner.params.special[IDX_KNIGHT_CHECK_VALUE + i];
-                let bishop_check_white = f64::from(pos.trace.bishop_safe_check[WHITE]) * tuner.params.special[IDX_BISHOP_CHECK_VALUE + i];
-                let rook_check_white = f64::from(pos.trace.rook_safe_check[WHITE]) * tuner.params.special[IDX_ROOK_CHECK_VALUE + i];
-                let queen_check_white = f64::from(pos.trace.queen_safe_check[WHITE]) * tuner.params.special[IDX_QUEEN_CHECK_VALUE + i];
-                let attacker_value_white = (attack_knight_white
-                    + attack_bishop_white
-                    + attack_rook_white
-                    + attack_queen_white
-                    + knight_check_white
-                    + bishop_check_white
-                    + rook_check_white
-                    + queen_check_white)
-                    .max(0.)
-                    .min(99.);
-                let attack_knight_black = f64::from(pos.trace.knight_attacked_sq[BLACK]) * tuner.params.special[IDX_KNIGHT_ATTACK_VALUE + i];
-                let attack_bishop_black = f64::from(pos.trace.bishop_attacked_sq[BLACK]) * tuner.params.special[IDX_BISHOP_ATTACK_VALUE + i];
-                let attack_rook_black = f64::from(pos.trace.rook_attacked_sq[BLACK]) * tuner.params.special[IDX_ROOK_ATTACK_VALUE + i];
-                let attack_queen_black = f64::from(pos.trace.queen_attacked_sq[BLACK]) * tuner.params.special[IDX_QUEEN_ATTACK_VALUE + i];
-                let knight_check_black = f64::from(pos.trace.knight_safe_check[BLACK]) * tuner.params.special[IDX_KNIGHT_CHECK_VALUE + i];
-                let bishop_check_black = f64::from(pos.trace.bishop_safe_check[BLACK]) * tuner.params.special[IDX_BISHOP_CHECK_VALUE + i];
-                let rook_check_black = f64::from(pos.trace.rook_safe_check[BLACK]) * tuner.params.special[IDX_ROOK_CHECK_VALUE + i];
-                let queen_check_black = f64::from(pos.trace.queen_safe_check[BLACK]) * tuner.params.special[IDX_QUEEN_CHECK_VALUE + i];
-                let attacker_value_black = (attack_knight_black
-                    + attack_bishop_black
-                    + attack_rook_black
-                    + attack_queen_black
-                    + knight_check_black
-                    + bishop_check_black
-                    + rook_check_black
-                    + queen_check_black)
-                    .max(0.)
-                    .min(99.);
-                gradient.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i] +=
-                    start_of_gradient * devaldg / 100.0 * tuner.params.special[IDX_SAFETY_TABLE + 2 * attacker_value_white as usize + i];
-                gradient.special[IDX_SAFETY_TABLE + 2 * attacker_value_white as usize + i] +=
-                    start_of_gradient * devaldg / 100.0 * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i];
-                gradient.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i] -=
-                    start_of_gradient * devaldg / 100.0 * tuner.params.special[IDX_SAFETY_TABLE + 2 * attacker_value_black as usize + i];
-                gradient.special[IDX_SAFETY_TABLE + 2 * attacker_value_black as usize + i] +=
-                    start_of_gradient * devaldg / 100.0 * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i];
-                //Attack constants
-                if TUNE_ATTACK_INDEX {
-                    //Knight
-                    {
-                        let c = tuner.params.special[IDX_KNIGHT_ATTACK_VALUE + i];
-                        gradient.special[IDX_KNIGHT_ATTACK_VALUE + i] += start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_white - attack_knight_white, pos.trace.knight_attacked_sq[WHITE], c)
-                            / 100.0;
-                        gradient.special[IDX_KNIGHT_ATTACK_VALUE + i] -= start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_black - attack_knight_black, pos.trace.knight_attacked_sq[BLACK], c)
-                            / 100.0;
-                    }
-                    //Bishop
-                    {
-                        let c = tuner.params.special[IDX_BISHOP_ATTACK_VALUE + i];
-                        gradient.special[IDX_BISHOP_ATTACK_VALUE + i] += start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_white - attack_bishop_white, pos.trace.bishop_attacked_sq[WHITE], c)
-                            / 100.0;
-                        gradient.special[IDX_BISHOP_ATTACK_VALUE + i] -= start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_black - attack_bishop_black, pos.trace.bishop_attacked_sq[BLACK], c)
-                            / 100.0;
-                    }
-                    //Rook
-                    {
-                        let c = tuner.params.special[IDX_ROOK_ATTACK_VALUE + i];
-                        gradient.special[IDX_ROOK_ATTACK_VALUE + i] += start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_white - attack_rook_white, pos.trace.rook_attacked_sq[WHITE], c)
-                            / 100.0;
-                        gradient.special[IDX_ROOK_ATTACK_VALUE + i] -= start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_black - attack_rook_black, pos.trace.rook_attacked_sq[BLACK], c)
-                            / 100.0;
-                    }
-                    //Queen
-                    {
-                        let c = tuner.params.special[IDX_QUEEN_ATTACK_VALUE + i];
-                        gradient.special[IDX_QUEEN_ATTACK_VALUE + i] += start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_white - attack_queen_white, pos.trace.queen_attacked_sq[WHITE], c)
-                            / 100.0;
-                        gradient.special[IDX_QUEEN_ATTACK_VALUE + i] -= start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_black - attack_queen_black, pos.trace.queen_attacked_sq[BLACK], c)
-                            / 100.0;
-                    }
-                    //Knight check
-                    {
-                        let c = tuner.params.special[IDX_KNIGHT_CHECK_VALUE + i];
-                        gradient.special[IDX_KNIGHT_CHECK_VALUE + i] += start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_white - knight_check_white, pos.trace.knight_safe_check[WHITE], c)
-                            / 100.0;
-                        gradient.special[IDX_KNIGHT_CHECK_VALUE + i] -= start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_black - knight_check_black, pos.trace.knight_safe_check[BLACK], c)
-                            / 100.0;
-                    }
-                    //Bishop check
-                    {
-                        let c = tuner.params.special[IDX_BISHOP_CHECK_VALUE + i];
-                        gradient.special[IDX_BISHOP_CHECK_VALUE + i] += start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_white - bishop_check_white, pos.trace.bishop_safe_check[WHITE], c)
-                            / 100.0;
-                        gradient.special[IDX_BISHOP_CHECK_VALUE + i] -= start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_black - bishop_check_black, pos.trace.bishop_safe_check[BLACK], c)
-                            / 100.0;
-                    }
-                    //Rook check
-                    {
-                        let c = tuner.params.special[IDX_ROOK_CHECK_VALUE + i];
-                        gradient.special[IDX_ROOK_CHECK_VALUE + i] += start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_white - rook_check_white, pos.trace.rook_safe_check[WHITE], c)
-                            / 100.0;
-                        gradient.special[IDX_ROOK_CHECK_VALUE + i] -= start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_black - rook_check_black, pos.trace.rook_safe_check[BLACK], c)
-                            / 100.0;
-                    }
-                    //Queen check
-                    {
-                        let c = tuner.params.special[IDX_QUEEN_CHECK_VALUE + i];
-                        gradient.special[IDX_QUEEN_CHECK_VALUE + i] += start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_white - queen_check_white, pos.trace.queen_safe_check[WHITE], c)
-                            / 100.0;
-                        gradient.special[IDX_QUEEN_CHECK_VALUE + i] -= start_of_gradient
-                            * devaldg
-                            * tuner.params.special[IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
-                            * dsafetytabledconstant(tuner, i, attacker_value_black - queen_check_black, pos.trace.queen_safe_check[BLACK], c)
-                            / 100.0;
-                    }
+        let start_of_gradient = f64::from(pos.weight) * (pos.label as f64 - s) * s * (1. - s);
+        //Step 3. Chain it with the derivative of the evaluation
+        add_evaluation_gradient(&tuner.params, &pos.trace, start_of_gradient, TUNE_ATTACK, TUNE_ATTACK_INDEX, &mut gradient);
+    }
+    //add_regularization(&mut gradient, &tuner.params, portion);
+    gradient
+}
+
+//Adds factor * d(trace.evaluate(params))/d(params) to the gradient. The attack constants only enter the evaluation through
+//an index into the safety table, so their derivative is approximated by a central difference of the table.
+pub fn add_evaluation_gradient(params: &Parameters, trace: &CollapsedTrace, factor: f64, tune_attack: bool, tune_attack_index: bool, gradient: &mut Parameters) {
+    if trace.is_guaranteed_draw {
+        return;
+    }
+    //The endgame score is rescaled in drawish endgames
+    let endgame_scale = if trace.slightly_winning_no_pawn {
+        params.special[IDX_SLIGHTLY_WINNING_NO_PAWN]
+    } else if trace.slightly_winning_enemy_can_sac {
+        params.special[IDX_SLIGHTLY_WINNING_ENEMY_CAN_SAC]
+    } else {
+        1.
+    };
+    let devaldmg = trace.phase as f64 / 128.0;
+    let devaldeg = (1. - trace.phase as f64 / 128.0) * endgame_scale;
+    for entry in trace.normal_tunable_coeffs.iter() {
+        //All entries left in the trace are tunable, others are collapsed.
+        gradient.normal[0][entry.0 as usize] += factor * devaldmg * f64::from(entry.1);
+        gradient.normal[1][entry.0 as usize] += factor * devaldeg * f64::from(entry.1);
+    }
+    //Safety
+    if tune_attack {
+        for i in 0..2 {
+            let devaldg = if i == 0 { devaldmg } else { devaldeg };
+            let attack_knight_white = f64::from(trace.knight_attacked_sq[WHITE]) * params.special[IDX_KNIGHT_ATTACK_VALUE + i];
+            let attack_bishop_white = f64::from(trace.bishop_attacked_sq[WHITE]) * params.special[IDX_BISHOP_ATTACK_VALUE + i];
+            let attack_rook_white = f64::from(trace.rook_attacked_sq[WHITE]) * params.special[IDX_ROOK_ATTACK_VALUE + i];
+            let attack_queen_white = f64::from(trace.queen_attacked_sq[WHITE]) * params.special[IDX_QUEEN_ATTACK_VALUE + i];
+            let knight_check_white = f64::from(trace.knight_safe_check[WHITE]) * params.special[IDX_KNIGHT_CHECK_VALUE + i];
+            let bishop_check_white = f64::from(trace.bishop_safe_check[WHITE]) * params.special[IDX_BISHOP_CHECK_VALUE + i];
+            let rook_check_white = f64::from(trace.rook_safe_check[WHITE]) * params.special[IDX_ROOK_CHECK_VALUE + i];
+            let queen_check_white = f64::from(trace.queen_safe_check[WHITE]) * params.special[IDX_QUEEN_CHECK_VALUE + i];
+            let attacker_sum_white =
+                attack_knight_white + attack_bishop_white + attack_rook_white + attack_queen_white + knight_check_white + bishop_check_white + rook_check_white + queen_check_white;
+            let attacker_value_white = attacker_sum_white.clamp(0., 99.);
+            let attack_knight_black = f64::from(trace.knight_attacked_sq[BLACK]) * params.special[IDX_KNIGHT_ATTACK_VALUE + i];
+            let attack_bishop_black = f64::from(trace.bishop_attacked_sq[BLACK]) * params.special[IDX_BISHOP_ATTACK_VALUE + i];
+            let attack_rook_black = f64::from(trace.rook_attacked_sq[BLACK]) * params.special[IDX_ROOK_ATTACK_VALUE + i];
+            let attack_queen_black = f64::from(trace.queen_attacked_sq[BLACK]) * params.special[IDX_QUEEN_ATTACK_VALUE + i];
+            let knight_check_black = f64::from(trace.knight_safe_check[BLACK]) * params.special[IDX_KNIGHT_CHECK_VALUE + i];
+            let bishop_check_black = f64::from(trace.bishop_safe_check[BLACK]) * params.special[IDX_BISHOP_CHECK_VALUE + i];
+            let rook_check_black = f64::from(trace.rook_safe_check[BLACK]) * params.special[IDX_ROOK_CHECK_VALUE + i];
+            let queen_check_black = f64::from(trace.queen_safe_check[BLACK]) * params.special[IDX_QUEEN_CHECK_VALUE + i];
+            let attacker_sum_black =
+                attack_knight_black + attack_bishop_black + attack_rook_black + attack_queen_black + knight_check_black + bishop_check_black + rook_check_black + queen_check_black;
+            let attacker_value_black = attacker_sum_black.clamp(0., 99.);
+            gradient.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[WHITE] as usize + i] +=
+                factor * devaldg / 100.0 * params.special[IDX_SAFETY_TABLE + 2 * attacker_value_white as usize + i];
+            gradient.special[IDX_SAFETY_TABLE + 2 * attacker_value_white as usize + i] +=
+                factor * devaldg / 100.0 * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[WHITE] as usize + i];
+            gradient.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[BLACK] as usize + i] -=
+                factor * devaldg / 100.0 * params.special[IDX_SAFETY_TABLE + 2 * attacker_value_black as usize + i];
+            gradient.special[IDX_SAFETY_TABLE + 2 * attacker_value_black as usize + i] -=
+                factor * devaldg / 100.0 * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[BLACK] as usize + i];
+            //Attack constants
+            if tune_attack_index {
+                //Knight
+                {
+                    let c = params.special[IDX_KNIGHT_ATTACK_VALUE + i];
+                    gradient.special[IDX_KNIGHT_ATTACK_VALUE + i] += factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[WHITE] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_white - attack_knight_white, trace.knight_attacked_sq[WHITE], c)
+                        / 100.0;
+                    gradient.special[IDX_KNIGHT_ATTACK_VALUE + i] -= factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[BLACK] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_black - attack_knight_black, trace.knight_attacked_sq[BLACK], c)
+                        / 100.0;
+                }
+                //Bishop
+                {
+                    let c = params.special[IDX_BISHOP_ATTACK_VALUE + i];
+                    gradient.special[IDX_BISHOP_ATTACK_VALUE + i] += factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[WHITE] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_white - attack_bishop_white, trace.bishop_attacked_sq[WHITE], c)
+                        / 100.0;
+                    gradient.special[IDX_BISHOP_ATTACK_VALUE + i] -= factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[BLACK] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_black - attack_bishop_black, trace.bishop_attacked_sq[BLACK], c)
+                        / 100.0;
+                }
+                //Rook
+                {
+                    let c = params.special[IDX_ROOK_ATTACK_VALUE + i];
+                    gradient.special[IDX_ROOK_ATTACK_VALUE + i] += factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[WHITE] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_white - attack_rook_white, trace.rook_attacked_sq[WHITE], c)
+                        / 100.0;
+                    gradient.special[IDX_ROOK_ATTACK_VALUE + i] -= factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[BLACK] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_black - attack_rook_black, trace.rook_attacked_sq[BLACK], c)
+                        / 100.0;
+                }
+                //Queen
+                {
+                    let c = params.special[IDX_QUEEN_ATTACK_VALUE + i];
+                    gradient.special[IDX_QUEEN_ATTACK_VALUE + i] += factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[WHITE] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_white - attack_queen_white, trace.queen_attacked_sq[WHITE], c)
+                        / 100.0;
+                    gradient.special[IDX_QUEEN_ATTACK_VALUE + i] -= factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[BLACK] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_black - attack_queen_black, trace.queen_attacked_sq[BLACK], c)
+                        / 100.0;
+                }
+                //Knight check
+                {
+                    let c = params.special[IDX_KNIGHT_CHECK_VALUE + i];
+                    gradient.special[IDX_KNIGHT_CHECK_VALUE + i] += factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[WHITE] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_white - knight_check_white, trace.knight_safe_check[WHITE], c)
+                        / 100.0;
+                    gradient.special[IDX_KNIGHT_CHECK_VALUE + i] -= factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[BLACK] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_black - knight_check_black, trace.knight_safe_check[BLACK], c)
+                        / 100.0;
+                }
+                //Bishop check
+                {
+                    let c = params.special[IDX_BISHOP_CHECK_VALUE + i];
+                    gradient.special[IDX_BISHOP_CHECK_VALUE + i] += factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[WHITE] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_white - bishop_check_white, trace.bishop_safe_check[WHITE], c)
+                        / 100.0;
+                    gradient.special[IDX_BISHOP_CHECK_VALUE + i] -= factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[BLACK] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_black - bishop_check_black, trace.bishop_safe_check[BLACK], c)
+                        / 100.0;
+                }
+                //Rook check
+                {
+                    let c = params.special[IDX_ROOK_CHECK_VALUE + i];
+                    gradient.special[IDX_ROOK_CHECK_VALUE + i] += factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[WHITE] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_white - rook_check_white, trace.rook_safe_check[WHITE], c)
+                        / 100.0;
+                    gradient.special[IDX_ROOK_CHECK_VALUE + i] -= factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[BLACK] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_black - rook_check_black, trace.rook_safe_check[BLACK], c)
+                        / 100.0;
+                }
+                //Queen check
+                {
+                    let c = params.special[IDX_QUEEN_CHECK_VALUE + i];
+                    gradient.special[IDX_QUEEN_CHECK_VALUE + i] += factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[WHITE] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_white - queen_check_white, trace.queen_safe_check[WHITE], c)
+                        / 100.0;
+                    gradient.special[IDX_QUEEN_CHECK_VALUE + i] -= factor
+                        * devaldg
+                        * params.special[IDX_ATTACK_WEIGHT + 2 * trace.attackers[BLACK] as usize + i]
+                        * dsafetytabledconstant(params, i, attacker_sum_black - queen_check_black, trace.queen_safe_check[BLACK], c)
+                        / 100.0;
                 }
             }
         }
     }
-    //add_regularization(&mut gradient, &tuner.params, portion);
-    gradient
 }
 
-pub fn dsafetytabledconstant(tuner: &Tuner, phase: usize, other: f64, relevant_feature: u8, current_constant: f64) -> f64 {
-    let safety_table_inc = tuner.params.special[IDX_SAFETY_TABLE + 2 * ((other + f64::from(relevant_feature) * (current_constant + 1.)) as usize).max(0).min(99) + phase];
-    let safety_table_dec = tuner.params.special[IDX_SAFETY_TABLE + 2 * ((other + f64::from(relevant_feature) * (current_constant - 1.)) as usize).max(0).min(99) + phase];
+pub fn dsafetytabledconstant(params: &Parameters, phase: usize, other: f64, relevant_feature: u8, current_constant: f64) -> f64 {
+    let safety_table_inc = params.special[IDX_SAFETY_TABLE + 2 * ((other + f64::from(relevant_feature) * (current_constant + 1.)) as usize).max(0).min(99) + phase];
+    let safety_table_dec = params.special[IDX_SAFETY_TABLE + 2 * ((other + f64::from(relevant_feature) * (current_constant - 1.)) as usize).max(0).min(99) + phase];
 
     (safety_table_inc - safety_table_dec) / 2.
 }
 
-pub unsafe fn texel_tuning(tuner: Tuner, threads: usize) {
+pub fn checkpoint_file() -> String {
+    format!("{}checkpoint.bin", PARAM_FILE)
+}
+
+pub unsafe fn texel_tuning(tuner: Tuner, threads: usize, optimizer: &mut dyn Optimizer, resume: bool, lambda: f32) {
     let tuner = Arc::new(TunerWrapper(UnsafeCell::new(tuner)));
-    let mut best_error = average_evaluation_error(tuner.get());
-    println!("Error in epoch 0: {}", best_error);
-    let mut epoch = 0;
-    let lr = START_LEARNING_RATE;
-    let mut adagrad = Parameters::zero();
+    let mut progress = if resume {
+        let progress = load_checkpoint(&checkpoint_file(), tuner.get(), optimizer).expect("Could not load checkpoint");
+        update_evaluations(tuner.get());
+        blend_labels(tuner.get(), lambda);
+        println!("Resuming from epoch {} with optimizer {}", progress.epoch, optimizer.name());
+        progress
+    } else {
+        blend_labels(tuner.get(), lambda);
+        TuningProgress::new(average_evaluation_error(tuner.get()), validation_error(tuner.get()))
+    };
+    println!("Error in epoch {}: {}", progress.epoch, progress.best_error);
+    println!("Validation error in epoch {}: {}", progress.epoch, progress.best_validation_error);
 
     loop {
-        epoch += 1;
-        println!("Starting epoch {}!", epoch);
+        progress.epoch += 1;
+        println!("Starting epoch {}!", progress.epoch);
         shuffle_positions(tuner.get());
-        let mut ada_add = Parameters::zero();
         for batch in 0..=(tuner.get().positions.len() - 1) / BATCH_SIZE {
             let from = batch * BATCH_SIZE;
             let to = ((batch + 1) * BATCH_SIZE).min(tuner.get().positions.len());
@@ -447,40 +523,67 @@ pub unsafe fn texel_tuning(tuner: Tuner, threads: usize) {
             for handle in thread_handles.into_iter() {
                 gradient.add(&handle.join().unwrap(), 1.);
             }
-            gradient.scale(2. / ((to - from) as f64));
-            ada_add.add(&gradient, 1.);
-
-            let mut ada_lr = adagrad.clone();
-            ada_lr.add_scalar(1e-6);
-            ada_lr.sqrt();
-            gradient.mul_inverse_other(&ada_lr);
-            tuner.get().params.add(&gradient, lr);
+            gradient.scale(2. / total_weight(&tuner.get().positions[from..to]));
+            optimizer.step(&mut tuner.get().params, &gradient);
         }
-        ada_add.square();
-        adagrad.add(&ada_add, 1.);
+        optimizer.end_epoch();
 
         update_evaluations(tuner.get());
         let error = average_evaluation_error(tuner.get());
-        println!("Error in epoch {}: {}", epoch, error);
-        if error < best_error {
-            best_error = error;
+        let val_error = validation_error(tuner.get());
+        println!("Error in epoch {}: {}", progress.epoch, error);
+        println!("Validation error in epoch {}: {}", progress.epoch, val_error);
+        if error < progress.best_error {
+            progress.best_error = error;
+        }
+        if val_error < progress.best_validation_error {
+            progress.best_validation_error = val_error;
+            progress.epochs_without_improvement = 0;
             tuner.get().params.write_to_file(&format!("{}tunebest.txt", PARAM_FILE));
             println!("Saved new best params in tunebest.txt");
+        } else {
+            progress.epochs_without_improvement += 1;
         }
         //Save progress
-        if (epoch + 1) % 10 == 0 {
-            tuner.get().params.write_to_file(&format!("{}tune{}.txt", PARAM_FILE, epoch + 1));
+        if (progress.epoch + 1) % 10 == 0 {
+            tuner.get().params.write_to_file(&format!("{}tune{}.txt", PARAM_FILE, progress.epoch + 1));
             println!("Saved general progress params in tune.txt");
         }
+        let stop = progress.epochs_without_improvement >= EARLY_STOPPING_PATIENCE;
+        if progress.epoch % CHECKPOINT_INTERVAL == 0 || stop {
+            match save_checkpoint(&checkpoint_file(), tuner.get(), optimizer, &progress) {
+                Ok(()) => println!("Saved checkpoint in {}", checkpoint_file()),
+                Err(e) => println!("Could not save checkpoint: {}", e),
+            }
+        }
+        if stop {
+            println!(
+                "Validation error did not improve for {} epochs, stopping. Best validation error: {}",
+                progress.epochs_without_improvement, progress.best_validation_error
+            );
+            break;
+        }
     }
 }
 
 pub fn average_evaluation_error(tuner: &Tuner) -> f64 {
     let mut res = 0.;
     for pos in &tuner.positions {
-        res += (pos.label as f64 - sigmoid(tuner.k, pos.eval)).powf(2.0);
+        res += f64::from(pos.weight) * (pos.label as f64 - sigmoid(tuner.k, pos.eval)).powf(2.0);
+    }
+    res / total_weight(&tuner.positions)
+}
+
+//Falls back to the training error if no positions were held back.
+pub fn validation_error(tuner: &Tuner) -> f64 {
+    if tuner.validation_positions.is_empty() {
+        return average_evaluation_error(tuner);
+    }
+    let mut res = 0.;
+    for pos in &tuner.validation_positions {
+        res += f64::from(pos.weight) * (pos.label as f64 - sigmoid(tuner.k, pos.trace.evaluate(&tuner.params))).powf(2.0);
     }
-    res / tuner.positions.len() as f64
+    res / total_weight(&tuner.validation_positions)
 }
 
 pub fn minimize_evaluation_error_fork(tuner: &mut Tuner) -> f64 {
@@ -503,9 +606,9 @@ pub fn minimize_evaluation_error_fork(tuner: &mut Tuner) -> f64 {
             let mut dedk = 0.;
             for pos in &tuner.positions[from..to] {
                 let eval = pos.eval;
-                dedk += (pos.label as f64 - sigmoid(tuner.k, eval)) * dsigmoiddk(tuner.k, eval);
+                dedk += f64::from(pos.weight) * (pos.label as f64 - sigmoid(tuner.k, eval)) * dsigmoiddk(tuner.k, eval);
             }
-            dedk *= -2.0 / (to - from) as f64;
+            dedk *= -2.0 / total_weight(&tuner.positions[from..to]);
             tuner.k += -lr * dedk;
         }
 
@@ -1,7 +1,8 @@
 use super::TexelState;
-use crate::TUNABLE_PARAM;
+use crate::{position_weight, TUNABLE_PARAM};
 use core_sdk::evaluation::parameters::Parameters;
 use core_sdk::{board_representation::game_state::GameState, evaluation::eval_game_state};
+use extended_sdk::packed::{read_packed_header, read_packed_position};
 use std::fmt::{Display, Formatter, Result};
 use std::fs;
 use std::fs::File;
@@ -11,11 +12,28 @@ pub enum FileFormatSupported {
     OwnEncoding,
     EPD,
     PGN,
+    Packed,
+}
+
+impl FileFormatSupported {
+    pub fn from_file_name(file: &str) -> Self {
+        if file.ends_with(".epd") {
+            FileFormatSupported::EPD
+        } else if file.ends_with(".pgn") {
+            FileFormatSupported::PGN
+        } else if file.ends_with(".pack") {
+            FileFormatSupported::Packed
+        } else {
+            FileFormatSupported::OwnEncoding
+        }
+    }
 }
 
 pub struct LabelledGameState {
     pub game_state: GameState,
     pub label: f32,
+    //Search score in centipawns from white's point of view, if known
+    pub score: Option<i16>,
 }
 
 pub struct Statistics {
@@ -72,13 +90,26 @@ pub struct PositionLoader {
 }
 impl PositionLoader {
     pub fn new(from_file: &str, file_format: FileFormatSupported) -> Self {
+        let mut reader = BufReader::new(File::open(from_file).expect("Could not open file"));
+        if let FileFormatSupported::Packed = file_format {
+            read_packed_header(&mut reader).expect("Invalid packed position file");
+        }
         PositionLoader {
-            reader: BufReader::new(File::open(from_file).expect("Could not open file")),
+            reader,
             file_format,
             parameter: Parameters::default(),
         }
     }
     pub fn next_position(&mut self) -> Option<LabelledGameState> {
+        if let FileFormatSupported::Packed = self.file_format {
+            return match read_packed_position(&mut self.reader).and_then(|packed| packed.map(|packed| packed.unpack()).transpose()) {
+                Ok(position) => position.map(|(game_state, label, score)| LabelledGameState { game_state, label, score }),
+                Err(e) => {
+                    println!("Stopped loading packed positions: {}", e);
+                    None
+                }
+            };
+        }
         let mut line = String::new();
         self.reader.read_line(&mut line).unwrap();
         if let FileFormatSupported::OwnEncoding = self.file_format {
@@ -100,6 +131,7 @@ impl PositionLoader {
             return Some(LabelledGameState {
                 game_state: state,
                 label: game_result,
+                score: None,
             });
         } else if let FileFormatSupported::EPD = self.file_format {
             if line.is_empty() {
@@ -117,6 +149,7 @@ impl PositionLoader {
             return Some(LabelledGameState {
                 game_state: state,
                 label: game_result,
+                score: None,
             });
         }
         None
@@ -129,7 +162,14 @@ impl PositionLoader {
             let eval = eval_game_state(&state.game_state);
             let trace = eval.trace.collapse(&TUNABLE_PARAM, &self.parameter);
             let eval = trace.evaluate(&self.parameter);
-            return Some(TexelState { label: state.label, eval, trace });
+            return Some(TexelState {
+                label: state.label,
+                result: state.label,
+                score: state.score,
+                weight: position_weight(&state.game_state),
+                eval,
+                trace,
+            });
         }
         None
     }
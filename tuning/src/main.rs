@@ -1,24 +1,50 @@
+use rand::{thread_rng, Rng};
 use std::thread;
 use std::time::Instant;
 use tuning::*;
 
 pub fn main() {
-    let mut args = std::env::args();
-    let threads = if args.nth(1) == Some("t".to_owned()) {
-        args.nth(0).unwrap().parse::<usize>().unwrap()
-    } else {
-        1
-    };
+    //Usage: tuning [t <threads>] [o <sgd|adagrad|adam>] [lr <learning rate>] [lambda <share of the game result in the label>] [resume]
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let mut threads = 1;
+    let mut optimizer = "adagrad".to_owned();
+    let mut lr = START_LEARNING_RATE;
+    let mut lambda = LAMBDA;
+    let mut resume = false;
+    let mut index = 0;
+    while index < args.len() {
+        match args[index].as_str() {
+            "t" => {
+                threads = args[index + 1].parse::<usize>().expect("Invalid thread count");
+                index += 1;
+            }
+            "o" => {
+                optimizer = args[index + 1].clone();
+                index += 1;
+            }
+            "lr" => {
+                lr = args[index + 1].parse::<f64>().expect("Invalid learning rate");
+                index += 1;
+            }
+            "lambda" => {
+                lambda = args[index + 1].parse::<f32>().expect("Invalid lambda");
+                index += 1;
+            }
+            "resume" => resume = true,
+            other => panic!("Unknown argument {}", other),
+        }
+        index += 1;
+    }
     let t = thread::Builder::new()
         .stack_size(12 * 1024 * 1024)
         .spawn(move || {
-            actual_main(threads);
+            actual_main(threads, &optimizer, lr, lambda, resume);
         })
         .expect("Couldn't start thread");
     t.join().expect("Could not join thread");
 }
 
-pub fn actual_main(threads: usize) {
+pub fn actual_main(threads: usize, optimizer: &str, lr: f64, lambda: f32, resume: bool) {
     //Step 1. Load all positions from a file. Those positions should already be the q-searched positions.
     let position_files = [
         "D:/FenCollection/Andrews/E12.33-1M-D12-Resolved.epd",
@@ -41,7 +67,7 @@ pub fn actual_main(threads: usize) {
                 .spawn(move || {
                     let mut positions: Vec<TexelState> = Vec::with_capacity(1);
                     for &file in my_pos.iter() {
-                        tuning::loading::PositionLoader::new(file, FileFormatSupported::EPD).load_texel_positions(&mut positions);
+                        load_texel_positions_cached(file, FileFormatSupported::from_file_name(file), &mut positions);
                     }
                     positions
                 })
@@ -53,15 +79,25 @@ pub fn actual_main(threads: usize) {
     }
     println!("Loaded {} positions!", positions.len());
     println!("Took {}ms", Instant::now().duration_since(now).as_millis());
+    let split_seed = if resume {
+        read_checkpoint_split_seed(&checkpoint_file()).expect("Could not read checkpoint")
+    } else {
+        thread_rng().gen()
+    };
+    let validation_positions = split_validation_positions(&mut positions, split_seed);
+    println!("Holding back {} positions for validation", validation_positions.len());
     let mut tuner = Tuner {
-        k: 1.1155,
+        k: DEFAULT_K,
         positions,
+        validation_positions,
         params: Parameters::default(),
+        split_seed,
     };
     println!("Start tuning for k");
     if OPTIMIZE_K {
         minimize_evaluation_error_fork(&mut tuner);
     }
     println!("Optimal K: {}", tuner.k);
-    unsafe { texel_tuning(tuner, threads) };
+    let mut optimizer = optimizer_from_name(optimizer, lr).expect("Unknown optimizer");
+    unsafe { texel_tuning(tuner, threads, optimizer.as_mut(), resume, lambda) };
 }
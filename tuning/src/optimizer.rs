@@ -0,0 +1,149 @@
+use crate::checkpoint::{read_f64, read_learning_rate, read_parameters, write_f64, write_parameters};
+use core_sdk::evaluation::parameters::Parameters;
+use std::io::{Read, Result, Write};
+
+pub const ADAGRAD_EPSILON: f64 = 1e-6;
+pub const ADAM_BETA1: f64 = 0.9;
+pub const ADAM_BETA2: f64 = 0.999;
+pub const ADAM_EPSILON: f64 = 1e-8;
+
+//The gradient handed to an optimizer is the one returned by `calculate_gradient`, which already points
+//in the direction of decreasing error. Optimizers therefore add their update to the parameters.
+pub trait Optimizer {
+    fn name(&self) -> &'static str;
+    fn step(&mut self, params: &mut Parameters, gradient: &Parameters);
+    fn end_epoch(&mut self) {}
+    fn save_state(&self, writer: &mut dyn Write) -> Result<()>;
+    fn load_state(&mut self, reader: &mut dyn Read) -> Result<()>;
+}
+
+pub fn optimizer_from_name(name: &str, lr: f64) -> Option<Box<dyn Optimizer>> {
+    match name.to_lowercase().as_str() {
+        "sgd" => Some(Box::new(Sgd::new(lr))),
+        "adagrad" => Some(Box::new(AdaGrad::new(lr))),
+        "adam" => Some(Box::new(Adam::new(lr))),
+        _ => None,
+    }
+}
+
+pub struct Sgd {
+    pub lr: f64,
+}
+impl Sgd {
+    pub fn new(lr: f64) -> Self {
+        Sgd { lr }
+    }
+}
+impl Optimizer for Sgd {
+    fn name(&self) -> &'static str {
+        "sgd"
+    }
+    fn step(&mut self, params: &mut Parameters, gradient: &Parameters) {
+        params.add(gradient, self.lr);
+    }
+    fn save_state(&self, writer: &mut dyn Write) -> Result<()> {
+        write_f64(writer, self.lr)
+    }
+    fn load_state(&mut self, reader: &mut dyn Read) -> Result<()> {
+        read_learning_rate(reader, self.lr)
+    }
+}
+
+//The squared gradients are accumulated per epoch, not per batch.
+pub struct AdaGrad {
+    pub lr: f64,
+    pub accumulated: Parameters,
+    pub epoch_gradient: Parameters,
+}
+impl AdaGrad {
+    pub fn new(lr: f64) -> Self {
+        AdaGrad {
+            lr,
+            accumulated: Parameters::zero(),
+            epoch_gradient: Parameters::zero(),
+        }
+    }
+}
+impl Optimizer for AdaGrad {
+    fn name(&self) -> &'static str {
+        "adagrad"
+    }
+    fn step(&mut self, params: &mut Parameters, gradient: &Parameters) {
+        self.epoch_gradient.add(gradient, 1.);
+        let mut ada_lr = self.accumulated.clone();
+        ada_lr.add_scalar(ADAGRAD_EPSILON);
+        ada_lr.sqrt();
+        let mut update = gradient.clone();
+        update.mul_inverse_other(&ada_lr);
+        params.add(&update, self.lr);
+    }
+    fn end_epoch(&mut self) {
+        self.epoch_gradient.square();
+        self.accumulated.add(&self.epoch_gradient, 1.);
+        self.epoch_gradient = Parameters::zero();
+    }
+    fn save_state(&self, writer: &mut dyn Write) -> Result<()> {
+        write_f64(writer, self.lr)?;
+        write_parameters(writer, &self.accumulated)?;
+        write_parameters(writer, &self.epoch_gradient)
+    }
+    fn load_state(&mut self, reader: &mut dyn Read) -> Result<()> {
+        read_learning_rate(reader, self.lr)?;
+        self.accumulated = read_parameters(reader)?;
+        self.epoch_gradient = read_parameters(reader)?;
+        Ok(())
+    }
+}
+
+pub struct Adam {
+    pub lr: f64,
+    pub steps: f64,
+    pub first_moment: Parameters,
+    pub second_moment: Parameters,
+}
+impl Adam {
+    pub fn new(lr: f64) -> Self {
+        Adam {
+            lr,
+            steps: 0.,
+            first_moment: Parameters::zero(),
+            second_moment: Parameters::zero(),
+        }
+    }
+}
+impl Optimizer for Adam {
+    fn name(&self) -> &'static str {
+        "adam"
+    }
+    fn step(&mut self, params: &mut Parameters, gradient: &Parameters) {
+        self.steps += 1.;
+        self.first_moment.scale(ADAM_BETA1);
+        self.first_moment.add(gradient, 1. - ADAM_BETA1);
+        let mut squared_gradient = gradient.clone();
+        squared_gradient.square();
+        self.second_moment.scale(ADAM_BETA2);
+        self.second_moment.add(&squared_gradient, 1. - ADAM_BETA2);
+
+        //Bias correction
+        let mut denominator = self.second_moment.clone();
+        denominator.scale(1. / (1. - ADAM_BETA2.powf(self.steps)));
+        denominator.sqrt();
+        denominator.add_scalar(ADAM_EPSILON);
+        let mut update = self.first_moment.clone();
+        update.mul_inverse_other(&denominator);
+        params.add(&update, self.lr / (1. - ADAM_BETA1.powf(self.steps)));
+    }
+    fn save_state(&self, writer: &mut dyn Write) -> Result<()> {
+        write_f64(writer, self.lr)?;
+        write_f64(writer, self.steps)?;
+        write_parameters(writer, &self.first_moment)?;
+        write_parameters(writer, &self.second_moment)
+    }
+    fn load_state(&mut self, reader: &mut dyn Read) -> Result<()> {
+        read_learning_rate(reader, self.lr)?;
+        self.steps = read_f64(reader)?;
+        self.first_moment = read_parameters(reader)?;
+        self.second_moment = read_parameters(reader)?;
+        Ok(())
+    }
+}
@@ -52,11 +52,13 @@ fn main() {
         quiet_nonstripped.push(LabelledGameState {
             game_state: state.clone(),
             label: position.label,
+            score: position.score,
         });
         if score.abs() < 1000 {
             quiet_stripped.push(LabelledGameState {
                 game_state: state,
                 label: position.label,
+                score: position.score,
             });
         }
     }
@@ -81,7 +83,7 @@ pub fn stripped_q_search(
     see_buffer: &mut Vec<i16>,
 ) -> (i16, GameState) {
     //Check for draw
-    if let SearchInstruction::StopSearching(res) = check_for_draw(&game_state, history) {
+    if let SearchInstruction::StopSearching(res) = check_for_draw(&game_state, history, 0) {
         return (res, game_state);
     }
     let incheck = game_state.in_check();
@@ -158,7 +160,7 @@ pub fn make_moves(
         if let GameMoveType::EnPassant = mv.move_type {
             gmv.1 = Some(100.0);
         } else {
-            if !incheck && !passes_delta_pruning(mv, phase, stand_pat, alpha) {
+            if !incheck && !passes_delta_pruning(mv, phase, stand_pat, alpha, DELTA_PRUNING) {
                 gmv.1 = Some(-1.);
                 continue;
             }
@@ -0,0 +1,265 @@
+use crate::checkpoint::{read_f64, read_u64, write_f64, write_u64};
+use crate::loading::{FileFormatSupported, PositionLoader};
+use crate::{TexelState, EARLY_GAME_FULL_MOVES, EARLY_GAME_WEIGHT, TUNABLE_PARAM};
+use core_sdk::evaluation::parameters::normal_parameters::NORMAL_PARAMS;
+use core_sdk::evaluation::parameters::Parameters;
+use core_sdk::evaluation::trace::{CollapsedTrace, TraceEntry};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+pub const TRACE_CACHE_MAGIC: &[u8; 4] = b"FCTR";
+pub const TRACE_CACHE_VERSION: u32 = 4;
+
+struct Fnv(u64);
+impl Fnv {
+    const PRIME: u64 = 0x0100_0000_01b3;
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+    fn feed(&mut self, value: u64) {
+        for byte in value.to_le_bytes().iter() {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Fnv::PRIME);
+        }
+    }
+}
+
+//Collapsed traces bake every non tunable parameter into their base evaluation, and the weights are computed while
+//loading, so a cache is only valid for the same tunable parameters, starting values and weight settings. Labels are blended after loading.
+pub fn parameter_set_version(tunable_params: &[bool; NORMAL_PARAMS], params: &Parameters) -> u64 {
+    let mut hash = Fnv::new();
+    let mut feed = |value: u64| hash.feed(value);
+    for &tunable in tunable_params.iter() {
+        feed(tunable as u64);
+    }
+    for phase in 0..2 {
+        for &value in params.normal[phase].iter() {
+            feed(value.to_bits());
+        }
+    }
+    for &value in params.special.iter() {
+        feed(value.to_bits());
+    }
+    feed(EARLY_GAME_FULL_MOVES as u64);
+    feed(u64::from(EARLY_GAME_WEIGHT.to_bits()));
+    hash.0
+}
+
+//A regenerated position file has a different size or modification time, so its cache is rebuilt
+pub fn source_file_version(position_file: &str) -> Result<u64> {
+    let metadata = fs::metadata(position_file)?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let mut hash = Fnv::new();
+    for byte in position_file.bytes() {
+        hash.feed(u64::from(byte));
+    }
+    hash.feed(metadata.len());
+    hash.feed(modified.as_secs());
+    hash.feed(u64::from(modified.subsec_nanos()));
+    Ok(hash.0)
+}
+
+pub fn trace_cache_file(position_file: &str) -> String {
+    format!("{}.traces", position_file)
+}
+
+fn write_side_features(writer: &mut dyn Write, features: &[u8; 2]) -> Result<()> {
+    writer.write_all(features)
+}
+fn read_side_features(reader: &mut dyn Read) -> Result<[u8; 2]> {
+    let mut buf = [0u8; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+pub fn write_texel_state(writer: &mut dyn Write, state: &TexelState) -> Result<()> {
+    let trace = &state.trace;
+    writer.write_all(&state.result.to_le_bytes())?;
+    writer.write_all(&[state.score.is_some() as u8])?;
+    writer.write_all(&state.score.unwrap_or(0).to_le_bytes())?;
+    writer.write_all(&state.weight.to_le_bytes())?;
+    write_f64(writer, trace.base_eval.0)?;
+    write_f64(writer, trace.base_eval.1)?;
+    writer.write_all(&trace.phase.to_le_bytes())?;
+    writer.write_all(&(trace.normal_tunable_coeffs.len() as u16).to_le_bytes())?;
+    for entry in trace.normal_tunable_coeffs.iter() {
+        writer.write_all(&entry.0.to_le_bytes())?;
+        writer.write_all(&entry.1.to_le_bytes())?;
+    }
+    writer.write_all(&trace.knights.to_le_bytes())?;
+    for features in [
+        &trace.attackers,
+        &trace.knight_attacked_sq,
+        &trace.bishop_attacked_sq,
+        &trace.rook_attacked_sq,
+        &trace.queen_attacked_sq,
+        &trace.knight_safe_check,
+        &trace.bishop_safe_check,
+        &trace.rook_safe_check,
+        &trace.queen_safe_check,
+    ]
+    .iter()
+    {
+        write_side_features(writer, features)?;
+    }
+    writer.write_all(&[
+        trace.is_guaranteed_draw as u8,
+        trace.slightly_winning_no_pawn as u8,
+        trace.slightly_winning_enemy_can_sac as u8,
+    ])
+}
+
+pub fn read_texel_state(reader: &mut dyn Read, params: &Parameters) -> Result<TexelState> {
+    let mut buf4 = [0u8; 4];
+    reader.read_exact(&mut buf4)?;
+    let result = f32::from_le_bytes(buf4);
+    let mut buf1 = [0u8; 1];
+    reader.read_exact(&mut buf1)?;
+    let has_score = buf1[0] != 0;
+    let mut buf2 = [0u8; 2];
+    reader.read_exact(&mut buf2)?;
+    let score = if has_score { Some(i16::from_le_bytes(buf2)) } else { None };
+    reader.read_exact(&mut buf4)?;
+    let weight = f32::from_le_bytes(buf4);
+    let base_eval = (read_f64(reader)?, read_f64(reader)?);
+    reader.read_exact(&mut buf4)?;
+    let phase = f32::from_le_bytes(buf4);
+    reader.read_exact(&mut buf2)?;
+    let entries = u16::from_le_bytes(buf2) as usize;
+    let mut normal_tunable_coeffs = Vec::with_capacity(entries);
+    let mut entry = [0u8; 3];
+    for _ in 0..entries {
+        reader.read_exact(&mut entry)?;
+        normal_tunable_coeffs.push(TraceEntry(u16::from_le_bytes([entry[0], entry[1]]), entry[2] as i8));
+    }
+    reader.read_exact(&mut buf1)?;
+    let knights = buf1[0] as i8;
+    let attackers = read_side_features(reader)?;
+    let knight_attacked_sq = read_side_features(reader)?;
+    let bishop_attacked_sq = read_side_features(reader)?;
+    let rook_attacked_sq = read_side_features(reader)?;
+    let queen_attacked_sq = read_side_features(reader)?;
+    let knight_safe_check = read_side_features(reader)?;
+    let bishop_safe_check = read_side_features(reader)?;
+    let rook_safe_check = read_side_features(reader)?;
+    let queen_safe_check = read_side_features(reader)?;
+    let mut flags = [0u8; 3];
+    reader.read_exact(&mut flags)?;
+    let trace = CollapsedTrace {
+        base_eval,
+        phase,
+        normal_tunable_coeffs,
+        knights,
+        attackers,
+        knight_attacked_sq,
+        bishop_attacked_sq,
+        rook_attacked_sq,
+        queen_attacked_sq,
+        knight_safe_check,
+        bishop_safe_check,
+        rook_safe_check,
+        queen_safe_check,
+        is_guaranteed_draw: flags[0] != 0,
+        slightly_winning_no_pawn: flags[1] != 0,
+        slightly_winning_enemy_can_sac: flags[2] != 0,
+    };
+    let eval = trace.evaluate(params);
+    Ok(TexelState {
+        label: result,
+        result,
+        score,
+        weight,
+        eval,
+        trace,
+    })
+}
+
+pub fn write_trace_cache(to_file: &str, version: u64, source_version: u64, positions: &[TexelState]) -> Result<()> {
+    let tmp_file = format!("{}.tmp", to_file);
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_file)?);
+        writer.write_all(TRACE_CACHE_MAGIC)?;
+        writer.write_all(&TRACE_CACHE_VERSION.to_le_bytes())?;
+        write_u64(&mut writer, version)?;
+        write_u64(&mut writer, source_version)?;
+        write_u64(&mut writer, positions.len() as u64)?;
+        for pos in positions {
+            write_texel_state(&mut writer, pos)?;
+        }
+        writer.flush()?;
+    }
+    fs::rename(&tmp_file, to_file)
+}
+
+//Returns Ok(None) if the cache was written for a different parameter set or position file.
+pub fn read_trace_cache(from_file: &str, version: u64, source_version: u64, params: &Parameters) -> Result<Option<Vec<TexelState>>> {
+    let mut reader = BufReader::new(File::open(from_file)?);
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    if &header[0..4] != TRACE_CACHE_MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{} is not a trace cache", from_file)));
+    }
+    if u32::from_le_bytes([header[4], header[5], header[6], header[7]]) != TRACE_CACHE_VERSION || read_u64(&mut reader)? != version || read_u64(&mut reader)? != source_version {
+        return Ok(None);
+    }
+    let count = read_u64(&mut reader)? as usize;
+    let mut positions = Vec::with_capacity(count);
+    for _ in 0..count {
+        positions.push(read_texel_state(&mut reader, params)?);
+    }
+    Ok(Some(positions))
+}
+
+//Loads the texel positions of a file, reusing the precomputed traces next to it if they were built for the
+//current parameter set and the file did not change since. Otherwise the positions are evaluated and the cache is (re)written.
+pub fn load_texel_positions_cached(from_file: &str, file_format: FileFormatSupported, buf: &mut Vec<TexelState>) {
+    let params = Parameters::default();
+    let version = parameter_set_version(&TUNABLE_PARAM, &params);
+    let cache_file = trace_cache_file(from_file);
+    let source_version = source_file_version(from_file).expect("Could not read position file");
+    if Path::new(&cache_file).exists() {
+        match read_trace_cache(&cache_file, version, source_version, &params) {
+            Ok(Some(positions)) => {
+                buf.extend(positions);
+                return;
+            }
+            Ok(None) => println!("Trace cache {} is outdated, rebuilding", cache_file),
+            Err(e) => println!("Could not read trace cache {}: {}, rebuilding", cache_file, e),
+        }
+    }
+    let mut positions = Vec::new();
+    PositionLoader::new(from_file, file_format).load_texel_positions(&mut positions);
+    if let Err(e) = write_trace_cache(&cache_file, version, source_version, &positions) {
+        println!("Could not write trace cache {}: {}", cache_file, e);
+    }
+    buf.extend(positions);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_trace_cache, source_file_version, write_trace_cache};
+    use core_sdk::evaluation::parameters::Parameters;
+    use std::fs;
+
+    #[test]
+    fn trace_cache_follows_position_file() {
+        let dir = std::env::temp_dir();
+        let position_file = dir.join("fc_trace_cache_positions.epd").to_str().unwrap().to_owned();
+        let cache_file = dir.join("fc_trace_cache_positions.epd.traces").to_str().unwrap().to_owned();
+        fs::write(&position_file, "8/8/8/8/8/8/8/K1k5 w - - 0 1 [0.5]\n").unwrap();
+        let source_version = source_file_version(&position_file).unwrap();
+        write_trace_cache(&cache_file, 1, source_version, &[]).unwrap();
+        assert!(read_trace_cache(&cache_file, 1, source_version, &Parameters::zero()).unwrap().is_some());
+        assert!(read_trace_cache(&cache_file, 2, source_version, &Parameters::zero()).unwrap().is_none());
+
+        //Regenerating the position file invalidates the cache
+        fs::write(&position_file, "8/8/8/8/8/8/8/K1k5 w - - 0 1 [0.5]\n8/8/8/8/8/8/8/K1k5 b - - 0 1 [0.5]\n").unwrap();
+        let new_source_version = source_file_version(&position_file).unwrap();
+        assert_ne!(new_source_version, source_version);
+        assert!(read_trace_cache(&cache_file, 1, new_source_version, &Parameters::zero()).unwrap().is_none());
+        fs::remove_file(&position_file).unwrap();
+        fs::remove_file(&cache_file).unwrap();
+    }
+}
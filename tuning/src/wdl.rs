@@ -0,0 +1,137 @@
+//Fits the coefficients of `core_sdk::evaluation::wdl::WdlModel` to game results, by maximizing the likelihood of the
+//observed outcome of every position.
+use crate::loading::LabelledGameState;
+use crate::optimizer::{ADAM_BETA1, ADAM_BETA2, ADAM_EPSILON};
+use core_sdk::evaluation::wdl::WdlModel;
+
+pub const WDL_LEARNING_RATE: f64 = 2.;
+//Keeps the likelihood of a position finite if the model gives its outcome no chance at all
+const PROBABILITY_EPSILON: f64 = 1e-9;
+
+pub struct WdlSample {
+    //Search score in centipawns from white's point of view
+    pub score: f64,
+    pub material: f64,
+    //1 for a white win, 0.5 for a draw and 0 for a black win
+    pub result: f64,
+}
+
+impl WdlSample {
+    //Only positions with a search score and a game result as label can be used
+    pub fn from_labelled(position: &LabelledGameState) -> Option<WdlSample> {
+        let score = position.score?;
+        if position.label != 0. && position.label != 0.5 && position.label != 1. {
+            return None;
+        }
+        Some(WdlSample {
+            score: f64::from(score),
+            material: WdlModel::material(&position.game_state),
+            result: f64::from(position.label),
+        })
+    }
+}
+
+//Average log likelihood of the samples and its gradient with respect to the coefficients of a and b
+pub fn log_likelihood(model: &WdlModel, samples: &[WdlSample]) -> (f64, [f64; 4], [f64; 4]) {
+    let mut likelihood = 0.;
+    let mut da = [0.; 4];
+    let mut db = [0.; 4];
+    for sample in samples {
+        let a = WdlModel::polynomial(&model.a, sample.material);
+        let b = WdlModel::polynomial(&model.b, sample.material);
+        let win = model.win_probability(sample.score, sample.material);
+        let loss = model.win_probability(-sample.score, sample.material);
+        //Derivatives of the win and loss probability with respect to a and b
+        let (dwin_da, dwin_db) = (-win * (1. - win) / b, -win * (1. - win) * (sample.score - a) / (b * b));
+        let (dloss_da, dloss_db) = (-loss * (1. - loss) / b, -loss * (1. - loss) * (-sample.score - a) / (b * b));
+        let (p, dp_da, dp_db) = if sample.result == 1. {
+            (win, dwin_da, dwin_db)
+        } else if sample.result == 0. {
+            (loss, dloss_da, dloss_db)
+        } else {
+            (1. - win - loss, -dwin_da - dloss_da, -dwin_db - dloss_db)
+        };
+        let p = p.max(PROBABILITY_EPSILON);
+        likelihood += p.ln();
+        let mut power = 1.;
+        for i in 0..4 {
+            da[i] += dp_da / p * power;
+            db[i] += dp_db / p * power;
+            power *= sample.material;
+        }
+    }
+    let n = samples.len().max(1) as f64;
+    for i in 0..4 {
+        da[i] /= n;
+        db[i] /= n;
+    }
+    (likelihood / n, da, db)
+}
+
+//Runs Adam on the full set of samples, starting from `model`
+pub fn fit_wdl_model(mut model: WdlModel, samples: &[WdlSample], iterations: usize, lr: f64, verbose: bool) -> WdlModel {
+    let mut first_moment = [0.; 8];
+    let mut second_moment = [0.; 8];
+    for iteration in 1..=iterations {
+        let (likelihood, da, db) = log_likelihood(&model, samples);
+        if verbose && (iteration == 1 || iteration % 100 == 0) {
+            println!("Iteration {}: log likelihood {:.6}, a: {:?}, b: {:?}", iteration, likelihood, model.a, model.b);
+        }
+        for i in 0..8 {
+            let gradient = if i < 4 { da[i] } else { db[i - 4] };
+            first_moment[i] = ADAM_BETA1 * first_moment[i] + (1. - ADAM_BETA1) * gradient;
+            second_moment[i] = ADAM_BETA2 * second_moment[i] + (1. - ADAM_BETA2) * gradient * gradient;
+            let m = first_moment[i] / (1. - ADAM_BETA1.powi(iteration as i32));
+            let v = second_moment[i] / (1. - ADAM_BETA2.powi(iteration as i32));
+            let step = lr * m / (v.sqrt() + ADAM_EPSILON);
+            if i < 4 {
+                model.a[i] += step;
+            } else {
+                model.b[i - 4] += step;
+            }
+        }
+    }
+    model
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn fit_recovers_generating_model() {
+        let truth = WdlModel {
+            a: [80., 60., 0., 0.],
+            b: [90., 40., 0., 0.],
+        };
+        let mut rng = StdRng::seed_from_u64(0x3d1);
+        let mut samples = Vec::new();
+        for _ in 0..20_000 {
+            let score = rng.gen_range(-600., 600.);
+            let material = rng.gen_range(0., 1.);
+            let (win, loss) = (truth.win_probability(score, material), truth.win_probability(-score, material));
+            let outcome = rng.gen_range(0., 1.);
+            let result = if outcome < win {
+                1.
+            } else if outcome < win + loss {
+                0.
+            } else {
+                0.5
+            };
+            samples.push(WdlSample { score, material, result });
+        }
+        let start = WdlModel {
+            a: [100., 0., 0., 0.],
+            b: [150., 0., 0., 0.],
+        };
+        let fitted = fit_wdl_model(start, &samples, 1500, WDL_LEARNING_RATE, false);
+        for &material in [0.1, 0.5, 0.9].iter() {
+            for &score in [-200., 0., 150.].iter() {
+                let expected = truth.win_probability(score, material);
+                assert!((fitted.win_probability(score, material) - expected).abs() < 0.03);
+            }
+        }
+    }
+}
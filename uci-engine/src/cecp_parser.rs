@@ -0,0 +1,363 @@
+use super::uci_engine::UCIEngine;
+use super::uci_parser::isready;
+use core_sdk::board_representation::game_state::{GameMove, GameResult, GameState};
+use core_sdk::move_generation::makemove::make_move;
+use core_sdk::search::cache::Cache;
+use core_sdk::search::searcher::{search_move, InterThreadCommunicationSystem};
+use core_sdk::search::timecontrol::TimeControl;
+use core_sdk::search::MAX_SEARCH_DEPTH;
+use core_sdk::Protocol;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+//Clock set with the `level` or `st` command
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Level {
+    //Moves per time control (0 for the whole game), base time and increment in ms
+    Conventional(usize, u64, u64),
+    MoveTime(u64),
+}
+
+struct RunningSearch {
+    handle: JoinHandle<Option<GameMove>>,
+    //Cleared by whoever comes first: the search thread when it sends its move, or the front end when it discards the search
+    play_move: Arc<AtomicBool>,
+}
+
+pub struct CecpEngine {
+    pub state: GameState,
+    //Positions before the current one, for undo and repetition detection
+    pub history: Vec<GameState>,
+    pub force: bool,
+    pub analyze: bool,
+    pub post: bool,
+    pub max_depth: usize,
+    pub level: Level,
+    pub time_left: u64, //In ms
+    search: Option<RunningSearch>,
+}
+
+impl Default for CecpEngine {
+    fn default() -> Self {
+        CecpEngine {
+            state: GameState::standard(),
+            history: Vec::new(),
+            force: false,
+            analyze: false,
+            post: false,
+            max_depth: MAX_SEARCH_DEPTH,
+            level: Level::Conventional(0, 300_000, 0),
+            time_left: 300_000,
+            search: None,
+        }
+    }
+}
+
+impl CecpEngine {
+    pub fn new_game(&mut self) {
+        self.state = GameState::standard();
+        self.history.clear();
+        self.force = false;
+        self.max_depth = MAX_SEARCH_DEPTH;
+    }
+
+    pub fn find_move(&self, mv: &str) -> Option<GameMove> {
+        self.state.legal_moves().find(|legal| format!("{:?}", legal) == mv)
+    }
+
+    pub fn make_move(&mut self, mv: GameMove) {
+        let next = make_move(&self.state, mv);
+        self.history.push(std::mem::replace(&mut self.state, next));
+    }
+
+    pub fn undo(&mut self, plies: usize) {
+        for _ in 0..plies {
+            if let Some(state) = self.history.pop() {
+                self.state = state;
+            }
+        }
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        if self.analyze {
+            return TimeControl::Infinite;
+        }
+        match self.level {
+            Level::MoveTime(ms) => TimeControl::MoveTime(ms),
+            Level::Conventional(0, _, inc) => TimeControl::Incremental(self.time_left, inc),
+            Level::Conventional(mps, _, inc) => {
+                let moves_to_go = mps - self.state.get_full_moves().saturating_sub(1) % mps;
+                TimeControl::Tournament(self.time_left, inc, moves_to_go)
+            }
+        }
+    }
+
+    //Starts thinking on the current position, or claims the result if the game is already over
+    pub fn start_search(&mut self, itcs: &Arc<InterThreadCommunicationSystem>) {
+        if let Some(result) = result_string(&self.state, &self.history) {
+            if !self.analyze {
+                println!("{}", result);
+            }
+            return;
+        }
+        isready(itcs, false);
+        {
+            let mut uci_options = itcs.uci_options.write().unwrap();
+            uci_options.protocol = Protocol::Cecp;
+            uci_options.quiet = !self.post && !self.analyze;
+        }
+        let play_move = Arc::new(AtomicBool::new(!self.analyze));
+        let (itcs, state, history, tc, depth) = (Arc::clone(itcs), self.state.clone(), self.history.clone(), self.time_control(), self.max_depth);
+        let thread_play_move = Arc::clone(&play_move);
+        let handle = thread::Builder::new()
+            .stack_size(2 * 1024 * 1024)
+            .spawn(move || {
+                search_move(Arc::clone(&itcs), depth as i16, state.clone(), history.clone(), tc);
+                let mv = itcs.best_pv.lock().unwrap().pv.pv[0]?;
+                if !thread_play_move.swap(false, Ordering::SeqCst) {
+                    return None;
+                }
+                println!("move {:?}", mv);
+                let mut history = history;
+                let next = make_move(&state, mv);
+                history.push(state);
+                if let Some(result) = result_string(&next, &history) {
+                    println!("{}", result);
+                }
+                Some(mv)
+            })
+            .expect("Couldn't start thread");
+        self.search = Some(RunningSearch { handle, play_move });
+    }
+
+    //Waits for the running search. With `stop` it is interrupted, with `discard` its move is not played.
+    //A move that was already sent to the GUI is always played on the internal board.
+    pub fn finish_search(&mut self, itcs: &Arc<InterThreadCommunicationSystem>, stop: bool, discard: bool) {
+        if let Some(search) = self.search.take() {
+            if discard {
+                search.play_move.store(false, Ordering::SeqCst);
+            }
+            //The search resets the flag when it starts, so keep setting it until the search is gone
+            while stop && !search.handle.is_finished() {
+                *itcs.timeout_flag.write().unwrap() = true;
+                thread::sleep(Duration::from_millis(1));
+            }
+            match search.handle.join() {
+                Ok(Some(mv)) => self.make_move(mv),
+                Ok(None) => {}
+                Err(_) => println!("tellusererror The search failed in this position"),
+            }
+        }
+    }
+
+    //Restarts the analysis after the position changed
+    fn position_changed(&mut self, itcs: &Arc<InterThreadCommunicationSystem>) {
+        if self.analyze {
+            self.start_search(itcs);
+        }
+    }
+}
+
+pub fn result_string(state: &GameState, history: &[GameState]) -> Option<String> {
+    let reason = state.end_reason(history)?;
+    let result = match state.game_result(history) {
+        GameResult::WhiteWin => "1-0",
+        GameResult::BlackWin => "0-1",
+        _ => "1/2-1/2",
+    };
+    Some(format!("{} {{{}}}", result, reason))
+}
+
+//Parses the base time of the `level` command, either minutes or minutes:seconds, into ms
+pub fn parse_base_time(base: &str) -> Option<u64> {
+    let mut parts = base.splitn(2, ':');
+    let minutes = parts.next()?.parse::<u64>().ok()?;
+    let seconds = match parts.next() {
+        Some(seconds) => seconds.parse::<u64>().ok()?,
+        None => 0,
+    };
+    Some((minutes * 60 + seconds) * 1000)
+}
+
+pub fn level(cmd: &[&str]) -> Option<Level> {
+    if cmd.len() < 3 {
+        return None;
+    }
+    let moves_per_session = cmd[0].parse::<usize>().ok()?;
+    let base = parse_base_time(cmd[1])?;
+    let increment = (cmd[2].parse::<f64>().ok()? * 1000.) as u64;
+    Some(Level::Conventional(moves_per_session, base, increment))
+}
+
+pub fn features(engine: &UCIEngine) {
+    println!(
+        "feature myname=\"{}\" ping=1 setboard=1 usermove=1 playother=1 analyze=1 colors=0 sigint=0 sigterm=0 reuse=1 memory=1 smp=1 done=1",
+        engine.name
+    );
+}
+
+//XBoard front end. It is entered from the UCI loop when the first command is `xboard`, and drives the same search.
+pub fn parse_loop(engine: &UCIEngine, itcs: &Arc<InterThreadCommunicationSystem>) {
+    let mut cecp = CecpEngine::default();
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match stdin.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                println!("Error (invalid input): {}", e);
+                continue;
+            }
+            Err(_) => break,
+        }
+        let arg: Vec<&str> = line.split_whitespace().collect();
+        if arg.is_empty() {
+            continue;
+        }
+        match arg[0] {
+            "protover" => features(engine),
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer" | "name" | "rating" | "ics" | "otim" | "." => {}
+            "ping" => println!("pong {}", arg.get(1).unwrap_or(&"")),
+            "new" => {
+                cecp.finish_search(itcs, true, true);
+                cecp.new_game();
+                cecp.analyze = false;
+                itcs.cache().clear_threaded(itcs.get_current_uci_options().threads);
+            }
+            "force" => {
+                cecp.finish_search(itcs, true, !cecp.analyze);
+                cecp.force = true;
+            }
+            "go" => {
+                cecp.finish_search(itcs, false, false);
+                cecp.force = false;
+                cecp.start_search(itcs);
+            }
+            "playother" => {
+                cecp.finish_search(itcs, false, false);
+                cecp.force = false;
+            }
+            "?" if !cecp.analyze => cecp.finish_search(itcs, true, false),
+            "result" => cecp.finish_search(itcs, true, true),
+            "level" => match level(&arg[1..]) {
+                Some(level) => {
+                    if let Level::Conventional(_, base, _) = level {
+                        cecp.time_left = base;
+                    }
+                    cecp.level = level;
+                }
+                None => println!("Error (invalid level): {}", line.trim()),
+            },
+            "st" => match arg.get(1).and_then(|secs| secs.parse::<f64>().ok()) {
+                Some(secs) => cecp.level = Level::MoveTime((secs * 1000.) as u64),
+                None => println!("Error (invalid time): {}", line.trim()),
+            },
+            "sd" => match arg.get(1).and_then(|depth| depth.parse::<usize>().ok()) {
+                Some(depth) => cecp.max_depth = depth.clamp(1, MAX_SEARCH_DEPTH),
+                None => println!("Error (invalid depth): {}", line.trim()),
+            },
+            "time" => {
+                if let Some(centis) = arg.get(1).and_then(|centis| centis.parse::<u64>().ok()) {
+                    cecp.time_left = centis * 10;
+                }
+            }
+            "post" => cecp.post = true,
+            "nopost" => cecp.post = false,
+            "analyze" => {
+                cecp.finish_search(itcs, true, true);
+                cecp.analyze = true;
+                cecp.start_search(itcs);
+            }
+            "exit" => {
+                cecp.finish_search(itcs, true, true);
+                cecp.analyze = false;
+            }
+            "setboard" => {
+                cecp.finish_search(itcs, true, true);
+                match GameState::try_from_fen(&arg[1..].join(" ")) {
+                    Ok(state) => {
+                        cecp.state = state;
+                        cecp.history.clear();
+                        cecp.position_changed(itcs);
+                    }
+                    Err(e) => println!("tellusererror Illegal position: {}", e),
+                }
+            }
+            "undo" | "remove" => {
+                cecp.finish_search(itcs, true, true);
+                cecp.undo(if arg[0] == "undo" { 1 } else { 2 });
+                cecp.position_changed(itcs);
+            }
+            "memory" => {
+                if let Some(mb) = arg.get(1).and_then(|mb| mb.parse::<usize>().ok()) {
+                    cecp.finish_search(itcs, true, true);
+                    itcs.uci_options.write().unwrap().hash_size = mb;
+                    *itcs.cache() = Cache::with_size_threaded(mb, itcs.get_current_uci_options().threads);
+                }
+            }
+            "cores" => {
+                if let Some(threads) = arg.get(1).and_then(|threads| threads.parse::<usize>().ok()) {
+                    cecp.finish_search(itcs, true, true);
+                    InterThreadCommunicationSystem::update_thread_count(itcs, threads.max(1));
+                }
+            }
+            "quit" => {
+                cecp.finish_search(itcs, true, true);
+                break;
+            }
+            cmd => {
+                //Protocol version 1 sends moves without the usermove prefix
+                let mv = if cmd == "usermove" { arg.get(1).copied().unwrap_or("") } else { cmd };
+                cecp.finish_search(itcs, cecp.analyze, cecp.analyze);
+                match cecp.find_move(mv) {
+                    Some(mv) => {
+                        cecp.make_move(mv);
+                        if cecp.analyze || !cecp.force {
+                            cecp.start_search(itcs);
+                        }
+                    }
+                    None if cmd == "usermove" => println!("Illegal move: {}", mv),
+                    None => println!("Error (unknown command): {}", cmd),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cecp_level_and_moves() {
+        assert_eq!(level(&["40", "5", "0"]), Some(Level::Conventional(40, 300_000, 0)));
+        assert_eq!(level(&["0", "2:30", "1.5"]), Some(Level::Conventional(0, 150_000, 1500)));
+        assert_eq!(level(&["0", "x", "0"]), None);
+
+        let mut cecp = CecpEngine {
+            level: Level::Conventional(40, 300_000, 0),
+            time_left: 120_000,
+            ..Default::default()
+        };
+        for mv in ["f2f3", "e7e5", "g2g4"].iter() {
+            let mv = cecp.find_move(mv).unwrap();
+            cecp.make_move(mv);
+        }
+        assert!(cecp.find_move("e2e5").is_none());
+        match cecp.time_control() {
+            TimeControl::Tournament(120_000, 0, 39) => {}
+            _ => panic!("Wrong time control"),
+        }
+        let mate = cecp.find_move("d8h4").unwrap();
+        cecp.make_move(mate);
+        assert_eq!(result_string(&cecp.state, &cecp.history), Some("0-1 {Checkmate}".to_owned()));
+        cecp.undo(2);
+        assert_eq!(cecp.history.len(), 2);
+        assert_eq!(result_string(&cecp.state, &cecp.history), None);
+    }
+}
@@ -1,11 +1,51 @@
+pub mod cecp_parser;
+pub mod uci_command;
 pub mod uci_engine;
 pub mod uci_parser;
 
 fn main() {
     let mut args = std::env::args();
-    if args.nth(1) == Some("bench".to_owned()) {
+    let mode = args.nth(1);
+    if mode == Some("bench".to_owned()) {
         core_sdk::bench(args.nth(2).and_then(|depth| depth.parse::<usize>().ok()).unwrap_or(13));
+    } else if mode == Some("bench-stats".to_owned()) {
+        bench_statistics(args.next().and_then(|depth| depth.parse::<usize>().ok()).unwrap_or(13));
+    } else if mode == Some("tree".to_owned()) {
+        dump_tree(args.collect());
     } else {
         uci_parser::parse_loop();
     }
 }
+
+//tree DEPTH PLIES OUTPUT [FEN]: writes the search tree of the position to OUTPUT, as Graphviz if it ends with .dot and as JSON otherwise
+#[cfg(feature = "search-tree")]
+fn dump_tree(args: Vec<String>) {
+    if args.len() < 3 {
+        println!("Usage: tree DEPTH PLIES OUTPUT [FEN]");
+        return;
+    }
+    let depth = args[0].parse::<i16>().expect("Invalid depth");
+    let plies = args[1].parse::<usize>().expect("Invalid number of plies");
+    let game_state = if args.len() > 3 {
+        core_sdk::board_representation::game_state::GameState::from_fen(&args[3..].join(" "))
+    } else {
+        core_sdk::board_representation::game_state::GameState::standard()
+    };
+    core_sdk::search::tree::dump_search_tree(game_state, depth, plies, &args[2]).expect("Could not write search tree");
+}
+
+#[cfg(not(feature = "search-tree"))]
+fn dump_tree(_args: Vec<String>) {
+    println!("Search tree logging is not compiled in, build with --features search-tree");
+}
+
+//bench-stats [DEPTH]: prints the search statistics averaged over the bench positions as JSON
+#[cfg(feature = "search-statistics")]
+fn bench_statistics(depth: usize) {
+    println!("{}", core_sdk::bench_statistics(depth).to_json());
+}
+
+#[cfg(not(feature = "search-statistics"))]
+fn bench_statistics(_depth: usize) {
+    println!("Search statistics are not compiled in, build with --features search-statistics");
+}
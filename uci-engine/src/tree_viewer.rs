@@ -0,0 +1,117 @@
+use core_sdk::search::tree::{load_search_tree, TreeNode};
+use std::env;
+use std::str::FromStr;
+
+const USAGE: &str = "Usage: tree-viewer TREE_FILE [root INDEX] [pv | path MOVE...] [depth PLIES] [dot OUTPUT_FILE]";
+
+//The value following the flag at `index`, or None after printing the usage if it is missing or invalid
+fn flag_value<T: FromStr>(args: &[String], index: usize) -> Option<T> {
+    let value = args.get(index + 1).and_then(|value| value.parse::<T>().ok());
+    if value.is_none() {
+        println!("Missing or invalid value for {}\n{}", args[index], USAGE);
+    }
+    value
+}
+
+fn print_node(node: &TreeNode, indent: usize, depth: usize) {
+    println!(
+        "{}{} d {} [{}, {}] score {}{}{}",
+        "  ".repeat(indent),
+        node.mv.as_deref().unwrap_or("root"),
+        node.depth,
+        node.alpha,
+        node.beta,
+        node.score,
+        if node.quiescence { " q" } else { "" },
+        node.excluded.as_ref().map_or(String::new(), |excluded| format!(" excluding {}", excluded))
+    );
+    for event in node.events.iter() {
+        println!("{}  | {:?}", "  ".repeat(indent), event);
+    }
+    if depth > 0 {
+        for child in node.children.iter() {
+            print_node(child, indent + 1, depth - 1);
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        println!("{}", USAGE);
+        return;
+    }
+    let roots = load_search_tree(&args[0]).expect("Could not load search tree");
+    if roots.is_empty() {
+        println!("The search tree is empty");
+        return;
+    }
+    let mut root_index = roots.len() - 1;
+    let mut path: Vec<String> = Vec::new();
+    let mut follow_pv = false;
+    let mut depth = 2;
+    let mut dot_path = None;
+    let mut index = 1;
+    while index < args.len() {
+        match &args[index][..] {
+            "root" => {
+                root_index = match flag_value::<usize>(&args, index) {
+                    Some(root_index) => root_index.min(roots.len() - 1),
+                    None => return,
+                };
+                index += 2;
+            }
+            "depth" => {
+                depth = match flag_value(&args, index) {
+                    Some(depth) => depth,
+                    None => return,
+                };
+                index += 2;
+            }
+            "dot" => {
+                dot_path = match flag_value::<String>(&args, index) {
+                    Some(dot_path) => Some(dot_path),
+                    None => return,
+                };
+                index += 2;
+            }
+            "pv" => {
+                follow_pv = true;
+                index += 1;
+            }
+            "path" => {
+                index += 1;
+                while index < args.len() && !["root", "depth", "dot", "pv"].contains(&&args[index][..]) {
+                    path.push(args[index].clone());
+                    index += 1;
+                }
+            }
+            _ => {
+                println!("Invalid argument {}\n{}", args[index], USAGE);
+                return;
+            }
+        }
+    }
+    let root = &roots[root_index];
+    if follow_pv {
+        path = root.pv();
+    }
+    println!("Search {} of {}, depth {}, path {}", root_index, roots.len(), root.depth, path.join(" "));
+
+    //Nodes along the path, then the subtree at its end
+    let mut node = root;
+    for (ply, mv) in path.iter().enumerate() {
+        print_node(node, ply, 0);
+        node = match node.child(mv) {
+            Some(child) => child,
+            None => {
+                println!("Move {} was not recorded", mv);
+                return;
+            }
+        };
+    }
+    print_node(node, path.len(), depth);
+    if let Some(dot_path) = dot_path {
+        std::fs::write(&dot_path, node.truncated(depth).to_dot()).expect("Could not write dot file");
+    }
+}
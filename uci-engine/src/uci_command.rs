@@ -0,0 +1,510 @@
+use core_sdk::board_representation::game_state::{GameState, WHITE};
+use core_sdk::move_generation::makemove::make_move;
+use core_sdk::move_generation::perft::DEFAULT_PERFT_HASH_SIZE;
+use core_sdk::search::cache::{MAX_HASH_SIZE, MIN_HASH_SIZE};
+use core_sdk::search::parameters::{find_search_parameter, SEARCH_PARAMETERS};
+use core_sdk::search::searcher::{MAX_CURRMOVE_DELAY, MAX_SKIP_RATIO, MAX_THREADS, MIN_CURRMOVE_DELAY, MIN_SKIP_RATIO, MIN_THREADS};
+use core_sdk::search::timecontrol::{TimeControl, MAX_MOVE_OVERHEAD, MIN_MOVE_OVERHEAD};
+use core_sdk::search::{MAX_CONTEMPT, MAX_SEARCH_DEPTH, MIN_CONTEMPT};
+use std::fmt::{Display, Formatter, Result};
+use std::iter::Peekable;
+use std::str::{FromStr, SplitWhitespace};
+
+//Everything that can go wrong while parsing a command. Errors are reported to the GUI as `info string`.
+#[derive(Debug, PartialEq)]
+pub enum UciError {
+    UnknownCommand(String),
+    UnknownOption(String),
+    UnexpectedToken(String),
+    MissingValue(String),
+    InvalidValue { name: String, value: String },
+    OutOfRange { name: String, value: String, min: String, max: String },
+    InvalidFen(String),
+    IllegalMove(String),
+}
+
+impl Display for UciError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        match self {
+            UciError::UnknownCommand(cmd) => write!(formatter, "Unknown command {}", cmd),
+            UciError::UnknownOption(name) => write!(formatter, "Unknown option {}", name),
+            UciError::UnexpectedToken(token) => write!(formatter, "Unexpected token {}", token),
+            UciError::MissingValue(name) => write!(formatter, "Missing value for {}", name),
+            UciError::InvalidValue { name, value } => write!(formatter, "Invalid value {} for {}", value, name),
+            UciError::OutOfRange { name, value, min, max } => write!(formatter, "Value {} for {} is not in [{}, {}]", value, name, min, max),
+            UciError::InvalidFen(reason) => write!(formatter, "Invalid FEN: {}", reason),
+            UciError::IllegalMove(mv) => write!(formatter, "Illegal move {}", mv),
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(name: &str, value: Option<&str>) -> std::result::Result<T, UciError> {
+    let value = value.ok_or_else(|| UciError::MissingValue(name.to_owned()))?;
+    value.parse::<T>().map_err(|_| UciError::InvalidValue {
+        name: name.to_owned(),
+        value: value.to_owned(),
+    })
+}
+
+fn parse_ranged<T: FromStr + PartialOrd + Display>(name: &str, value: Option<&str>, min: T, max: T) -> std::result::Result<T, UciError> {
+    let parsed = parse_value::<T>(name, value)?;
+    if parsed < min || parsed > max {
+        return Err(UciError::OutOfRange {
+            name: name.to_owned(),
+            value: parsed.to_string(),
+            min: min.to_string(),
+            max: max.to_string(),
+        });
+    }
+    Ok(parsed)
+}
+
+pub struct Tokenizer<'a> {
+    tokens: Peekable<SplitWhitespace<'a>>,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(line: &'a str) -> Self {
+        Tokenizer {
+            tokens: line.split_whitespace().peekable(),
+        }
+    }
+
+    pub fn next_token(&mut self) -> Option<&'a str> {
+        self.tokens.next()
+    }
+
+    pub fn peek(&mut self) -> Option<&'a str> {
+        self.tokens.peek().copied()
+    }
+
+    //Consumes the token following a keyword as its value
+    pub fn value<T: FromStr>(&mut self, name: &str) -> std::result::Result<T, UciError> {
+        parse_value(name, self.next_token())
+    }
+
+    pub fn ranged<T: FromStr + PartialOrd + Display>(&mut self, name: &str, min: T, max: T) -> std::result::Result<T, UciError> {
+        parse_ranged(name, self.next_token(), min, max)
+    }
+
+    pub fn positive<T: FromStr + PartialOrd + Default + Display>(&mut self, name: &str) -> std::result::Result<T, UciError> {
+        let parsed = self.value::<T>(name)?;
+        if parsed <= T::default() {
+            return Err(UciError::InvalidValue {
+                name: name.to_owned(),
+                value: parsed.to_string(),
+            });
+        }
+        Ok(parsed)
+    }
+
+    //Some GUIs report an overstepped clock as negative time left
+    pub fn time(&mut self, name: &str) -> std::result::Result<u64, UciError> {
+        Ok(self.value::<i64>(name)?.max(0) as u64)
+    }
+
+    //All tokens up to, but excluding, the given keyword
+    pub fn until(&mut self, keyword: &str) -> Vec<&'a str> {
+        let mut res = Vec::new();
+        while let Some(token) = self.peek() {
+            if token.eq_ignore_ascii_case(keyword) {
+                break;
+            }
+            res.push(token);
+            self.tokens.next();
+        }
+        res
+    }
+
+    pub fn rest(&mut self) -> Vec<&'a str> {
+        self.tokens.by_ref().collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineOption {
+    Hash(usize),
+    ClearHash,
+    HashFile(String),
+    SaveHash,
+    LoadHash,
+    Threads(usize),
+    MoveOverhead(u64),
+    DebugSMPPrint(bool),
+    ShowWDL(bool),
+    CurrMoveDelay(u64),
+    SMPSkipRatio(usize),
+    //Index into SEARCH_PARAMETERS and the new value
+    SearchParameter(usize, i16),
+    Contempt(i16),
+    AnalysisContempt(bool),
+    LmrA(f32),
+    LmrB(f32),
+    LmrC(f32),
+    LmrD(f32),
+}
+
+impl EngineOption {
+    pub fn parse(name: &str, value: Option<&str>) -> std::result::Result<EngineOption, UciError> {
+        let lmr = |value: Option<&str>| -> std::result::Result<f32, UciError> {
+            let parsed = parse_value::<f32>(name, value)?;
+            if !parsed.is_finite() {
+                return Err(UciError::InvalidValue {
+                    name: name.to_owned(),
+                    value: parsed.to_string(),
+                });
+            }
+            Ok(parsed)
+        };
+        Ok(match name.to_lowercase().as_str() {
+            "hash" => EngineOption::Hash(parse_ranged(name, value, MIN_HASH_SIZE, MAX_HASH_SIZE)?),
+            "clearhash" => EngineOption::ClearHash,
+            "hashfile" => EngineOption::HashFile(value.ok_or_else(|| UciError::MissingValue(name.to_owned()))?.to_owned()),
+            "savehash" => EngineOption::SaveHash,
+            "loadhash" => EngineOption::LoadHash,
+            "threads" => EngineOption::Threads(parse_ranged(name, value, MIN_THREADS, MAX_THREADS)?),
+            "moveoverhead" => EngineOption::MoveOverhead(parse_ranged(name, value, MIN_MOVE_OVERHEAD, MAX_MOVE_OVERHEAD)?),
+            "debugsmpprint" => EngineOption::DebugSMPPrint(parse_value(name, value)?),
+            "uci_showwdl" => EngineOption::ShowWDL(parse_value(name, value)?),
+            "currmovedelay" => EngineOption::CurrMoveDelay(parse_ranged(name, value, MIN_CURRMOVE_DELAY, MAX_CURRMOVE_DELAY)?),
+            "smpskipratio" => EngineOption::SMPSkipRatio(parse_ranged(name, value, MIN_SKIP_RATIO, MAX_SKIP_RATIO)?),
+            "contempt" => EngineOption::Contempt(parse_ranged(name, value, MIN_CONTEMPT, MAX_CONTEMPT)?),
+            "analysiscontempt" => EngineOption::AnalysisContempt(parse_value(name, value)?),
+            "lmr_a" => EngineOption::LmrA(lmr(value)?),
+            "lmr_b" => EngineOption::LmrB(lmr(value)?),
+            "lmr_c" => EngineOption::LmrC(lmr(value)?),
+            "lmr_d" => EngineOption::LmrD(lmr(value)?),
+            _ => match find_search_parameter(name) {
+                Some(index) => {
+                    let parameter = &SEARCH_PARAMETERS[index];
+                    EngineOption::SearchParameter(index, parse_ranged(name, value, parameter.min, parameter.max)?)
+                }
+                None => return Err(UciError::UnknownOption(name.to_owned())),
+            },
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GoParameters {
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<usize>,
+    pub movetime: Option<u64>,
+    pub nodes: Option<u64>,
+    pub depth: Option<usize>,
+    pub infinite: bool,
+}
+
+impl GoParameters {
+    pub fn parse(tokens: &mut Tokenizer) -> std::result::Result<GoParameters, UciError> {
+        let mut res = GoParameters::default();
+        while let Some(token) = tokens.next_token() {
+            match token {
+                "wtime" => res.wtime = Some(tokens.time(token)?),
+                "btime" => res.btime = Some(tokens.time(token)?),
+                "winc" => res.winc = Some(tokens.time(token)?),
+                "binc" => res.binc = Some(tokens.time(token)?),
+                "movestogo" => res.movestogo = Some(tokens.positive(token)?),
+                "movetime" => res.movetime = Some(tokens.value(token)?),
+                "nodes" => res.nodes = Some(tokens.positive(token)?),
+                "depth" => res.depth = Some(tokens.ranged(token, 1, MAX_SEARCH_DEPTH)?),
+                "infinite" => res.infinite = true,
+                //Pondering is never advertised, the search just runs on the clock it is given
+                "ponder" => {}
+                _ => return Err(UciError::UnexpectedToken(token.to_owned())),
+            }
+        }
+        Ok(res)
+    }
+
+    //A go without any limits searches until it is stopped
+    pub fn time_control(&self, color_to_move: usize) -> TimeControl {
+        let (time, inc) = if color_to_move == WHITE { (self.wtime, self.winc) } else { (self.btime, self.binc) };
+        if self.infinite {
+            TimeControl::Infinite
+        } else if let Some(movetime) = self.movetime {
+            TimeControl::MoveTime(movetime)
+        } else if let Some(nodes) = self.nodes {
+            TimeControl::Nodes(nodes)
+        } else if time.is_none() && inc.is_none() {
+            TimeControl::Infinite
+        } else if let Some(movestogo) = self.movestogo {
+            TimeControl::Tournament(time.unwrap_or(0), inc.unwrap_or(0), movestogo)
+        } else {
+            TimeControl::Incremental(time.unwrap_or(0), inc.unwrap_or(0))
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth.unwrap_or(MAX_SEARCH_DEPTH)
+    }
+}
+
+pub enum UciCommand {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption(EngineOption),
+    UciNewGame,
+    //The position to search and the positions played before it
+    Position(GameState, Vec<GameState>),
+    Go(GoParameters),
+    Stop,
+    Quit,
+    Display,
+    Perft { depth: usize, threads: usize, hash_size: usize },
+    Eval,
+    //Search statistics of the last finished search
+    Stats,
+    Xboard,
+}
+
+impl UciCommand {
+    //Parses one line sent by the GUI. Arguments of commands which take none are ignored.
+    pub fn parse(line: &str) -> std::result::Result<UciCommand, UciError> {
+        let mut tokens = Tokenizer::new(line);
+        let cmd = match tokens.next_token() {
+            Some(cmd) => cmd,
+            None => return Err(UciError::UnknownCommand(String::new())),
+        };
+        Ok(match cmd {
+            "uci" => UciCommand::Uci,
+            "debug" => match tokens.next_token() {
+                Some("on") => UciCommand::Debug(true),
+                Some("off") => UciCommand::Debug(false),
+                Some(token) => return Err(UciError::UnexpectedToken(token.to_owned())),
+                None => return Err(UciError::MissingValue(cmd.to_owned())),
+            },
+            "isready" => UciCommand::IsReady,
+            "setoption" => UciCommand::parse_setoption(&mut tokens)?,
+            "ucinewgame" | "newgame" => UciCommand::UciNewGame,
+            "position" => UciCommand::parse_position(&mut tokens)?,
+            "go" => UciCommand::Go(GoParameters::parse(&mut tokens)?),
+            "stop" => UciCommand::Stop,
+            "quit" => UciCommand::Quit,
+            "d" => UciCommand::Display,
+            "perft" => UciCommand::parse_perft(&mut tokens)?,
+            "static" | "eval" => UciCommand::Eval,
+            "stats" => UciCommand::Stats,
+            "xboard" => UciCommand::Xboard,
+            _ => return Err(UciError::UnknownCommand(cmd.to_owned())),
+        })
+    }
+
+    //setoption name <name> [value <value>]
+    fn parse_setoption(tokens: &mut Tokenizer) -> std::result::Result<UciCommand, UciError> {
+        match tokens.next_token() {
+            Some(token) if token.eq_ignore_ascii_case("name") => {}
+            Some(token) => return Err(UciError::UnexpectedToken(token.to_owned())),
+            None => return Err(UciError::MissingValue("name".to_owned())),
+        }
+        let name = tokens.until("value").join(" ");
+        if name.is_empty() {
+            return Err(UciError::MissingValue("name".to_owned()));
+        }
+        let value = if tokens.next_token().is_some() { Some(tokens.rest().join(" ")) } else { None };
+        Ok(UciCommand::SetOption(EngineOption::parse(&name, value.as_deref())?))
+    }
+
+    //position (startpos | fen <fen>) [moves <moves>]
+    fn parse_position(tokens: &mut Tokenizer) -> std::result::Result<UciCommand, UciError> {
+        let mut state = match tokens.next_token() {
+            Some("startpos") => GameState::standard(),
+            Some("fen") => GameState::try_from_fen(&tokens.until("moves").join(" ")).map_err(UciError::InvalidFen)?,
+            Some(token) => return Err(UciError::UnexpectedToken(token.to_owned())),
+            None => return Err(UciError::MissingValue("position".to_owned())),
+        };
+        let mut history = Vec::new();
+        match tokens.next_token() {
+            Some(token) if token.eq_ignore_ascii_case("moves") => {}
+            Some(token) => return Err(UciError::UnexpectedToken(token.to_owned())),
+            None => return Ok(UciCommand::Position(state, history)),
+        }
+        while let Some(token) = tokens.next_token() {
+            let uci_move = token.to_ascii_lowercase();
+            let mv = state
+                .legal_moves()
+                .find(|legal| format!("{:?}", legal) == uci_move)
+                .ok_or_else(|| UciError::IllegalMove(token.to_owned()))?;
+            let next = make_move(&state, mv);
+            history.push(std::mem::replace(&mut state, next));
+        }
+        Ok(UciCommand::Position(state, history))
+    }
+
+    //perft <depth> [threads <threads>] [hash <mb>]
+    fn parse_perft(tokens: &mut Tokenizer) -> std::result::Result<UciCommand, UciError> {
+        let depth = tokens.ranged("depth", 1, MAX_SEARCH_DEPTH)?;
+        let mut threads = 1;
+        let mut hash_size = DEFAULT_PERFT_HASH_SIZE;
+        while let Some(token) = tokens.next_token() {
+            match token {
+                "threads" => threads = tokens.ranged(token, MIN_THREADS, MAX_THREADS)?,
+                "hash" => hash_size = tokens.ranged(token, MIN_HASH_SIZE, MAX_HASH_SIZE)?,
+                _ => return Err(UciError::UnexpectedToken(token.to_owned())),
+            }
+        }
+        Ok(UciCommand::Perft { depth, threads, hash_size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const FEN: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+
+    #[test]
+    fn uci_command_errors() {
+        assert!(UciCommand::parse("go movestogo 0 wtime 1000 btime 1000").is_err());
+        assert!(UciCommand::parse("go depth").is_err());
+        assert!(UciCommand::parse("position").is_err());
+        assert!(UciCommand::parse("position startfen").is_err());
+        assert!(UciCommand::parse("position startpos moves e2e5").is_err());
+        assert!(UciCommand::parse("position fen 8/8/8/8/8/8/8/8 w - - 0 1").is_err());
+        assert!(UciCommand::parse("position fen 4k3/8/8/8/8/8/4R3/4K3 w - - 0 1").is_err());
+        assert!(UciCommand::parse("position fen 4k3/8/8/8/8/8/3R4/4K3 w - - 0 0").is_err());
+        assert!(UciCommand::parse("position fen 4k3/8/8/8/8/8/3R4/4K3 w - - 0 1").is_ok());
+        assert!(UciCommand::parse("setoption name Hash value lots").is_err());
+        assert!(UciCommand::parse("setoption name Hash value 99999999999").is_err());
+        assert!(UciCommand::parse("setoption name NoSuchOption value 1").is_err());
+        assert!(UciCommand::parse("setoption name Contempt value 101").is_err());
+        assert!(UciCommand::parse("setoption name ProbCutReduction value 0").is_err());
+        assert!(matches!(
+            UciCommand::parse("setoption name futilitymargin value 100"),
+            Ok(UciCommand::SetOption(EngineOption::SearchParameter(0, 100)))
+        ));
+        assert!(matches!(UciCommand::parse("stats"), Ok(UciCommand::Stats)));
+        assert!(matches!(
+            UciCommand::parse("setoption name contempt value -20"),
+            Ok(UciCommand::SetOption(EngineOption::Contempt(-20)))
+        ));
+        assert_eq!(
+            UciCommand::parse("perft 0").err(),
+            Some(UciError::OutOfRange {
+                name: "depth".to_owned(),
+                value: "0".to_owned(),
+                min: "1".to_owned(),
+                max: MAX_SEARCH_DEPTH.to_string()
+            })
+        );
+
+        match UciCommand::parse("go") {
+            Ok(UciCommand::Go(go)) => assert!(matches!(go.time_control(WHITE), TimeControl::Infinite)),
+            _ => panic!("go without arguments should search infinitely"),
+        }
+        match UciCommand::parse("go wtime -20 btime 5000 winc 0 binc 100 movestogo 3 depth 7") {
+            Ok(UciCommand::Go(go)) => {
+                assert!(matches!(go.time_control(WHITE), TimeControl::Tournament(0, 0, 3)));
+                assert_eq!(go.depth(), 7);
+            }
+            _ => panic!("Valid go command not parsed"),
+        }
+        match UciCommand::parse(&format!("position fen {} moves h5f7", FEN)) {
+            Ok(UciCommand::Position(state, history)) => {
+                assert_eq!(history.len(), 1);
+                assert!(state.is_checkmate());
+            }
+            _ => panic!("Valid position command not parsed"),
+        }
+        match UciCommand::parse("setoption name HashFile value my hash.bin") {
+            Ok(UciCommand::SetOption(option)) => assert_eq!(option, EngineOption::HashFile("my hash.bin".to_owned())),
+            _ => panic!("Valid setoption command not parsed"),
+        }
+    }
+
+    //Random command lines must never bring the engine down
+    #[test]
+    fn uci_command_fuzz() {
+        let words = [
+            "uci",
+            "debug",
+            "on",
+            "off",
+            "isready",
+            "setoption",
+            "name",
+            "value",
+            "Hash",
+            "Threads",
+            "MoveOverhead",
+            "UCI_ShowWDL",
+            "FutilityMargin",
+            "Contempt",
+            "lmr_a",
+            "HashFile",
+            "ucinewgame",
+            "position",
+            "startpos",
+            "fen",
+            "moves",
+            "go",
+            "wtime",
+            "btime",
+            "winc",
+            "binc",
+            "movestogo",
+            "movetime",
+            "nodes",
+            "depth",
+            "infinite",
+            "stop",
+            "perft",
+            "threads",
+            "hash",
+            "e2e4",
+            "e7e5",
+            "g1f3",
+            "e7e8q",
+            "a1a1",
+            "e2",
+            "true",
+            "false",
+            "0",
+            "-1",
+            "1",
+            "18446744073709551616",
+            "NaN",
+            "inf",
+            "w",
+            "b",
+            "-",
+            "KQkq",
+            "8/8/8/8/8/8/8/8",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+            "",
+        ];
+        let mut rng = StdRng::seed_from_u64(0x0c1);
+        for _ in 0..20_000 {
+            let len = rng.gen_range(0, 12);
+            let line = (0..len).map(|_| words[rng.gen_range(0, words.len())]).collect::<Vec<&str>>().join(" ");
+            let _ = UciCommand::parse(&line);
+        }
+        //Mutated FENs and move lists
+        let alphabet: Vec<char> = "pnbrqkPNBRQK12345678/ wb-KQkqabcdefgh9x".chars().collect();
+        for _ in 0..20_000 {
+            let mut line: Vec<char> = format!("position fen {} moves h5f7", FEN).chars().collect();
+            for _ in 0..rng.gen_range(1, 4) {
+                let index = rng.gen_range(13, line.len());
+                match rng.gen_range(0, 3) {
+                    0 => line[index] = alphabet[rng.gen_range(0, alphabet.len())],
+                    1 => line.insert(index, alphabet[rng.gen_range(0, alphabet.len())]),
+                    _ => {
+                        line.remove(index);
+                    }
+                }
+            }
+            let line: String = line.into_iter().collect();
+            if let Ok(UciCommand::Position(state, _)) = UciCommand::parse(&line) {
+                //Whatever got through has to be usable by the engine
+                let _ = core_sdk::evaluation::eval_game_state(&state);
+                let _ = state.legal_moves().count();
+            }
+        }
+    }
+}
@@ -1,10 +1,14 @@
 use core_sdk::board_representation::game_state::GameState;
 
+pub const DEFAULT_HASH_FILE: &str = "fabchess.hash";
+
 pub struct UCIEngine<'a> {
     pub name: &'a str,
     pub author: &'a str,
     pub contributors: &'a [&'a str],
     pub internal_state: GameState,
+    //Target of the SaveHash and LoadHash buttons
+    pub hash_file: String,
 }
 
 impl<'a> UCIEngine<'a> {
@@ -23,6 +27,7 @@ impl<'a> UCIEngine<'a> {
             author: &"Fabian von der Warth",
             contributors: &["Erik Imgrund", "Marcin Mielniczuk", "Terje Kirstihagen"],
             internal_state: GameState::standard(),
+            hash_file: DEFAULT_HASH_FILE.to_owned(),
         }
     }
 
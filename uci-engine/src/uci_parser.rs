@@ -1,18 +1,18 @@
+use super::cecp_parser;
+use super::uci_command::{EngineOption, UciCommand};
 use super::uci_engine::UCIEngine;
-use core_sdk::board_representation::game_state::{GameMove, GameMoveType, GameState, PieceType};
-use core_sdk::move_generation::makemove::make_move;
-use core_sdk::move_generation::movegen;
-use core_sdk::search::alphabeta::{MAX_FUTILITY_MARGIN, MIN_FUTILITY_MARGIN};
+use core_sdk::board_representation::game_state::GameState;
+use core_sdk::move_generation::perft::{perft_divide, PerftTable};
 use core_sdk::search::cache::{Cache, MAX_HASH_SIZE, MIN_HASH_SIZE};
-use core_sdk::search::searcher::{search_move, InterThreadCommunicationSystem, MAX_SKIP_RATIO, MAX_THREADS, MIN_SKIP_RATIO, MIN_THREADS};
-use core_sdk::search::timecontrol::{TimeControl, MAX_MOVE_OVERHEAD, MIN_MOVE_OVERHEAD};
-use core_sdk::search::MAX_SEARCH_DEPTH;
+use core_sdk::search::parameters::SEARCH_PARAMETERS;
+use core_sdk::search::searcher::{search_move, InterThreadCommunicationSystem, MAX_CURRMOVE_DELAY, MAX_SKIP_RATIO, MAX_THREADS, MIN_CURRMOVE_DELAY, MIN_SKIP_RATIO, MIN_THREADS};
+use core_sdk::search::timecontrol::{MAX_MOVE_OVERHEAD, MIN_MOVE_OVERHEAD};
+use core_sdk::search::{MAX_CONTEMPT, MIN_CONTEMPT};
 use core_sdk::UCIOptions;
 use std::io;
-use std::sync::{atomic::Ordering, Arc};
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
-use std::u64;
+use std::time::{Duration, Instant};
 
 pub fn parse_loop() {
     let mut history: Vec<GameState> = vec![];
@@ -21,46 +21,56 @@ pub fn parse_loop() {
 
     let itcs = Arc::new(InterThreadCommunicationSystem::default());
     *itcs.cache() = Cache::with_size_threaded(itcs.get_current_uci_options().hash_size, itcs.get_current_uci_options().threads);
-    let mut movelist = movegen::MoveList::default();
 
     let stdin = io::stdin();
     let mut line = String::new();
     loop {
         line.clear();
-        stdin.read_line(&mut line).unwrap();
-        if line.is_empty() {
-            break;
+        match stdin.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                println!("info string {}", e);
+                continue;
+            }
+            Err(_) => break,
         }
-        let arg: Vec<&str> = line.split_whitespace().collect();
-        if arg.is_empty() {
+        if line.trim().is_empty() {
             continue;
         }
-        let cmd = arg[0];
-        match cmd.trim() {
-            "" => {
+        let command = match UciCommand::parse(&line) {
+            Ok(command) => command,
+            Err(e) => {
+                println!("info string {}", e);
                 continue;
             }
-            "uci" => {
+        };
+        match command {
+            UciCommand::Uci => {
                 uci(&us);
             }
-            "setoption" => setoption(&arg[1..], &itcs),
-
-            "ucinewgame" | "newgame" => {
+            UciCommand::Xboard => {
+                cecp_parser::parse_loop(&us, &itcs);
+                break;
+            }
+            UciCommand::SetOption(option) => setoption(&mut us, option, &itcs),
+            UciCommand::UciNewGame => {
                 newgame(&mut us);
                 itcs.cache().clear_threaded(itcs.get_current_uci_options().threads);
-                itcs.saved_time.store(0, Ordering::Relaxed);
             }
-            "isready" => isready(&itcs, true),
-            "position" => {
-                history = position(&mut us, &arg[1..], &mut movelist);
+            UciCommand::IsReady => isready(&itcs, true),
+            UciCommand::Debug(on) => {
+                itcs.uci_options.write().unwrap().uci_debug = on;
             }
-            "go" => {
+            UciCommand::Position(state, new_history) => {
+                us.internal_state = state;
+                history = new_history;
+            }
+            UciCommand::Go(go) => {
                 isready(&itcs, false);
-                let (tc, depth) = go(&us, &arg[1..]);
-                let mut new_history = vec![];
-                for gs in &history {
-                    new_history.push(gs.clone());
-                }
+                let tc = go.time_control(us.internal_state.get_color_to_move());
+                let depth = go.depth();
+                let new_history = history.clone();
                 let new_state = us.internal_state.clone();
                 let itcs = Arc::clone(&itcs);
                 thread::Builder::new()
@@ -70,150 +80,52 @@ pub fn parse_loop() {
                     })
                     .expect("Couldn't start thread");
             }
-            "stop" => {
+            UciCommand::Stop => {
                 *itcs.timeout_flag.write().unwrap() = true;
                 thread::sleep(Duration::from_millis(5));
             }
-            "quit" => {
+            UciCommand::Quit => {
                 break;
             }
-            "d" => {
+            UciCommand::Display => {
                 print_internal_state(&us);
             }
-            "perft" => perft(&us.internal_state, &arg[1..]),
-            "static" | "eval" => {
+            UciCommand::Perft { depth, threads, hash_size } => perft(&us.internal_state, depth, threads, hash_size),
+            UciCommand::Eval => {
                 println!("{}", core_sdk::evaluation::eval_game_state(&us.internal_state).final_eval);
             }
-            _ => {
-                println!("Unknown command {}", line);
-            }
+            UciCommand::Stats => print_statistics(&itcs),
         }
     }
 }
 
-pub fn perft(game_state: &GameState, cmd: &[&str]) {
-    let depth = cmd[0].parse::<usize>().unwrap();
-    core_sdk::perft_div(&game_state, depth);
+pub fn perft(game_state: &GameState, depth: usize, threads: usize, hash_size: usize) {
+    let now = Instant::now();
+    let table = Arc::new(PerftTable::with_size(hash_size));
+    let divide = perft_divide(game_state, depth, threads, &table);
+    let mut count = 0;
+    for (mv, nodes) in divide {
+        println!("{:?}: {}", mv, nodes);
+        count += nodes;
+    }
+    let secs = now.elapsed().as_secs_f64();
+    println!("{}", count);
+    println!("Time {:.3} ({:.0} nps)", secs, count as f64 / secs);
 }
 
 pub fn print_internal_state(engine: &UCIEngine) {
     println!("{}", engine.internal_state);
 }
 
-pub fn go(engine: &UCIEngine, cmd: &[&str]) -> (TimeControl, usize) {
-    let mut wtime: u64 = 0;
-    let mut btime: u64 = 0;
-    let mut winc: u64 = 0;
-    let mut binc: u64 = 0;
-    let mut depth = MAX_SEARCH_DEPTH;
-    if cmd[0].to_lowercase() == "infinite" {
-        return (TimeControl::Infinite, depth);
-    } else if cmd[0].to_lowercase() == "depth" {
-        depth = cmd[1].parse::<usize>().unwrap();
-        return (TimeControl::Infinite, depth);
-    }
-    let mut index = 0;
-    let mut movestogo: Option<usize> = None;
-    while index < cmd.len() {
-        match cmd[index] {
-            "wtime" => {
-                wtime = cmd[index + 1].parse::<u64>().unwrap_or(0);
-            }
-            "btime" => {
-                btime = cmd[index + 1].parse::<u64>().unwrap_or(0);
-            }
-            "winc" => {
-                winc = cmd[index + 1].parse::<u64>().unwrap_or(0);
-            }
-            "binc" => {
-                binc = cmd[index + 1].parse::<u64>().unwrap_or(0);
-            }
-            "movetime" => {
-                let mvtime = cmd[index + 1].parse::<u64>().unwrap_or(0);
-                return (TimeControl::MoveTime(mvtime), depth);
-            }
-            "movestogo" => movestogo = Some(cmd[index + 1].parse::<usize>().unwrap_or(1)),
-            _ => println!("Some parts of the go command weren't recognized well."),
-        };
-        index += 2;
-    }
-    if movestogo.is_none() {
-        if engine.internal_state.get_color_to_move() == 0 {
-            (TimeControl::Incremental(wtime, winc), depth)
-        } else {
-            (TimeControl::Incremental(btime, binc), depth)
-        }
-    } else if let Some(mvs) = movestogo {
-        if mvs == 0 {
-            panic!("movestogo = 0");
-        }
-        if engine.internal_state.get_color_to_move() == 0 {
-            (TimeControl::Tournament(wtime, winc, mvs), depth)
-        } else {
-            (TimeControl::Tournament(btime, binc, mvs), depth)
-        }
-    } else {
-        panic!("Something went wrong in go!");
-    }
+//One line of JSON, so it can be collected after every go
+#[cfg(feature = "search-statistics")]
+pub fn print_statistics(itcs: &Arc<InterThreadCommunicationSystem>) {
+    println!("{}", itcs.search_statistics.lock().unwrap().snapshot().to_json());
 }
 
-pub fn position(engine: &mut UCIEngine, cmd: &[&str], movelist: &mut movegen::MoveList) -> Vec<GameState> {
-    let mut move_index = 1;
-    match cmd[0] {
-        "fen" => {
-            let mut fen_string = String::new();
-            while move_index < cmd.len() && cmd[move_index].to_lowercase() != "moves" {
-                fen_string.push_str(cmd[move_index]);
-                fen_string.push_str(" ");
-                move_index += 1;
-            }
-            engine.internal_state = GameState::from_fen(fen_string.trim_end());
-        }
-        "startpos" => {
-            engine.internal_state = GameState::standard();
-        }
-        _ => {
-            panic!("Illegal position cmd");
-        }
-    }
-    let mut history: Vec<GameState> = vec![];
-    history.push(engine.internal_state.clone());
-    if move_index < cmd.len() && cmd[move_index].to_lowercase() == "moves" {
-        move_index += 1;
-        while move_index < cmd.len() {
-            //Parse the move and make it
-            let mv = cmd[move_index];
-            let (from, to, promo) = GameMove::string_to_move(mv);
-            engine.internal_state = scout_and_make_draftmove(from, to, promo, &engine.internal_state, movelist);
-            history.push(engine.internal_state.clone());
-            move_index += 1;
-        }
-    }
-    history.pop();
-    history
-}
-
-pub fn scout_and_make_draftmove(from: usize, to: usize, promo_pieces: Option<PieceType>, game_state: &GameState, movelist: &mut movegen::MoveList) -> GameState {
-    movegen::generate_moves(&game_state, false, movelist);
-    for gmv in movelist.move_list.iter() {
-        let mv = gmv.0;
-        if mv.from as usize == from && mv.to as usize == to {
-            if let GameMoveType::Promotion(ps, _) = mv.move_type {
-                match promo_pieces {
-                    Some(piece) => {
-                        if piece != ps {
-                            continue;
-                        }
-                    }
-                    None => {
-                        continue;
-                    }
-                }
-            }
-            return make_move(&game_state, mv);
-        }
-    }
-    panic!("Invalid move; not found in list!");
+#[cfg(not(feature = "search-statistics"))]
+pub fn print_statistics(_itcs: &Arc<InterThreadCommunicationSystem>) {
+    println!("info string Search statistics are not compiled in, build with --features search-statistics");
 }
 
 pub fn isready(itcs: &Arc<InterThreadCommunicationSystem>, print_rdy: bool) {
@@ -231,95 +143,107 @@ pub fn uci(engine: &UCIEngine) {
     let options = UCIOptions::default();
     println!("option name Hash type spin default {} min {} max {}", options.hash_size, MIN_HASH_SIZE, MAX_HASH_SIZE);
     println!("option name ClearHash type button");
+    println!("option name HashFile type string default {}", engine.hash_file);
+    println!("option name SaveHash type button");
+    println!("option name LoadHash type button");
     println!("option name Threads type spin default {} min {} max {}", options.threads, MIN_THREADS, MAX_THREADS);
     println!(
         "option name MoveOverhead type spin default {} min {} max {}",
         options.move_overhead, MIN_MOVE_OVERHEAD, MAX_MOVE_OVERHEAD
     );
     println!("option name DebugSMPPrint type check default {}", options.debug_print);
+    println!("option name UCI_ShowWDL type check default {}", options.show_wdl);
     println!(
-        "option name SMPSkipRatio type spin default {} min {} max {}",
-        options.skip_ratio, MIN_SKIP_RATIO, MAX_SKIP_RATIO
+        "option name CurrMoveDelay type spin default {} min {} max {}",
+        options.currmove_delay, MIN_CURRMOVE_DELAY, MAX_CURRMOVE_DELAY
     );
     println!(
-        "option name FutilityMargin type spin default {} min {} max {}",
-        options.futility_margin, MIN_FUTILITY_MARGIN, MAX_FUTILITY_MARGIN
+        "option name SMPSkipRatio type spin default {} min {} max {}",
+        options.skip_ratio, MIN_SKIP_RATIO, MAX_SKIP_RATIO
     );
+    for parameter in SEARCH_PARAMETERS.iter() {
+        println!(
+            "option name {} type spin default {} min {} max {}",
+            parameter.name,
+            (parameter.get)(&options.search_parameters),
+            parameter.min,
+            parameter.max
+        );
+    }
+    println!("option name Contempt type spin default {} min {} max {}", options.contempt, MIN_CONTEMPT, MAX_CONTEMPT);
+    println!("option name AnalysisContempt type check default {}", options.analysis_contempt);
     println!("uciok");
 }
 
-pub fn setoption(cmd: &[&str], itcs: &Arc<InterThreadCommunicationSystem>) {
-    let mut index = 0;
-    while index < cmd.len() {
-        let arg = cmd[index];
-        match arg.to_lowercase().as_str() {
-            "hash" => {
-                let num = cmd[index + 2].parse::<usize>().expect("Invalid Hash value!");
-                itcs.uci_options.write().unwrap().hash_size = num;
-                let num_threads = itcs.get_current_uci_options().threads;
-                *itcs.cache() = Cache::with_size_threaded(num, num_threads);
-                println!("info String Succesfully set Hash to {}", num);
-                return;
-            }
-            "clearhash" => {
-                itcs.cache().clear_threaded(itcs.get_current_uci_options().threads);
-                println!("info String Succesfully cleared hash!");
-                return;
-            }
-            "threads" => {
-                let num = cmd[index + 2].parse::<usize>().expect("Invalid Threads value!");
-                InterThreadCommunicationSystem::update_thread_count(&itcs, num);
-                println!("info String Succesfully set Threads to {}", num);
-                return;
-            }
-            "moveoverhead" => {
-                let num = cmd[index + 2].parse::<u64>().expect("Invalid MoveOverhead value!");
-                itcs.uci_options.write().unwrap().move_overhead = num;
-                println!("info String Succesfully set MoveOverhad to {}", num);
-                return;
-            }
-            "debugsmpprint" => {
-                let val = cmd[index + 2].parse::<bool>().expect("Invalid DebugSMPPrint value!");
-                itcs.uci_options.write().unwrap().debug_print = val;
-                println!("info String Succesfully set DebugSMPPrint to {}", val);
-                return;
-            }
-            "smpskipratio" => {
-                let num = cmd[index + 2].parse::<usize>().expect("Invalid SMPSkipRatio value!");
-                itcs.uci_options.write().unwrap().skip_ratio = num;
-                println!("info String Succesfully set SMPSkipRatio to {}", num);
-                return;
-            }
-            "futilitymargin" => {
-                let num = cmd[index + 2].parse::<i16>().expect("Invalid FutilityMargin value!");
-                itcs.uci_options.write().unwrap().futility_margin = num;
-                println!("info String Succesfully set FutilityMargin to {}", num);
-                return;
-            }
-            "lmr_a" => {
-                let num = cmd[index + 2].parse::<f32>().unwrap();
-                itcs.uci_options.write().unwrap().lmr_a = num;
-                return;
-            }
-            "lmr_b" => {
-                let num = cmd[index + 2].parse::<f32>().unwrap();
-                itcs.uci_options.write().unwrap().lmr_b = num;
-                return;
-            }
-            "lmr_c" => {
-                let num = cmd[index + 2].parse::<f32>().unwrap();
-                itcs.uci_options.write().unwrap().lmr_c = num;
-                return;
-            }
-            "lmr_d" => {
-                let num = cmd[index + 2].parse::<f32>().unwrap();
-                itcs.uci_options.write().unwrap().lmr_d = num;
-                return;
-            }
-            _ => {
-                index += 1;
-            }
+pub fn setoption(engine: &mut UCIEngine, option: EngineOption, itcs: &Arc<InterThreadCommunicationSystem>) {
+    match option {
+        EngineOption::Hash(num) => {
+            itcs.uci_options.write().unwrap().hash_size = num;
+            let num_threads = itcs.get_current_uci_options().threads;
+            *itcs.cache() = Cache::with_size_threaded(num, num_threads);
+            println!("info String Succesfully set Hash to {}", num);
+        }
+        EngineOption::ClearHash => {
+            itcs.cache().clear_threaded(itcs.get_current_uci_options().threads);
+            println!("info String Succesfully cleared hash!");
+        }
+        EngineOption::HashFile(file) => {
+            engine.hash_file = file;
+            println!("info String Succesfully set HashFile to {}", engine.hash_file);
+        }
+        EngineOption::SaveHash => match itcs.cache().save_to(&engine.hash_file) {
+            Ok(()) => println!("info String Succesfully saved hash to {}", engine.hash_file),
+            Err(e) => println!("info String Could not save hash to {}: {}", engine.hash_file, e),
+        },
+        EngineOption::LoadHash => match Cache::load_from(&engine.hash_file, itcs.get_current_uci_options().threads) {
+            Ok(cache) => {
+                itcs.uci_options.write().unwrap().hash_size = cache.size_mb();
+                println!("info String Succesfully loaded hash of size {} from {}", cache.size_mb(), engine.hash_file);
+                *itcs.cache() = cache;
+            }
+            Err(e) => println!("info String Could not load hash from {}: {}", engine.hash_file, e),
+        },
+        EngineOption::Threads(num) => {
+            InterThreadCommunicationSystem::update_thread_count(itcs, num);
+            println!("info String Succesfully set Threads to {}", num);
+        }
+        EngineOption::MoveOverhead(num) => {
+            itcs.uci_options.write().unwrap().move_overhead = num;
+            println!("info String Succesfully set MoveOverhad to {}", num);
+        }
+        EngineOption::DebugSMPPrint(val) => {
+            itcs.uci_options.write().unwrap().debug_print = val;
+            println!("info String Succesfully set DebugSMPPrint to {}", val);
+        }
+        EngineOption::ShowWDL(val) => {
+            itcs.uci_options.write().unwrap().show_wdl = val;
+            println!("info String Succesfully set UCI_ShowWDL to {}", val);
+        }
+        EngineOption::CurrMoveDelay(num) => {
+            itcs.uci_options.write().unwrap().currmove_delay = num;
+            println!("info String Succesfully set CurrMoveDelay to {}", num);
+        }
+        EngineOption::SMPSkipRatio(num) => {
+            itcs.uci_options.write().unwrap().skip_ratio = num;
+            println!("info String Succesfully set SMPSkipRatio to {}", num);
+        }
+        EngineOption::SearchParameter(index, num) => {
+            let parameter = &SEARCH_PARAMETERS[index];
+            (parameter.set)(&mut itcs.uci_options.write().unwrap().search_parameters, num);
+            println!("info String Succesfully set {} to {}", parameter.name, num);
+        }
+        EngineOption::Contempt(num) => {
+            itcs.uci_options.write().unwrap().contempt = num;
+            println!("info String Succesfully set Contempt to {}", num);
+        }
+        EngineOption::AnalysisContempt(val) => {
+            itcs.uci_options.write().unwrap().analysis_contempt = val;
+            println!("info String Succesfully set AnalysisContempt to {}", val);
         }
+        EngineOption::LmrA(num) => itcs.uci_options.write().unwrap().lmr_a = num,
+        EngineOption::LmrB(num) => itcs.uci_options.write().unwrap().lmr_b = num,
+        EngineOption::LmrC(num) => itcs.uci_options.write().unwrap().lmr_c = num,
+        EngineOption::LmrD(num) => itcs.uci_options.write().unwrap().lmr_d = num,
     }
 }
 